@@ -0,0 +1,577 @@
+//! Counterexample export.
+
+mikino_api::prelude!();
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use check::cexs::Cex;
+use expr::Cst;
+use trans::Sys;
+
+/// Engine a counterexample comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Base case of induction.
+    Base,
+    /// Step case of induction.
+    Step,
+    /// Bounded model-checking.
+    Bmc,
+}
+impl Origin {
+    /// String representation, used in file names.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Base => "base",
+            Self::Step => "step",
+            Self::Bmc => "bmc",
+        }
+    }
+}
+
+/// Counterexample export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Comma-separated values.
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+}
+impl Format {
+    /// Legal string representations of formats.
+    pub const KEYS: [&'static str; 2] = ["csv", "tsv"];
+
+    /// Format from a string.
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            _ => None,
+        }
+    }
+
+    /// Cell separator.
+    pub fn sep(self) -> char {
+        match self {
+            Self::Csv => ',',
+            Self::Tsv => '\t',
+        }
+    }
+
+    /// File extension.
+    pub fn ext(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+        }
+    }
+
+    /// Escapes a cell if needed.
+    pub fn cell(self, s: &str) -> String {
+        match self {
+            Self::Csv if s.contains([',', '"', '\n']) => {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            }
+            Self::Csv => s.into(),
+            Self::Tsv => s.replace(['\t', '\n'], " "),
+        }
+    }
+}
+
+/// Counterexample layouts on the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One block of `var = value` lines per step.
+    Blocks,
+    /// Variables as rows and steps as columns.
+    Table,
+}
+impl Layout {
+    /// Legal string representations of layouts.
+    pub const KEYS: [&'static str; 2] = ["blocks", "table"];
+
+    /// Layout from a string.
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "blocks" => Some(Self::Blocks),
+            "table" => Some(Self::Table),
+            _ => None,
+        }
+    }
+}
+
+/// Spreadsheet-friendly representation of a constant.
+///
+/// Unlike `Cst`'s `Display` implementation, which produces SMT-LIB-style `(- 7)` and `(/ 1 3)`,
+/// this produces `-7` and `1/3`.
+pub fn cst_to_cell(cst: &Cst) -> String {
+    match cst {
+        Cst::B(b) => b.to_string(),
+        Cst::I(i) => i.to_string(),
+        Cst::R(r) if r.is_integer() => r.numer().to_string(),
+        Cst::R(r) => format!("{}/{}", r.numer(), r.denom()),
+    }
+}
+
+/// Turns a candidate name into something that can safely appear in a file name.
+pub fn file_friendly(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// File name stem for the counterexamples of a candidate of a system.
+///
+/// This is [`file_friendly`] unless another candidate of `sys` has the same file-friendly
+/// version, in which case a hash of the actual name is appended so that the counterexamples of
+/// the two candidates do not overwrite each other.
+pub fn file_stem(sys: &Sys, candidate: &str) -> String {
+    let stem = file_friendly(candidate);
+    let clash = sys
+        .po_s()
+        .keys()
+        .any(|name| name != candidate && file_friendly(name) == stem);
+    if clash {
+        // FNV-1a, stable across runs and compiler versions unlike std's hasher.
+        let hash = candidate.bytes().fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+        format!("{}-{:08x}", stem, hash)
+    } else {
+        stem
+    }
+}
+
+/// Creates (or truncates) a file in a directory, creating the directory if needed.
+fn create_file(dir: &str, path: &Path) -> Res<std::fs::File> {
+    use std::fs::OpenOptions;
+
+    if !Path::new(dir).exists() {
+        std::fs::create_dir_all(dir).chain_err(|| {
+            format!(
+                "while recursively creating counterexample directory `{}`",
+                dir
+            )
+        })?
+    }
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .chain_err(|| format!("while opening file `{}` in write mode", path.display()))
+}
+
+/// Writes counterexamples in a directory, one file per counterexample.
+#[derive(Debug, Clone)]
+pub struct Dump {
+    /// Format of the files.
+    pub format: Format,
+    /// Target directory.
+    pub dir: String,
+}
+impl Dump {
+    /// Constructor.
+    pub fn new(format: Format, dir: impl Into<String>) -> Self {
+        Self {
+            format,
+            dir: dir.into(),
+        }
+    }
+
+    /// Path of the file for a counterexample, see [`file_stem`].
+    pub fn path_of(&self, sys: &Sys, candidate: &str, origin: Origin) -> PathBuf {
+        let mut path = PathBuf::from(&self.dir);
+        path.push(format!(
+            "{}.{}.{}",
+            file_stem(sys, candidate),
+            origin.as_str(),
+            self.format.ext(),
+        ));
+        path
+    }
+
+    /// Writes a counterexample, returns the path of the file written.
+    ///
+    /// The file has a header row `step,<var_1>,...,<var_n>` with one column per state variable
+    /// of the system, and one row per step of the trace. Steps are relative to the beginning of
+    /// the trace, *i.e.* `0` is `k` and `1` is `k + 1` for step counterexamples.
+    pub fn write(&self, sys: &Sys, candidate: &str, cex: &Cex, origin: Origin) -> Res<PathBuf> {
+        let path = self.path_of(sys, candidate, origin);
+        let mut file = create_file(&self.dir, &path)?;
+        self.write_to(&mut file, sys, cex).chain_err(|| {
+            format!(
+                "while writing counterexample for `{}` to file `{}`",
+                candidate,
+                path.display()
+            )
+        })?;
+        Ok(path)
+    }
+
+    /// Writes a counterexample to a writer.
+    pub fn write_to(&self, w: &mut impl Write, sys: &Sys, cex: &Cex) -> Res<()> {
+        let (format, sep) = (self.format, self.format.sep());
+        let vars: Vec<_> = sys.decls().all().collect();
+
+        write!(w, "step")?;
+        for var in &vars {
+            write!(w, "{}{}", sep, format.cell(var.id()))?;
+        }
+        writeln!(w)?;
+
+        for (step, values) in &cex.trace {
+            write!(w, "{}", step)?;
+            for var in &vars {
+                let cell = values.get(var).map(cst_to_cell).unwrap_or_default();
+                write!(w, "{}{}", sep, format.cell(&cell))?;
+            }
+            writeln!(w)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// Hsmt identifier of a state variable at some step.
+///
+/// Unquoted identifier `v` yields `v_at_<step>`, quoted identifier `|v|` yields `|v@<step>|`.
+pub fn unrolled_id(id: &str, step: Unroll) -> String {
+    if id.len() >= 2 && id.starts_with('|') && id.ends_with('|') {
+        format!("|{}@{}|", &id[1..id.len() - 1], step)
+    } else {
+        format!("{}_at_{}", id, step)
+    }
+}
+
+/// Hsmt constraint forcing an unrolled variable to have some value.
+///
+/// Integer values for rational variables are turned into rationals.
+fn value_constraint(id: &str, typ: Typ, cst: &Cst) -> String {
+    match cst {
+        Cst::I(i) if typ == Typ::Rat => {
+            value_constraint(id, typ, &Cst::R(Rat::from_integer(i.clone())))
+        }
+        Cst::B(true) => id.into(),
+        Cst::B(false) => format!("{} {}", expr::Op::Not.hsmt_str()[0], id),
+        // Multiplied through if the value has no finite decimal representation.
+        _ => crate::hsmt::expr_to_hsmt(
+            &expr::PExpr::App {
+                op: expr::Op::Eq,
+                args: vec![expr::PExpr::Var(id), expr::PExpr::Cst(cst.clone())],
+            },
+            |id| id.to_string(),
+        ),
+    }
+}
+
+/// Writes counterexamples as hsmt scripts in a directory, one script per counterexample.
+///
+/// Scripts declare the unrolled state variables, assert the system's constraints and the concrete
+/// values of the counterexample, and check sat. They can be replayed with `mikino script`.
+#[derive(Debug, Clone)]
+pub struct ScriptDump {
+    /// Target directory.
+    pub dir: String,
+}
+impl ScriptDump {
+    /// Constructor.
+    pub fn new(dir: impl Into<String>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of the script for a counterexample, see [`file_stem`].
+    pub fn path_of(&self, sys: &Sys, candidate: &str, origin: Origin) -> PathBuf {
+        let mut path = PathBuf::from(&self.dir);
+        path.push(format!(
+            "{}.{}.rs",
+            file_stem(sys, candidate),
+            origin.as_str()
+        ));
+        path
+    }
+
+    /// Writes a counterexample script, returns the path of the file written.
+    pub fn write(&self, sys: &Sys, candidate: &str, cex: &Cex, origin: Origin) -> Res<PathBuf> {
+        let path = self.path_of(sys, candidate, origin);
+        let mut file = create_file(&self.dir, &path)?;
+        self.write_to(&mut file, sys, candidate, cex, origin)
+            .chain_err(|| {
+                format!(
+                    "while writing counterexample script for `{}` to file `{}`",
+                    candidate,
+                    path.display()
+                )
+            })?;
+        Ok(path)
+    }
+
+    /// Writes a counterexample script to a writer.
+    ///
+    /// Base and BMC counterexamples start from the initial states. Step counterexamples do not,
+    /// instead the candidate is assumed to hold on all steps but the last one.
+    pub fn write_to(
+        &self,
+        w: &mut impl Write,
+        sys: &Sys,
+        candidate: &str,
+        cex: &Cex,
+        origin: Origin,
+    ) -> Res<()> {
+        use crate::hsmt::expr_to_hsmt;
+
+        let def = sys.po_s().get(candidate).ok_or_else(|| {
+            format!(
+                "failed to retrieve definition for candidate `{}`",
+                candidate,
+            )
+        })?;
+        let first = cex.trace.keys().next().cloned().unwrap_or(0);
+        let last = cex.trace.keys().next_back().cloned().unwrap_or(first);
+        let at = |step: Unroll| move |var: &expr::Var| unrolled_id(var.id(), step);
+
+        writeln!(
+            w,
+            "//! Counterexample for candidate `{}`, found by {}.",
+            candidate,
+            match origin {
+                Origin::Base => "the base check of induction",
+                Origin::Step => "the step check of induction",
+                Origin::Bmc => "BMC",
+            }
+        )?;
+        writeln!(w, "//!")?;
+        if origin == Origin::Step {
+            writeln!(
+                w,
+                "//! Step `{}` stands for `k`, step `{}` for `k + 1`, *etc.*",
+                first,
+                first + 1
+            )?;
+            writeln!(w, "//!")?;
+        }
+        writeln!(
+            w,
+            "//! Checks the counterexample is a legal trace of the system, replay with"
+        )?;
+        writeln!(w, "//! `mikino script <this file>`.")?;
+
+        writeln!(w)?;
+        writeln!(w, "/// State variables, one version per step.")?;
+        writeln!(w, "vars {{")?;
+        for step in first..=last {
+            for var in sys.decls().all() {
+                writeln!(w, "    {}: {},", unrolled_id(var.id(), step), var.typ())?;
+            }
+        }
+        writeln!(w, "}}")?;
+
+        if origin != Origin::Step {
+            writeln!(w)?;
+            writeln!(w, "/// Initial predicate.")?;
+            writeln!(w, "assert {{")?;
+            writeln!(w, "    {},", expr_to_hsmt(sys.init(), at(first)))?;
+            writeln!(w, "}}")?;
+        }
+
+        for step in first..last {
+            writeln!(w)?;
+            writeln!(
+                w,
+                "/// Transition relation from step {} to step {}.",
+                step,
+                step + 1
+            )?;
+            writeln!(w, "assert {{")?;
+            let trans = expr_to_hsmt(sys.trans(), |svar: &expr::SVar| {
+                let step = if svar.is_next() { step + 1 } else { step };
+                unrolled_id(svar.id(), step)
+            });
+            writeln!(w, "    {},", trans)?;
+            writeln!(w, "}}")?;
+        }
+
+        writeln!(w)?;
+        writeln!(w, "/// Candidate `{}`.", candidate)?;
+        writeln!(w, "assert {{")?;
+        if origin == Origin::Step {
+            for step in first..last {
+                writeln!(w, "    // holds at step {}", step)?;
+                writeln!(w, "    {},", expr_to_hsmt(def, at(step)))?;
+            }
+        }
+        writeln!(w, "    // falsified at step {}", last)?;
+        writeln!(
+            w,
+            "    {} {},",
+            expr::Op::Not.hsmt_str()[0],
+            expr_to_hsmt(def, at(last))
+        )?;
+        writeln!(w, "}}")?;
+
+        for (step, values) in &cex.trace {
+            if values.is_empty() {
+                continue;
+            }
+            writeln!(w)?;
+            writeln!(w, "/// Values at step {}.", step)?;
+            writeln!(w, "assert {{")?;
+            for (var, cst) in values {
+                let id = unrolled_id(var.id(), *step);
+                writeln!(w, "    {},", value_constraint(&id, var.typ(), cst))?;
+            }
+            writeln!(w, "}}")?;
+        }
+
+        writeln!(w)?;
+        writeln!(w, "if check_sat!() {{")?;
+        writeln!(
+            w,
+            "    echo!(\"counterexample confirmed, it is a legal trace of the system\")"
+        )?;
+        writeln!(w, "    get_model!()")?;
+        writeln!(w, "}} else {{")?;
+        writeln!(
+            w,
+            "    echo!(\"counterexample rejected, it is not a legal trace of the system\")"
+        )?;
+        writeln!(w, "}} otherwise {{")?;
+        writeln!(
+            w,
+            "    echo!(\"solver could not decide whether the counterexample is legal\")"
+        )?;
+        writeln!(w, "}}")?;
+
+        w.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expr::Var;
+
+    fn sys(candidates: &[&str]) -> Sys {
+        let candidates: Vec<_> = candidates
+            .iter()
+            .map(|name| format!("\"{}\": x ≥ 0,", name))
+            .collect();
+        let txt = format!(
+            "svars {{ x: int }} init {{ x = 0 }} trans {{ 'x = x + 1 }} candidates {{ {} }}",
+            candidates.join(" ")
+        );
+        parse::trans(&txt).unwrap()
+    }
+
+    fn cex() -> Cex {
+        let mut cex = Cex::new();
+        let (x, r, b) = (
+            Var::new("x", Typ::Int),
+            Var::new("r", Typ::Rat),
+            Var::new("|a,\"b\"|", Typ::Bool),
+        );
+        cex.insert(0, x.clone(), Cst::I((-7).into())).unwrap();
+        cex.insert(0, r.clone(), Cst::R(Rat::new(1.into(), 3.into())))
+            .unwrap();
+        cex.insert(0, b, Cst::B(true)).unwrap();
+        cex.insert(1, x, Cst::I(2.into())).unwrap();
+        cex.insert(1, r, Cst::R(Rat::new(4.into(), 2.into())))
+            .unwrap();
+        cex
+    }
+
+    fn dump(format: Format) -> String {
+        let sys = parse::trans(
+            "svars { x: int, r: rat, |a,\"b\"|: bool } init { x = 0 } trans { 'x = x } \
+            candidates { \"p\": x ≥ 0 }",
+        )
+        .unwrap();
+        let mut bytes = vec![];
+        Dump::new(format, "cex")
+            .write_to(&mut bytes, &sys, &cex())
+            .unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            dump(Format::Csv),
+            "step,r,x,\"|a,\"\"b\"\"|\"\n0,1/3,-7,true\n1,2,2,\n",
+        );
+    }
+
+    #[test]
+    fn tsv() {
+        assert_eq!(
+            dump(Format::Tsv),
+            "step\tr\tx\t|a,\"b\"|\n0\t1/3\t-7\ttrue\n1\t2\t2\t\n",
+        );
+    }
+
+    #[test]
+    fn cells() {
+        assert_eq!(Format::Csv.cell("plain"), "plain");
+        assert_eq!(Format::Csv.cell("a\nb"), "\"a\nb\"");
+        assert_eq!(Format::Tsv.cell("a\tb\nc,\"d\""), "a b c,\"d\"");
+    }
+
+    #[test]
+    fn value_constraints() {
+        let third = Cst::R(Rat::new(1.into(), 3.into()));
+        assert_eq!(
+            value_constraint("x_at_0", Typ::Rat, &third),
+            "((3.0 * x_at_0) = 1.0)"
+        );
+        assert_eq!(
+            value_constraint("x_at_0", Typ::Rat, &Cst::I((-7).into())),
+            "(x_at_0 = (- 7.0))"
+        );
+        assert_eq!(
+            value_constraint("b_at_1", Typ::Bool, &Cst::B(false)),
+            "¬ b_at_1"
+        );
+    }
+
+    #[test]
+    fn stems_without_clash() {
+        let sys = sys(&["x is positive", "x-nat"]);
+        assert_eq!(file_stem(&sys, "x is positive"), "x_is_positive");
+        assert_eq!(file_stem(&sys, "x-nat"), "x-nat");
+    }
+
+    #[test]
+    fn stems_with_clash() {
+        let sys = sys(&["a b", "a_b", "a.b"]);
+        let stems: Vec<_> = ["a b", "a_b", "a.b"]
+            .iter()
+            .map(|name| file_stem(&sys, name))
+            .collect();
+        for (idx, stem) in stems.iter().enumerate() {
+            assert!(stem.starts_with("a_b-"), "unexpected stem `{}`", stem);
+            assert!(!stems[idx + 1..].contains(stem), "stem `{}` clashes", stem);
+        }
+        assert_eq!(stems[0], file_stem(&sys, "a b"));
+
+        let dump = Dump::new(Format::Csv, "cex");
+        assert_ne!(
+            dump.path_of(&sys, "a b", Origin::Base),
+            dump.path_of(&sys, "a_b", Origin::Base),
+        );
+        let scripts = ScriptDump::new("cex");
+        assert_ne!(
+            scripts.path_of(&sys, "a b", Origin::Step),
+            scripts.path_of(&sys, "a.b", Origin::Step),
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! BMC checkpoints.
+//!
+//! With `--checkpoint FILE`, BMC saves its progress after each depth: the range of depths checked
+//! and the status of each candidate, with the counterexamples of the falsified ones. The range
+//! does not start at `0` with `--bmc_min`. With `--resume FILE`, BMC continues after the last depth
+//! of a checkpoint, candidates the checkpoint falsifies keep their counterexample. The fingerprint
+//! of the system identifies the system the checkpoint is for, whatever its file and layout.
+//!
+//! Checkpoints are s-expressions, strings are SMT-LIB string literals:
+//!
+//! ```text
+//! ; mikino BMC checkpoint
+//! (system "demo.mkn")
+//! (fingerprint 9f1c2a4e5d6b7c80)
+//! (first_depth 0)
+//! (depth 12)
+//! (okay "cnt is positive")
+//! (falsified "cnt is not -7"
+//!   (0 ("cnt" Int (- 7)) ("reset" Bool false))
+//! )
+//! ```
+
+mikino_api::prelude!();
+
+use std::{collections::BTreeMap as Map, io::Write, path::Path};
+
+use check::{cexs::Cex, CheckRes};
+use expr::{Cst, PExpr, Var};
+use trans::Sys;
+
+use crate::import::{sexp, vmt};
+
+/// A BMC checkpoint.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// System the checkpoint is for, as given on the command line.
+    pub system: String,
+    /// Fingerprint of the system, see [`fingerprint`].
+    pub fingerprint: u64,
+    /// First depth checked, all depths from `first_depth` to `depth` were checked.
+    pub first_depth: Unroll,
+    /// Last depth checked.
+    pub depth: Unroll,
+    /// Candidates not falsified up to `depth`.
+    pub okay: Vec<String>,
+    /// Falsified candidates and their counterexample.
+    pub falsified: Vec<(String, Cex)>,
+}
+
+/// Fingerprint of a system: the 64-bit FNV-1a hash of its pretty-printed form.
+///
+/// Unlike the hashers of `std`, it is stable across versions of Rust.
+pub fn fingerprint(sys: &Sys) -> u64 {
+    sys.to_ml_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// String literal.
+fn string(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+/// Parses a string literal.
+fn of_string(sexp: &sexp::Sexp) -> Res<String> {
+    let atom = sexp.atom("a string literal")?;
+    match atom.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(s) => Ok(s.replace("\"\"", "\"")),
+        None => bail!(
+            "line {}: expected a string literal, found `{}`",
+            sexp.line(),
+            atom
+        ),
+    }
+}
+
+/// SMT-LIB sort of a type.
+fn sort(typ: Typ) -> &'static str {
+    match typ {
+        Typ::Bool => "Bool",
+        Typ::Int => "Int",
+        Typ::Rat => "Real",
+    }
+}
+
+/// Writes a checkpoint for a BMC run on `system` that checked all depths from `first_depth` to
+/// `depth`.
+///
+/// The checkpoint is written to a temporary file first, so that an interruption cannot leave a
+/// partial checkpoint behind.
+pub fn write(
+    path: &str,
+    system: &str,
+    fingerprint: u64,
+    first_depth: Unroll,
+    depth: Unroll,
+    res: &CheckRes,
+) -> Res<()> {
+    let tmp = format!("{}.tmp", path);
+    let mut file = std::fs::File::create(&tmp)
+        .chain_err(|| format!("while opening file `{}` in write mode", tmp))?;
+    writeln!(file, "; mikino BMC checkpoint")?;
+    writeln!(file, "(system {})", string(system))?;
+    writeln!(file, "(fingerprint {:016x})", fingerprint)?;
+    writeln!(file, "(first_depth {})", first_depth)?;
+    writeln!(file, "(depth {})", depth)?;
+    for candidate in res.okay.iter() {
+        writeln!(file, "(okay {})", string(candidate))?;
+    }
+    for (candidate, cex) in res.cexs.iter() {
+        writeln!(file, "(falsified {}", string(candidate))?;
+        for (step, values) in &cex.trace {
+            write!(file, "  ({}", step)?;
+            for (var, cst) in values {
+                write!(file, " ({} {} {})", string(var.id()), sort(var.typ()), cst)?;
+            }
+            writeln!(file, ")")?;
+        }
+        for (desc, val) in &cex.unexpected {
+            writeln!(file, "  (unexpected {} {})", string(desc), string(val))?;
+        }
+        writeln!(file, ")")?;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)
+        .chain_err(|| format!("while moving checkpoint `{}` to `{}`", tmp, path))?;
+    Ok(())
+}
+
+/// Reads a checkpoint.
+pub fn read(path: impl AsRef<Path>) -> Res<Checkpoint> {
+    let path = path.as_ref();
+    let txt = std::fs::read_to_string(path)
+        .chain_err(|| format!("while reading checkpoint `{}`", path.display()))?;
+    parse(&txt).chain_err(|| format!("while parsing checkpoint `{}`", path.display()))
+}
+
+/// Parses a checkpoint.
+pub fn parse(txt: &str) -> Res<Checkpoint> {
+    let mut system = None;
+    let mut fingerprint = None;
+    let mut first_depth = None;
+    let mut depth = None;
+    let mut okay = vec![];
+    let mut falsified = vec![];
+    for entry in sexp::parse(txt)? {
+        let list = entry.list("a checkpoint entry")?;
+        match list {
+            [kw, val] if kw.as_atom() == Some("system") => system = Some(of_string(val)?),
+            [kw, val] if kw.as_atom() == Some("fingerprint") => {
+                let val = val.atom("a fingerprint")?;
+                let val = u64::from_str_radix(val, 16)
+                    .map_err(|_| format!("line {}: illegal fingerprint `{}`", entry.line(), val))?;
+                fingerprint = Some(val)
+            }
+            [kw, val] if kw.as_atom() == Some("first_depth") => {
+                first_depth = Some(parse_depth(val)?)
+            }
+            [kw, val] if kw.as_atom() == Some("depth") => depth = Some(parse_depth(val)?),
+            [kw, val] if kw.as_atom() == Some("okay") => okay.push(of_string(val)?),
+            [kw, candidate, steps @ ..] if kw.as_atom() == Some("falsified") => {
+                falsified.push((of_string(candidate)?, parse_cex(steps)?))
+            }
+            _ => bail!(
+                "line {}: unexpected checkpoint entry `{}`",
+                entry.line(),
+                entry
+            ),
+        }
+    }
+    let first_depth = first_depth.ok_or("no `first_depth` entry")?;
+    let depth = depth.ok_or("no `depth` entry")?;
+    if first_depth > depth {
+        bail!("first depth {} is above last depth {}", first_depth, depth)
+    }
+    Ok(Checkpoint {
+        system: system.ok_or("no `system` entry")?,
+        fingerprint: fingerprint.ok_or("no `fingerprint` entry")?,
+        first_depth,
+        depth,
+        okay,
+        falsified,
+    })
+}
+
+/// Parses a depth.
+fn parse_depth(sexp: &sexp::Sexp) -> Res<Unroll> {
+    let val = sexp.atom("a depth")?;
+    val.parse::<Unroll>()
+        .map_err(|_| format!("line {}: illegal depth `{}`", sexp.line(), val).into())
+}
+
+/// Parses the steps of a counterexample.
+fn parse_cex(steps: &[sexp::Sexp]) -> Res<Cex> {
+    let mut cex = Cex::new();
+    for step in steps {
+        match step.list("a counterexample step")? {
+            [kw, desc, val] if kw.as_atom() == Some("unexpected") => {
+                cex.insert_unexpected(of_string(desc)?, of_string(val)?)?
+            }
+            [idx, values @ ..] => {
+                let idx = idx.atom("a step index")?;
+                let idx = idx
+                    .parse::<Unroll>()
+                    .map_err(|_| format!("line {}: illegal step `{}`", step.line(), idx))?;
+                for value in values {
+                    let (id, typ, cst) = match value.list("a variable value")? {
+                        [id, typ, cst] => (of_string(id)?, typ, cst),
+                        _ => bail!("line {}: illegal variable value", value.line()),
+                    };
+                    let typ = match typ.as_atom() {
+                        Some("Bool") => Typ::Bool,
+                        Some("Int") => Typ::Int,
+                        Some("Real") => Typ::Rat,
+                        _ => bail!("line {}: unknown sort `{}`", typ.line(), typ),
+                    };
+                    let cst = match vmt::term(cst, &Map::new())? {
+                        PExpr::Cst(Cst::I(i)) if typ == Typ::Rat => Cst::R(Rat::from_integer(i)),
+                        PExpr::Cst(cst) => cst,
+                        _ => bail!("line {}: expected a constant, found `{}`", cst.line(), cst),
+                    };
+                    cex.insert(idx, Var::new(id, typ), cst)?
+                }
+            }
+            [] => bail!("line {}: empty counterexample step", step.line()),
+        }
+    }
+    Ok(cex)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn demo() -> Sys {
+        crate::import::parse("demo.mkn", mikino_api::TRANS_DEMO.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let sys = demo();
+        let mut res = CheckRes::new(&sys);
+        let falsified = *res.okay.iter().next().unwrap();
+        res.okay.remove(falsified);
+        let mut cex = Cex::new();
+        cex.insert(0, Var::new("cnt", Typ::Int), Cst::I((-7).into()))
+            .unwrap();
+        cex.insert(0, Var::new("a \"b\"", Typ::Bool), Cst::B(false))
+            .unwrap();
+        cex.insert(
+            1,
+            Var::new("r", Typ::Rat),
+            Cst::R(Rat::new((-1).into(), 3.into())),
+        )
+        .unwrap();
+        cex.insert_unexpected("div0", "(- 1)").unwrap();
+        res.cexs.insert(falsified, cex.clone());
+
+        let mut path = std::env::temp_dir();
+        path.push(format!("mikino-checkpoint-test-{}", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        write(&path, "demo.mkn", fingerprint(&sys), 2, 7, &res).unwrap();
+        let checkpoint = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let checkpoint = checkpoint.unwrap();
+
+        assert_eq!(checkpoint.system, "demo.mkn");
+        assert_eq!(checkpoint.fingerprint, fingerprint(&sys));
+        assert_eq!((checkpoint.first_depth, checkpoint.depth), (2, 7));
+        let okay: Vec<_> = res.okay.iter().map(|s| s.to_string()).collect();
+        assert_eq!(checkpoint.okay, okay);
+        assert_eq!(checkpoint.falsified.len(), 1);
+        let (name, read_cex) = &checkpoint.falsified[0];
+        assert_eq!(name, falsified);
+        assert_eq!(read_cex.trace, cex.trace);
+        assert_eq!(read_cex.unexpected, cex.unexpected);
+    }
+
+    #[test]
+    fn fingerprint_ignores_layout() {
+        let sys = |txt: &str| crate::import::parse("s.mkn", txt.as_bytes()).unwrap();
+        let spaced = sys(
+            "svars {\n    // counter\n    cnt: int,\n}\ninit { cnt >= 0 }\ntrans { 'cnt = cnt + 1 }\n\
+             candidates { \"pos\": cnt >= 0 }",
+        );
+        let compact = sys("svars{cnt:int}init{cnt>=0}trans{'cnt=cnt+1}candidates{\"pos\":cnt>=0}");
+        let other = sys("svars{cnt:int}init{cnt>=1}trans{'cnt=cnt+1}candidates{\"pos\":cnt>=0}");
+        assert_eq!(fingerprint(&spaced), fingerprint(&compact));
+        assert_ne!(fingerprint(&spaced), fingerprint(&other));
+    }
+
+    const HEADER: &str = "(system \"s\") (fingerprint 00000000000000ff)";
+
+    #[test]
+    fn missing_entries() {
+        assert!(parse("(system \"s\") (first_depth 0) (depth 1)").is_err());
+        assert!(parse(&format!("{} (depth 1)", HEADER)).is_err());
+        assert!(parse(&format!("{} (first_depth 0)", HEADER)).is_err());
+        let checkpoint = parse(&format!("{} (first_depth 0) (depth 1)", HEADER)).unwrap();
+        assert_eq!(checkpoint.fingerprint, 0xff);
+    }
+
+    #[test]
+    fn malformed() {
+        for body in [
+            "(first_depth 3) (depth 1)",
+            "(first_depth 0) (depth -1)",
+            "(first_depth 0) (depth 1) (unknown 2)",
+            "(first_depth 0) (depth 1) (okay unquoted)",
+            "(first_depth 0) (depth 1) (falsified \"c\" (0 (\"x\" Float 1)))",
+            "(first_depth 0) (depth 1) (falsified \"c\" (0 (\"x\" Int x)))",
+            "(first_depth 0) (depth 1) (falsified \"c\" ())",
+            "(first_depth 0) (depth 1",
+        ] {
+            assert!(parse(&format!("{} {}", HEADER, body)).is_err(), "{}", body);
+        }
+        assert!(parse("(system \"s\") (fingerprint xyz) (first_depth 0) (depth 1)").is_err());
+    }
+}
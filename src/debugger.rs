@@ -0,0 +1,673 @@
+//! Script debugger, see `mikino script --debug`.
+//!
+//! The script runner does not expose where it is in the script, nor the values of the
+//! meta-variables. So the debugger instruments the script with `echo!` markers before running it:
+//!
+//! - before each command, to pause before it runs;
+//! - at the start of each `if` branch, to report the branch taken;
+//! - after each `let`, as an `if` on the meta-variable, to learn its value.
+//!
+//! The runner's steps go through [`Debugger::handle`], which swallows the markers.
+//!
+//! The solver keeps running the script between pauses, so the model of a sat check-sat is retrieved
+//! right after it, before the script changes the assertions.
+
+mikino_api::prelude!();
+
+use std::{
+    collections::{BTreeMap as Map, BTreeSet as Set},
+    io::{BufRead, Write},
+};
+
+use mikino_api::{
+    ast::script::{Block, Command, Echo, Ite, Query},
+    expr::{Cst, Expr, MExpr, MetaVar},
+    parse::{Span, Spn},
+    script::{CheckSatResEnum, Step},
+};
+
+use crate::Run;
+
+/// Prefix of the markers' messages.
+const MARKER: &str = "\u{0}mikino-debug ";
+
+/// Debugger commands.
+pub const HELP: &str = "\
+debugger commands:
+  s, step                 runs the next command, stepping into blocks and branches
+  n, next                 runs the next command, stepping over blocks and branches
+  c, continue             runs until the next breakpoint
+  b, break <LINE>         pauses before the commands starting on line <LINE>
+  b, break check_sat      pauses before each check-sat
+  d, delete <LINE>        deletes the breakpoint on line <LINE>
+  d, delete check_sat     stops pausing before each check-sat
+  breakpoints             lists the breakpoints
+  m, meta                 shows the meta-variables and their value
+  model                   shows the model of the last check-sat
+  w, where                shows the next command
+  q, quit                 stops the script and exits with an error
+  h, help                 shows this message
+
+An empty line repeats the last command. The debugger always pauses before a `panic!`.";
+
+/// Markers the script is instrumented with.
+#[derive(Debug, Clone)]
+enum Marker {
+    /// About to run a command.
+    At {
+        /// Span of the command.
+        span: Span,
+        /// Nesting depth of the command.
+        depth: usize,
+        /// True if the command is or contains a check-sat.
+        check: bool,
+        /// True if the command is a panic.
+        panic: bool,
+    },
+    /// Entering an `if` branch.
+    Branch {
+        /// Span of the `if`.
+        span: Span,
+        /// Meta-variable the condition is, if any, otherwise it is a check-sat.
+        cnd: Option<String>,
+        /// Check-sat result leading to this branch.
+        res: CheckSatResEnum,
+    },
+    /// Value of a meta-variable.
+    Meta {
+        /// Span of the `let`.
+        span: Span,
+        /// Meta-variable.
+        name: String,
+        /// Value.
+        res: CheckSatResEnum,
+    },
+}
+
+/// A model, values and types by variable.
+type Model = Map<String, (Cst, Typ)>;
+
+/// What the runner does with a step the debugger handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handled {
+    /// The step is the runner's.
+    Runner,
+    /// The step is internal to the debugger.
+    Internal,
+    /// The user stopped the script.
+    Quit,
+}
+
+/// Debugger commands, see [`HELP`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmd {
+    /// Runs the next command.
+    Step,
+    /// Runs the next command at the same depth or lower.
+    Next,
+    /// Runs until the next breakpoint.
+    Continue,
+    /// Adds a breakpoint on a line, or before each check-sat if `None`.
+    Break(Option<usize>),
+    /// Deletes a breakpoint on a line, or before each check-sat if `None`.
+    Delete(Option<usize>),
+    /// Lists the breakpoints.
+    Breakpoints,
+    /// Shows the meta-variables.
+    Meta,
+    /// Shows the model of the last check-sat.
+    Model,
+    /// Shows the next command.
+    Where,
+    /// Stops the script.
+    Quit,
+    /// Shows the help.
+    Help,
+}
+impl Cmd {
+    /// Parses a command, `None` if the command is unknown.
+    ///
+    /// The error is the message to show when the command exists but its argument is illegal.
+    fn parse(line: &str) -> Option<Result<Self, String>> {
+        let (cmd, arg) = match line.trim().split_once(char::is_whitespace) {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (line.trim(), ""),
+        };
+        let line_or_check = |arg: &str| match arg {
+            "check_sat" => Ok(None),
+            _ => match arg.parse::<usize>() {
+                Ok(line) if line > 0 => Ok(Some(line)),
+                _ => Err(format!(
+                    "expected a line number or `check_sat`, found `{}`",
+                    arg
+                )),
+            },
+        };
+        let cmd = match (cmd, arg) {
+            ("s" | "step", "") => Self::Step,
+            ("n" | "next", "") => Self::Next,
+            ("c" | "continue", "") => Self::Continue,
+            ("b" | "break", arg) => return Some(line_or_check(arg).map(Self::Break)),
+            ("d" | "delete", arg) => return Some(line_or_check(arg).map(Self::Delete)),
+            ("breakpoints", "") => Self::Breakpoints,
+            ("m" | "meta", "") => Self::Meta,
+            ("model", "") => Self::Model,
+            ("w" | "where", "") => Self::Where,
+            ("q" | "quit", "") => Self::Quit,
+            ("h" | "help", "") => Self::Help,
+            _ => return None,
+        };
+        Some(Ok(cmd))
+    }
+}
+
+/// What to run before pausing again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// Next command.
+    Step,
+    /// Next command at this depth or lower.
+    Next(usize),
+    /// Next breakpoint.
+    Continue,
+}
+
+/// Script debugger.
+pub struct Debugger<'env> {
+    /// Run environment.
+    env: &'env Run,
+    /// Markers, a marker's message is its index.
+    markers: Vec<Marker>,
+    /// Breakpoint lines, starting at `1`.
+    breakpoints: Set<usize>,
+    /// True if the debugger pauses before check-sats.
+    break_on_check: bool,
+    /// What to run before pausing again.
+    resume: Resume,
+    /// Span of the command the debugger is paused before, if any.
+    current: Option<Span>,
+    /// Meta-variables and their value.
+    meta: Map<String, CheckSatResEnum>,
+    /// Span and result of the last check-sat.
+    last_check: Option<(Span, CheckSatResEnum)>,
+    /// Model of the last check-sat if it is sat, or the error the solver produced instead.
+    model: Option<Result<Model, String>>,
+    /// Last command, repeated on empty lines.
+    last_cmd: String,
+    /// True if the input is a terminal, prompts are only shown in this case.
+    interactive: bool,
+    /// True once the input is closed, the script then runs to completion.
+    closed: bool,
+}
+
+impl<'env> Debugger<'env> {
+    /// Instruments a script, yields the debugger for the script it returns.
+    pub fn new(env: &'env Run, script: Command<Expr, MExpr>) -> (Self, Command<Expr, MExpr>) {
+        let mut slf = Self {
+            env,
+            markers: vec![],
+            breakpoints: Set::new(),
+            break_on_check: false,
+            resume: Resume::Step,
+            current: None,
+            meta: Map::new(),
+            last_check: None,
+            model: None,
+            last_cmd: "step".into(),
+            interactive: atty::is(atty::Stream::Stdin),
+            closed: false,
+        };
+        let script = match script {
+            Command::Query(Query::Block(block)) => {
+                Command::Query(Query::Block(slf.block(block, 0)))
+            }
+            script => script,
+        };
+        if slf.interactive {
+            println!(
+                "debugging script, `{}` for the list of commands",
+                env.bold.paint("help")
+            );
+        }
+        (slf, script)
+    }
+
+    /// Command echoing a marker.
+    fn marker(&mut self, marker: Marker, span: Span) -> Command<Expr, MExpr> {
+        let msg = format!("{}{}", MARKER, self.markers.len());
+        self.markers.push(marker);
+        Command::Echo(Echo {
+            span,
+            token: "echo".into(),
+            msg,
+        })
+    }
+
+    /// Instruments a block.
+    fn block(&mut self, block: Block<Expr, MExpr>, depth: usize) -> Block<Expr, MExpr> {
+        self.block_with(block, depth, None)
+    }
+
+    /// Instruments a block, starting with the marker `first` if any.
+    fn block_with(
+        &mut self,
+        block: Block<Expr, MExpr>,
+        depth: usize,
+        first: Option<(Marker, Span)>,
+    ) -> Block<Expr, MExpr> {
+        let mut content = Vec::with_capacity(2 * block.content.len() + 1);
+        if let Some((marker, span)) = first {
+            content.push(self.marker(marker, span))
+        }
+        for cmd in block.content {
+            if let Some(span) = span_of(&cmd) {
+                let marker = Marker::At {
+                    span,
+                    depth,
+                    check: is_check(&cmd),
+                    panic: matches!(cmd, Command::Query(Query::Panic(_))),
+                };
+                content.push(self.marker(marker, span))
+            }
+            match cmd {
+                Command::MLet(mlet) => {
+                    let (name, span) = (mlet.lhs.inner.clone(), mlet.lhs.span);
+                    content.push(Command::MLet(mlet));
+                    content.push(self.reveal(name, span))
+                }
+                Command::Query(Query::Block(block)) => {
+                    content.push(Command::Query(Query::Block(self.block(block, depth + 1))))
+                }
+                Command::Query(Query::Ite(ite)) => {
+                    content.push(Command::Query(Query::Ite(self.ite(ite, depth + 1))))
+                }
+                cmd => content.push(cmd),
+            }
+        }
+        Block::new(content)
+    }
+
+    /// Instruments an `if`.
+    fn ite(&mut self, ite: Ite<Expr, MExpr>, depth: usize) -> Ite<Expr, MExpr> {
+        let Ite {
+            span,
+            cnd,
+            thn,
+            els,
+            otw,
+            ..
+        } = ite;
+        let meta = match &cnd {
+            Either::Left(meta) => Some(meta.inner.ident.clone()),
+            Either::Right(_) => None,
+        };
+        let branch = |slf: &mut Self, block, res| {
+            let marker = Marker::Branch {
+                span,
+                cnd: meta.clone(),
+                res,
+            };
+            slf.block_with(block, depth, Some((marker, span)))
+        };
+        let thn = branch(self, thn, CheckSatResEnum::True);
+        let els = branch(self, els, CheckSatResEnum::False);
+        let otw = otw.map(|otw| branch(self, otw, CheckSatResEnum::Unknown));
+        Ite::new(span, cnd, thn, els, otw)
+    }
+
+    /// `if` on a meta-variable echoing its value.
+    fn reveal(&mut self, name: String, span: Span) -> Command<Expr, MExpr> {
+        let mut branch = |res| {
+            let marker = Marker::Meta {
+                span,
+                name: name.clone(),
+                res,
+            };
+            Block::new(vec![self.marker(marker, span)])
+        };
+        let thn = branch(CheckSatResEnum::True);
+        let els = branch(CheckSatResEnum::False);
+        let otw = branch(CheckSatResEnum::Unknown);
+        let cnd = Either::Left(Spn::new(MetaVar::from(name.clone()), span));
+        Command::Query(Query::Ite(Ite::new(span, cnd, thn, els, Some(otw))))
+    }
+
+    /// Records the result of the check-sat that just ran, and its model if it is sat.
+    fn checked(&mut self, span: Span, res: CheckSatResEnum, solver: &mut SLSolver) {
+        self.last_check = Some((span, res));
+        self.model = if res == CheckSatResEnum::True {
+            let model = solver
+                .get_model()
+                .map(|model| {
+                    model
+                        .into_iter()
+                        .map(|(id, _, typ, val)| (id, (val, typ)))
+                        .collect()
+                })
+                .map_err(|e| e.to_string());
+            Some(model)
+        } else {
+            None
+        };
+    }
+
+    /// Handles a step of the runner.
+    ///
+    /// Pauses and interacts with the user before the commands it should pause before.
+    pub fn handle(&mut self, step: &Step, solver: &mut SLSolver, txt: &str) -> Res<Handled> {
+        let marker = match step {
+            Step::CheckRes(span, res) => {
+                self.checked(*span, *res, solver);
+                return Ok(Handled::Runner);
+            }
+            Step::Echo(echo) => match echo.msg.strip_prefix(MARKER) {
+                Some(idx) => {
+                    let idx: usize = idx.parse().map_err(|_| "[fatal] illegal debug marker")?;
+                    self.markers
+                        .get(idx)
+                        .cloned()
+                        .ok_or("[fatal] unknown debug marker")?
+                }
+                None => return Ok(Handled::Runner),
+            },
+            _ => return Ok(Handled::Runner),
+        };
+        match marker {
+            Marker::At {
+                span,
+                depth,
+                check,
+                panic,
+            } => {
+                let line = line_of(span, txt);
+                let pause = match self.resume {
+                    Resume::Step => true,
+                    Resume::Next(max) => depth <= max,
+                    Resume::Continue => false,
+                } || self.breakpoints.contains(&line)
+                    || (self.break_on_check && check)
+                    || panic;
+                if pause && !self.closed {
+                    if panic {
+                        println!("{} is about to panic", self.env.bold.paint("script"));
+                    }
+                    self.show(span, txt);
+                    self.current = Some(span);
+                    let quit = self.interact(txt, depth)?;
+                    self.current = None;
+                    if quit {
+                        return Ok(Handled::Quit);
+                    }
+                }
+            }
+            Marker::Branch { span, cnd, res } => {
+                let branch = match res {
+                    CheckSatResEnum::True => "then",
+                    CheckSatResEnum::False => "else",
+                    CheckSatResEnum::Timeout | CheckSatResEnum::Unknown => "otherwise",
+                };
+                let why = match cnd {
+                    Some(meta) => format!("`{}` is {}", self.env.bold.paint(meta), res_str(res)),
+                    None => {
+                        self.checked(span, res, solver);
+                        format!("its check-sat is {}", res_str(res))
+                    }
+                };
+                println!(
+                    "{}",
+                    self.env.gray.paint(format!(
+                        "line {}: taking the `{}` branch, {}",
+                        line_of(span, txt),
+                        branch,
+                        why
+                    ))
+                )
+            }
+            Marker::Meta { span, name, res } => {
+                self.checked(span, res, solver);
+                let _ = self.meta.insert(name, res);
+            }
+        }
+        Ok(Handled::Internal)
+    }
+
+    /// Shows the command at some span.
+    fn show(&self, span: Span, txt: &str) {
+        let (prev, row, _, line, next) = span.pretty_of(txt);
+        let width = (row + 2).to_string().len();
+        if let Some(prev) = prev {
+            println!("  {:>w$} | {}", row, prev, w = width);
+        }
+        println!(
+            "{} {:>w$} | {}",
+            self.env.green.paint(">"),
+            row + 1,
+            line,
+            w = width
+        );
+        if let Some(next) = next {
+            println!("  {:>w$} | {}", row + 2, next, w = width);
+        }
+    }
+
+    /// Reads and runs debugger commands until one resumes the script, returns `true` if the user
+    /// quit.
+    fn interact(&mut self, txt: &str, depth: usize) -> Res<bool> {
+        let stdin = std::io::stdin();
+        loop {
+            if self.interactive {
+                print!("{}", self.env.gray.paint("(debug) "));
+                std::io::stdout().flush()?;
+            }
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line)? == 0 {
+                // No more commands, run to completion.
+                self.closed = true;
+                return Ok(false);
+            }
+            let line = line.trim();
+            let cmd = if line.is_empty() {
+                self.last_cmd.clone()
+            } else {
+                line.to_string()
+            };
+            self.last_cmd = cmd.clone();
+            let cmd = match Cmd::parse(&cmd) {
+                Some(Ok(cmd)) => cmd,
+                Some(Err(msg)) => {
+                    println!("{}", msg);
+                    continue;
+                }
+                None => {
+                    println!(
+                        "unknown debugger command `{}`, `{}` lists the commands",
+                        line,
+                        self.env.bold.paint("help")
+                    );
+                    continue;
+                }
+            };
+            match cmd {
+                Cmd::Step => {
+                    self.resume = Resume::Step;
+                    return Ok(false);
+                }
+                Cmd::Next => {
+                    self.resume = Resume::Next(depth);
+                    return Ok(false);
+                }
+                Cmd::Continue => {
+                    self.resume = Resume::Continue;
+                    return Ok(false);
+                }
+                Cmd::Break(None) => {
+                    self.break_on_check = true;
+                    println!("pausing before each check-sat")
+                }
+                Cmd::Break(Some(line)) => {
+                    self.breakpoints.insert(line);
+                    println!("breakpoint on line {}", line)
+                }
+                Cmd::Delete(None) => {
+                    self.break_on_check = false;
+                    println!("not pausing before each check-sat anymore")
+                }
+                Cmd::Delete(Some(line)) => {
+                    if self.breakpoints.remove(&line) {
+                        println!("deleted breakpoint on line {}", line)
+                    } else {
+                        println!("no breakpoint on line {}", line)
+                    }
+                }
+                Cmd::Breakpoints => {
+                    if self.breakpoints.is_empty() && !self.break_on_check {
+                        println!("no breakpoints")
+                    }
+                    for line in &self.breakpoints {
+                        println!("line {}", line)
+                    }
+                    if self.break_on_check {
+                        println!("check-sats")
+                    }
+                }
+                Cmd::Meta => {
+                    if self.meta.is_empty() {
+                        println!("no meta-variables so far")
+                    }
+                    for (name, res) in &self.meta {
+                        println!("{}: {}", self.env.bold.paint(name), res_str(*res))
+                    }
+                }
+                Cmd::Model => self.show_model(txt),
+                Cmd::Where => {
+                    if let Some(span) = self.current {
+                        self.show(span, txt)
+                    }
+                }
+                Cmd::Quit => return Ok(true),
+                Cmd::Help => println!("{}", HELP),
+            }
+        }
+    }
+
+    /// Shows the model of the last check-sat.
+    fn show_model(&self, txt: &str) {
+        let span = match self.last_check {
+            Some((span, CheckSatResEnum::True)) => span,
+            Some((span, res)) => {
+                println!(
+                    "no model, the last check-sat (line {}) is {}",
+                    line_of(span, txt),
+                    res_str(res)
+                );
+                return;
+            }
+            None => {
+                println!("no model, no check-sat so far");
+                return;
+            }
+        };
+        match &self.model {
+            Some(Ok(model)) => {
+                let step = Step::Model {
+                    span,
+                    token: "model".into(),
+                    model: model.clone(),
+                };
+                if let Some(pretty) = step.pretty(txt, &self.env.styles, false) {
+                    println!("{}", pretty)
+                }
+            }
+            Some(Err(e)) => println!("the solver could not produce a model: {}", e),
+            None => println!("[fatal] no model for the last check-sat, please report this bug"),
+        }
+    }
+}
+
+/// Span of a command, if any.
+fn span_of(cmd: &Command<Expr, MExpr>) -> Option<Span> {
+    match cmd {
+        Command::SetOptions(c) => Some(c.span),
+        Command::Vars(c) => Some(c.span),
+        Command::MLet(c) => Some(c.lhs.span),
+        Command::Assert(c) => Some(c.span),
+        Command::Echo(c) => Some(c.span),
+        Command::GetModel(c) => Some(c.span),
+        Command::GetValues(c) => Some(c.span),
+        Command::Reset(c) => Some(c.span),
+        Command::Query(Query::Block(_)) => None,
+        Command::Query(Query::CheckSat(c)) => Some(c.span),
+        Command::Query(Query::Ite(c)) => Some(c.span),
+        Command::Query(Query::Panic(c)) => Some(c.span),
+        Command::Query(Query::Exit(c)) => Some(c.span),
+    }
+}
+
+/// True if a command runs a check-sat itself.
+fn is_check(cmd: &Command<Expr, MExpr>) -> bool {
+    match cmd {
+        Command::MLet(_) | Command::Query(Query::CheckSat(_)) => true,
+        Command::Query(Query::Ite(ite)) => ite.cnd.is_right(),
+        _ => false,
+    }
+}
+
+/// Line of a span, starting at `1`.
+fn line_of(span: Span, txt: &str) -> usize {
+    span.pretty_of(txt).1 + 1
+}
+
+/// String representation of a check-sat result for meta-variables.
+fn res_str(res: CheckSatResEnum) -> &'static str {
+    match res {
+        CheckSatResEnum::True => "sat",
+        CheckSatResEnum::False => "unsat",
+        CheckSatResEnum::Timeout | CheckSatResEnum::Unknown => "unknown or timeout",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(Cmd::parse("s"), Some(Ok(Cmd::Step)));
+        assert_eq!(Cmd::parse("  next "), Some(Ok(Cmd::Next)));
+        assert_eq!(Cmd::parse("b 12"), Some(Ok(Cmd::Break(Some(12)))));
+        assert_eq!(Cmd::parse("break   check_sat"), Some(Ok(Cmd::Break(None))));
+        assert_eq!(Cmd::parse("d 3"), Some(Ok(Cmd::Delete(Some(3)))));
+        assert_eq!(Cmd::parse("delete check_sat"), Some(Ok(Cmd::Delete(None))));
+        assert_eq!(Cmd::parse("model"), Some(Ok(Cmd::Model)));
+        assert_eq!(Cmd::parse("q"), Some(Ok(Cmd::Quit)));
+    }
+
+    #[test]
+    fn illegal_commands() {
+        for line in ["b", "b 0", "break x", "d -1"] {
+            assert!(matches!(Cmd::parse(line), Some(Err(_))), "`{}`", line)
+        }
+        for line in ["step 2", "quit now", "run", "m x", ""] {
+            assert_eq!(Cmd::parse(line), None, "`{}`", line)
+        }
+    }
+
+    #[test]
+    fn check_sats_and_lines() {
+        let txt = "vars { x: int }\nassert { x > 0 }\n\nlet ok = check_sat!();\n\
+            if check_sat!() { echo!(\"sat\") } else { exit!(1) }\n\
+            if ok { exit!(0) } else { panic!(\"no\") }\n";
+        let ast = parse::script(txt).unwrap();
+        let content = match mikino_api::script::build::doit(ast) {
+            Ok(Command::Query(Query::Block(block))) => block.content,
+            _ => panic!("expected a block of commands"),
+        };
+        let checks: Vec<_> = content.iter().map(is_check).collect();
+        assert_eq!(checks, vec![false, false, true, true, false]);
+        let lines: Vec<_> = content
+            .iter()
+            .map(|cmd| span_of(cmd).map(|span| line_of(span, txt)))
+            .collect();
+        assert_eq!(lines, vec![Some(1), Some(2), Some(4), Some(5), Some(6)]);
+    }
+}
@@ -0,0 +1,530 @@
+//! Environment diagnostics.
+//!
+//! Checks, in order, that the solver command resolves to an executable, that the solver reports a
+//! supported version, that it answers a tiny sat/unsat test, and whether the terminal supports
+//! colors. Each check yields a [`Diagnostic`], with a fix when something is wrong.
+
+mikino_api::prelude!();
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use crate::{solver::Backend, Run};
+
+/// Time the solver has to answer the smoke test.
+const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Smoke test: `x > 0` is sat, `x > 0 ∧ x < 0` is unsat, and `x > 0` is sat again after a pop.
+const SMOKE_TEST: &str = "\
+(declare-fun x () Int)
+(assert (> x 0))
+(check-sat)
+(push 1)
+(assert (< x 0))
+(check-sat)
+(pop 1)
+(check-sat)
+(exit)
+";
+
+/// Expected answers to the [`SMOKE_TEST`], with a description of the query.
+const SMOKE_TEST_ANSWERS: [(&str, &str); 3] = [
+    ("sat", "`x > 0`"),
+    ("unsat", "`x > 0 ∧ x < 0`"),
+    ("sat", "`x > 0` after popping"),
+];
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Everything is fine.
+    Ok,
+    /// Mikino works, but not as well as it could.
+    Warning,
+    /// Mikino cannot work.
+    Problem,
+    /// Check did not run because the solver command cannot be found.
+    Skipped,
+}
+
+/// Result of a check.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// What was checked.
+    pub what: &'static str,
+    /// Outcome.
+    pub status: Status,
+    /// Details.
+    pub info: String,
+    /// How to fix the problem, if any.
+    pub fix: Option<String>,
+}
+impl Diagnostic {
+    /// Successful check.
+    pub fn ok(what: &'static str, info: impl Into<String>) -> Self {
+        Self {
+            what,
+            status: Status::Ok,
+            info: info.into(),
+            fix: None,
+        }
+    }
+    /// Check that found a minor issue.
+    pub fn warning(what: &'static str, info: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            what,
+            status: Status::Warning,
+            info: info.into(),
+            fix: Some(fix.into()),
+        }
+    }
+    /// Failed check.
+    pub fn problem(what: &'static str, info: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            what,
+            status: Status::Problem,
+            info: info.into(),
+            fix: Some(fix.into()),
+        }
+    }
+    /// Check that did not run because the solver command cannot be found.
+    pub fn skipped(what: &'static str) -> Self {
+        Self {
+            what,
+            status: Status::Skipped,
+            info: "requires the solver command".into(),
+            fix: None,
+        }
+    }
+}
+
+/// Runs all the checks.
+pub fn diagnose(env: &Run) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    let backend = env.solver;
+
+    let bin = env.solver_cmd.split_whitespace().next().unwrap_or("");
+    let path = match resolve(bin) {
+        Some(path) => {
+            res.push(Diagnostic::ok(
+                "solver command",
+                format!(
+                    "{} command `{}` is `{}`",
+                    backend.desc(),
+                    bin,
+                    path.display()
+                ),
+            ));
+            path
+        }
+        None => {
+            let flag = if backend == Backend::Z3 {
+                "--z3_cmd"
+            } else {
+                "--solver_cmd"
+            };
+            res.push(Diagnostic::problem(
+                "solver command",
+                format!("cannot find {} command `{}`", backend.desc(), bin),
+                format!(
+                    "install {} and make sure it is in your `PATH`, \
+                    or pass the path to its binary with `{} <path>`",
+                    backend.desc(),
+                    flag
+                ),
+            ));
+            res.push(Diagnostic::skipped("solver version"));
+            res.push(Diagnostic::skipped("smoke test"));
+            res.push(color());
+            return res;
+        }
+    };
+
+    res.push(version(backend, &path));
+    res.push(smoke_test(env));
+    res.push(color());
+    res
+}
+
+/// Finds the binary a command runs, if any.
+pub fn resolve(bin: &str) -> Option<PathBuf> {
+    if bin.is_empty() {
+        return None;
+    }
+    if bin.contains(std::path::MAIN_SEPARATOR) || bin.contains('/') {
+        let path = PathBuf::from(bin);
+        return if path.is_file() { Some(path) } else { None };
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            let exe = dir
+                .join(bin)
+                .with_extension(std::env::consts::EXE_EXTENSION);
+            vec![dir.join(bin), exe]
+        })
+        .find(|path| path.is_file())
+}
+
+/// Minimal supported version of a solver.
+pub fn min_version(backend: Backend) -> &'static [u64] {
+    match backend {
+        Backend::Z3 => &[4, 8, 0],
+        Backend::Cvc5 => &[1, 0, 0],
+        // Older versions cannot print models in SMT-LIB 2 format.
+        Backend::Yices2 => &[2, 6, 2],
+        Backend::MathSat => &[5, 6, 0],
+    }
+}
+
+/// True if a version is at least `min`, missing numbers are zeros.
+pub fn is_supported(version: &[u64], min: &[u64]) -> bool {
+    let len = std::cmp::max(version.len(), min.len());
+    let pad = |v: &[u64]| {
+        let mut v = v.to_vec();
+        v.resize(len, 0);
+        v
+    };
+    pad(version) >= pad(min)
+}
+
+/// Extracts the first version number `x.y[.z...]` of some text.
+pub fn parse_version(txt: &str) -> Option<Vec<u64>> {
+    txt.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|word| word.trim_matches('.'))
+        .filter(|word| word.contains('.'))
+        .find_map(|word| {
+            word.split('.')
+                .map(|n| n.parse::<u64>().ok())
+                .collect::<Option<Vec<_>>>()
+        })
+}
+
+/// String representation of a version.
+fn version_to_string(version: &[u64]) -> String {
+    let version: Vec<_> = version.iter().map(u64::to_string).collect();
+    version.join(".")
+}
+
+/// Checks the version of the solver.
+fn version(backend: Backend, path: &Path) -> Diagnostic {
+    const WHAT: &str = "solver version";
+    let flag = match backend {
+        Backend::MathSat => "-version",
+        Backend::Z3 | Backend::Cvc5 | Backend::Yices2 => "--version",
+    };
+    let output = match Command::new(path).arg(flag).output() {
+        Ok(output) => output,
+        Err(e) => {
+            return Diagnostic::problem(
+                WHAT,
+                format!("could not run `{} {}`: {}", path.display(), flag, e),
+                "make sure the solver binary is executable",
+            )
+        }
+    };
+    let txt = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let min = min_version(backend);
+    let name = match backend {
+        Backend::Z3 => "z3",
+        Backend::Cvc5 => "cvc5",
+        Backend::Yices2 => "yices",
+        Backend::MathSat => "mathsat",
+    };
+    if !txt.to_lowercase().contains(name) {
+        return Diagnostic::warning(
+            WHAT,
+            format!(
+                "output of `{} {}` does not mention {}",
+                path.display(),
+                flag,
+                backend.desc()
+            ),
+            format!(
+                "make sure the command runs {}, or select the right solver with `--solver`",
+                backend.desc()
+            ),
+        );
+    }
+    match parse_version(&txt) {
+        Some(version) if is_supported(&version, min) => Diagnostic::ok(
+            WHAT,
+            format!("{} {}", backend.desc(), version_to_string(&version)),
+        ),
+        Some(version) => Diagnostic::problem(
+            WHAT,
+            format!(
+                "{} {} is not supported, mikino needs {} or later",
+                backend.desc(),
+                version_to_string(&version),
+                version_to_string(min)
+            ),
+            format!("install a more recent version of {}", backend.desc()),
+        ),
+        None => Diagnostic::warning(
+            WHAT,
+            format!(
+                "could not find a version number in the output of `{} {}`",
+                path.display(),
+                flag
+            ),
+            format!(
+                "make sure the command runs {}, mikino needs {} or later",
+                backend.desc(),
+                version_to_string(min)
+            ),
+        ),
+    }
+}
+
+/// Runs a tiny sat/unsat test.
+fn smoke_test(env: &Run) -> Diagnostic {
+    const WHAT: &str = "smoke test";
+    let conf = match env.solver_conf() {
+        Ok(conf) => conf,
+        Err(e) => {
+            return Diagnostic::problem(
+                WHAT,
+                format!("illegal solver configuration: {}", e),
+                "check the options passed with `--solver_opt` and `--seed`",
+            )
+        }
+    };
+
+    match run_smoke_test(&conf, SMOKE_TEST_TIMEOUT) {
+        Ok(true) => Diagnostic::ok(WHAT, "solver answers `sat` and `unsat` as expected"),
+        Ok(false) => Diagnostic::problem(
+            WHAT,
+            format!(
+                "solver did not answer within {} seconds",
+                SMOKE_TEST_TIMEOUT.as_secs()
+            ),
+            "make sure the command runs an SMT-LIB 2 solver reading commands on its standard input",
+        ),
+        Err(e) => Diagnostic::problem(
+            WHAT,
+            format!("solver failed the smoke test: {}", e),
+            "make sure the command runs an SMT-LIB 2 solver, \
+            and check the options passed with `--solver_opt`",
+        ),
+    }
+}
+
+/// Runs the [`SMOKE_TEST`], false if the solver does not answer within `timeout`.
+///
+/// The solver may hang if the command is not an SMT-LIB 2 solver. It is driven directly rather
+/// than through `rsmt2` so that it can be killed, whatever happens.
+fn run_smoke_test(conf: &SmtConf, timeout: Duration) -> Res<bool> {
+    let mut kid = Command::new(conf.get_cmd())
+        .args(conf.get_options())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .chain_err(|| "while spawning the solver")?;
+    let res = drive_smoke_test(&mut kid, timeout);
+    let _ = kid.kill();
+    let _ = kid.wait();
+    res
+}
+
+/// Sends the [`SMOKE_TEST`] to a solver and checks its answers.
+fn drive_smoke_test(kid: &mut std::process::Child, timeout: Duration) -> Res<bool> {
+    let deadline = Instant::now() + timeout;
+    let stdout = kid
+        .stdout
+        .take()
+        .ok_or("could not access solver's stdout")?;
+    let (sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let sent = line.map(|line| sender.send(line).is_ok());
+            if !matches!(sent, Ok(true)) {
+                return;
+            }
+        }
+    });
+    let mut stdin = kid.stdin.take().ok_or("could not access solver's stdin")?;
+    // A solver that exits early closes its input, the answers tell what went wrong.
+    let _ = stdin
+        .write_all(SMOKE_TEST.as_bytes())
+        .and_then(|()| stdin.flush());
+
+    for (expected, query) in SMOKE_TEST_ANSWERS {
+        let answer = loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match lines.recv_timeout(timeout) {
+                Ok(line) => match line.trim() {
+                    answer @ ("sat" | "unsat" | "unknown") => break answer.to_string(),
+                    error if error.starts_with("(error") => {
+                        bail!("solver answered `{}` to {}", error, query)
+                    }
+                    // Lines that are not answers, warnings for instance.
+                    _ => continue,
+                },
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(RecvTimeoutError::Disconnected) => {
+                    bail!("solver exited before answering {}", query)
+                }
+            }
+        };
+        if answer != expected {
+            bail!("{} is {}", query, answer)
+        }
+    }
+    Ok(true)
+}
+
+/// Checks the terminal supports colors.
+fn color() -> Diagnostic {
+    const WHAT: &str = "colors";
+    if cfg!(all(windows, not(feature = "force-color"))) {
+        Diagnostic::warning(
+            WHAT,
+            "colors are deactivated on Windows",
+            "build mikino with the `force-color` feature to activate them",
+        )
+    } else if !atty::is(atty::Stream::Stdout) {
+        Diagnostic::warning(
+            WHAT,
+            "standard output is not a terminal, output is not colored",
+            "run mikino directly in a terminal to get colors",
+        )
+    } else if std::env::var("TERM")
+        .map(|term| term == "dumb")
+        .unwrap_or(false)
+    {
+        Diagnostic::warning(
+            WHAT,
+            "terminal is `dumb`, it probably cannot show colors",
+            "use a terminal that supports ANSI colors, or pass `--no_color`",
+        )
+    } else {
+        Diagnostic::ok(WHAT, "terminal supports colors")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Outputs of `--version` (`-version` for MathSAT).
+    const VERSIONS: [(Backend, &str, &[u64]); 5] = [
+        (Backend::Z3, "Z3 version 4.12.2 - 64 bit\n", &[4, 12, 2]),
+        (Backend::Z3, "Z3 version 4.8.7 - 64 bit\n", &[4, 8, 7]),
+        (
+            Backend::Cvc5,
+            "This is cvc5 version 1.0.5 [git tag 1.0.5 branch HEAD]\n\
+            compiled with GCC version 11.3.0\n",
+            &[1, 0, 5],
+        ),
+        (
+            Backend::Yices2,
+            "Yices 2.6.4\nCopyright SRI International.\nLinked with GMP 6.2.1\n",
+            &[2, 6, 4],
+        ),
+        (
+            Backend::MathSat,
+            "MathSAT5 version 5.6.10 (a0bdd1a5b1d1) \
+            (Jun  1 2023 10:53:19, gmp 6.2.1, gcc 9.4.0, 64-bit)\n",
+            &[5, 6, 10],
+        ),
+    ];
+
+    #[test]
+    fn versions() {
+        for (backend, txt, expected) in VERSIONS {
+            let version = parse_version(txt).unwrap();
+            assert_eq!(version, expected, "{}", txt);
+            assert!(is_supported(&version, min_version(backend)), "{}", txt);
+        }
+        assert_eq!(parse_version("Yices 2.5\n"), Some(vec![2, 5]));
+        assert_eq!(parse_version("version 4.8.\n"), Some(vec![4, 8]));
+        assert_eq!(parse_version("no version here, 42\n"), None);
+    }
+
+    #[test]
+    fn min_versions() {
+        let supported = |backend, version| is_supported(version, min_version(backend));
+        assert!(supported(Backend::Z3, &[4, 8]));
+        assert!(!supported(Backend::Z3, &[4, 7, 9]));
+        assert!(supported(Backend::Cvc5, &[1]));
+        assert!(!supported(Backend::Cvc5, &[0, 9, 9]));
+        assert!(supported(Backend::Yices2, &[2, 6, 2]));
+        assert!(!supported(Backend::Yices2, &[2, 6, 1]));
+        assert!(!supported(Backend::Yices2, &[2, 5]));
+        assert!(supported(Backend::MathSat, &[5, 6]));
+        assert!(supported(Backend::MathSat, &[5, 10, 0]));
+        assert!(!supported(Backend::MathSat, &[5, 5, 1]));
+    }
+
+    #[test]
+    fn resolution() {
+        assert_eq!(resolve(""), None);
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(resolve(&exe.to_string_lossy()), Some(exe.clone()));
+        let missing = exe.with_file_name("mikino-no-such-solver");
+        assert_eq!(resolve(&missing.to_string_lossy()), None);
+        assert_eq!(resolve("mikino-no-such-solver"), None);
+        if cfg!(unix) {
+            assert!(resolve("sh").is_some());
+        }
+    }
+
+    /// Runs the smoke test against a fake solver, a shell script.
+    #[cfg(unix)]
+    fn fake_smoke_test(name: &str, script: &str, timeout: Duration) -> Res<bool> {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("mikino-doctor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let conf = Backend::Z3.conf(path.to_string_lossy(), None).unwrap();
+        run_smoke_test(&conf, timeout)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn smoke_tests() {
+        let timeout = Duration::from_secs(10);
+        let ok = "cat > /dev/null & printf 'success\\nsat\\nunsat\\nsat\\n'; wait";
+        assert!(fake_smoke_test("ok", ok, timeout).unwrap());
+
+        let wrong = "printf 'sat\\nsat\\nsat\\n'";
+        let e = fake_smoke_test("wrong", wrong, timeout).unwrap_err();
+        assert_eq!(e.to_string(), "`x > 0 ∧ x < 0` is sat");
+
+        let error = "printf '(error \"unknown command\")\\n'";
+        let e = fake_smoke_test("error", error, timeout).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "solver answered `(error \"unknown command\")` to `x > 0`"
+        );
+
+        let exits = "printf 'sat\\n'";
+        let e = fake_smoke_test("exits", exits, timeout).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "solver exited before answering `x > 0 ∧ x < 0`"
+        );
+
+        // The solver must be killed when it times out, otherwise waiting for it takes a minute.
+        let start = Instant::now();
+        let hangs = fake_smoke_test("hangs", "exec sleep 60", Duration::from_millis(200));
+        assert!(!hangs.unwrap());
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        let dir = std::env::temp_dir().join(format!("mikino-doctor-{}", std::process::id()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
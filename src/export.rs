@@ -0,0 +1,271 @@
+//! System export to the input formats of other model-checkers.
+
+mikino_api::prelude!();
+
+use std::{
+    collections::{BTreeMap as Map, BTreeSet as Set},
+    fmt::Display,
+    io::Write,
+};
+
+use expr::{Cst, Op, PExpr};
+use trans::Sys;
+
+pub mod chc;
+pub mod lustre;
+pub mod smv;
+pub mod vmt;
+
+/// Export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// VMT-LIB, SMT-LIB 2 with transition system annotations.
+    Vmt,
+    /// nuXmv's SMV dialect.
+    Smv,
+    /// Lustre, as supported by Kind 2.
+    Lustre,
+    /// Constrained Horn clauses in SMT-LIB's `HORN` logic.
+    Chc,
+}
+impl Format {
+    /// Description of the format.
+    pub fn desc(self) -> &'static str {
+        match self {
+            Self::Vmt => "VMT-LIB",
+            Self::Smv => "SMV",
+            Self::Lustre => "Lustre",
+            Self::Chc => "CHC",
+        }
+    }
+
+    /// Writes a system in this format to a writer.
+    pub fn write_to(self, w: &mut impl Write, sys: &Sys) -> Res<()> {
+        match self {
+            Self::Vmt => vmt::write(w, sys)?,
+            Self::Smv => smv::write(w, sys)?,
+            Self::Lustre => lustre::write(w, sys)?,
+            Self::Chc => chc::write(w, sys)?,
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Writes a system in this format to a file.
+    pub fn write(self, sys: &Sys, target: &str) -> Res<()> {
+        use std::fs::OpenOptions;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(target)
+            .chain_err(|| format!("while opening file `{}` in write mode", target))?;
+        self.write_to(&mut file, sys)
+            .chain_err(|| format!("while writing {} export to file `{}`", self.desc(), target))
+    }
+}
+
+/// SMT-LIB 2 sort of a type.
+pub fn typ_to_smt2(typ: Typ) -> &'static str {
+    match typ {
+        Typ::Bool => "Bool",
+        Typ::Int => "Int",
+        Typ::Rat => "Real",
+    }
+}
+
+/// SMT-LIB 2 representation of a constant.
+///
+/// Unlike `Cst`'s `Expr2Smt` implementation, negative constants are `(- 7)` and rationals use
+/// decimal numerals as required by the `Real` theory.
+pub fn cst_to_smt2(cst: &Cst) -> String {
+    match cst {
+        Cst::B(b) => b.to_string(),
+        Cst::I(i) if i.sign() == Sign::Minus => format!("(- {})", -i),
+        Cst::I(i) => i.to_string(),
+        Cst::R(r) if r.numer().sign() == Sign::Minus => {
+            format!("(- {})", cst_to_smt2(&Cst::R(-r)))
+        }
+        Cst::R(r) if r.is_integer() => format!("{}.0", r.numer()),
+        Cst::R(r) => format!("(/ {}.0 {}.0)", r.numer(), r.denom()),
+    }
+}
+
+/// SMT-LIB 2 representation of an operator.
+pub fn op_to_smt2(op: Op) -> &'static str {
+    match op {
+        Op::Ite => "ite",
+        Op::Implies => "=>",
+        Op::Add => "+",
+        Op::Sub => "-",
+        Op::Mul => "*",
+        Op::Div => "/",
+        Op::IDiv => "div",
+        Op::Mod => "mod",
+        Op::Ge => ">=",
+        Op::Le => "<=",
+        Op::Gt => ">",
+        Op::Lt => "<",
+        Op::Eq => "=",
+        Op::Not => "not",
+        Op::And => "and",
+        Op::Or => "or",
+    }
+}
+
+/// SMT-LIB 2 representation of an expression, variables are printed by `var`.
+pub fn expr_to_smt2<V, S: Display>(expr: &PExpr<V>, mut var: impl FnMut(&V) -> S) -> String {
+    expr.fold(
+        |v| var(v).to_string(),
+        cst_to_smt2,
+        |op, args| match (op, args.len()) {
+            (Op::And, 0) => "true".into(),
+            (Op::Or, 0) => "false".into(),
+            _ => format!("({} {})", op_to_smt2(op), args.join(" ")),
+        },
+    )
+}
+
+/// Type of an operator application given the type of its arguments, assumes it is well-typed.
+pub fn app_typ(op: Op, args: &[Typ]) -> Typ {
+    match op {
+        Op::Div => Typ::Rat,
+        Op::IDiv | Op::Mod => Typ::Int,
+        Op::Add | Op::Sub | Op::Mul => args.first().cloned().unwrap_or(Typ::Int),
+        Op::Ite => args.get(1).cloned().unwrap_or(Typ::Bool),
+        Op::Implies | Op::Ge | Op::Le | Op::Gt | Op::Lt | Op::Eq | Op::Not | Op::And | Op::Or => {
+            Typ::Bool
+        }
+    }
+}
+
+/// Bottom-up fold over an expression that also passes the type of the arguments to `app_action`.
+pub fn typed_fold<V: HasTyp>(
+    expr: &PExpr<V>,
+    mut var_action: impl FnMut(&V) -> String,
+    mut cst_action: impl FnMut(&Cst) -> String,
+    mut app_action: impl FnMut(Op, Vec<(String, Typ)>) -> String,
+) -> String {
+    let (res, _) = expr.fold(
+        |var| (var_action(var), var.typ()),
+        |cst| (cst_action(cst), cst.typ()),
+        |op, args| {
+            let typs: Vec<_> = args.iter().map(|(_, typ)| *typ).collect();
+            let typ = app_typ(op, &typs);
+            (app_action(op, args), typ)
+        },
+    );
+    res
+}
+
+/// Maps the state variables of a system to legal, distinct identifiers of some target format.
+///
+/// Quoted identifiers lose their `|` delimiters, illegal characters become `_`, and identifiers
+/// that are `reserved` in the target format get a trailing `_`.
+pub fn legal_ids(sys: &Sys, reserved: &[&str]) -> Map<String, String> {
+    let mut res = Map::new();
+    let mut used = Set::new();
+    for var in sys.decls().all() {
+        let id = var.id();
+        let inner = if id.len() >= 2 && id.starts_with('|') && id.ends_with('|') {
+            &id[1..id.len() - 1]
+        } else {
+            id
+        };
+        let mut legal: String = inner
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if legal.is_empty() || legal.starts_with(|c: char| c.is_ascii_digit()) {
+            legal.insert(0, '_')
+        }
+        if reserved.contains(&legal.as_str()) {
+            legal.push('_')
+        }
+        let mut unique = legal.clone();
+        let mut cnt = 2;
+        while used.contains(&unique) {
+            unique = format!("{}_{}", legal, cnt);
+            cnt += 1;
+        }
+        used.insert(unique.clone());
+        res.insert(id.to_string(), unique);
+    }
+    res
+}
+
+/// Left-associative infix application `((a op b) op c)`, for formats with binary operators.
+pub fn infix_left(op: &str, args: Vec<String>) -> String {
+    let mut args = args.into_iter();
+    let mut res = args.next().unwrap_or_default();
+    for arg in args {
+        res = format!("({} {} {})", res, op, arg)
+    }
+    res
+}
+
+/// Right-associative infix application `(a op (b op c))`, for formats with binary operators.
+pub fn infix_right(op: &str, args: Vec<String>) -> String {
+    let mut args = args.into_iter().rev();
+    let mut res = args.next().unwrap_or_default();
+    for arg in args {
+        res = format!("({} {} {})", arg, op, res)
+    }
+    res
+}
+
+/// Chained infix relation `((a op b) and (b op c))`, for formats with binary relations.
+pub fn infix_chain(op: &str, and: &str, args: Vec<String>) -> String {
+    let pairs = args
+        .windows(2)
+        .map(|pair| format!("({} {} {})", pair[0], op, pair[1]))
+        .collect();
+    infix_left(and, pairs)
+}
+
+/// Comment-friendly version of a string: no newlines.
+pub fn one_line(s: &str) -> String {
+    s.replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn legal_and_distinct_ids() {
+        let sys = parse::trans(
+            "svars { |a b|: int, a_b: int, |1x|: bool, next: bool } init { a_b = 0 } \
+            trans { 'a_b = a_b } candidates { \"p\": a_b ≥ 0 }",
+        )
+        .unwrap();
+        let ids = legal_ids(&sys, &["next"]);
+        let ids: Vec<_> = ids
+            .iter()
+            .map(|(id, legal)| (id.as_str(), legal.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("a_b", "a_b"),
+                ("next", "next_"),
+                ("|1x|", "_1x"),
+                ("|a b|", "a_b_2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn infix() {
+        let args = || vec!["a".to_string(), "b".into(), "c".into()];
+        assert_eq!(infix_left("-", args()), "((a - b) - c)");
+        assert_eq!(infix_right("=>", args()), "(a => (b => c))");
+        assert_eq!(infix_chain("<", "and", args()), "((a < b) and (b < c))");
+    }
+}
@@ -0,0 +1,228 @@
+//! Constrained Horn clause export.
+//!
+//! The system is encoded in SMT-LIB's `HORN` logic with a single uninterpreted predicate `inv` over
+//! the state variables `s`:
+//!
+//! - `init(s) ⇒ inv(s)`;
+//! - `inv(s) ⋀ trans(s, s') ⇒ inv(s')`;
+//! - `inv(s) ⋀ ¬candidate(s) ⇒ false`, for each candidate.
+//!
+//! The clauses are satisfiable iff all candidates hold in all reachable states, in which case any
+//! interpretation of `inv` is an inductive invariant strengthening the candidates.
+
+mikino_api::prelude!();
+
+use std::io::Write;
+
+use super::{expr_to_smt2, one_line, typ_to_smt2, vmt::next_id};
+use trans::Sys;
+
+/// Horn clauses encoding a system.
+pub struct Clauses {
+    /// Name of the invariant predicate.
+    pub pred: String,
+    /// Sorts of the arguments of the invariant predicate.
+    pub sorts: Vec<&'static str>,
+    /// Initial clause.
+    pub init: String,
+    /// Transition clause.
+    pub trans: String,
+    /// Query clauses, one for each candidate.
+    pub queries: Vec<(String, String)>,
+}
+impl Clauses {
+    /// Encodes a system.
+    pub fn new(sys: &Sys) -> Res<Self> {
+        let vars: Vec<_> = sys.decls().all().collect();
+        for var in &vars {
+            let next = next_id(var.id());
+            if sys.decls().contains(&next) {
+                bail!(
+                    "cannot encode system as Horn clauses: next version `{}` of variable `{}` \
+                    clashes with an existing variable",
+                    next,
+                    var.id(),
+                )
+            }
+        }
+
+        let mut pred = "inv".to_string();
+        while sys.decls().contains(&pred) {
+            pred.push('_')
+        }
+        let sorts = vars.iter().map(|var| typ_to_smt2(var.typ())).collect();
+
+        let binders = |next: bool| {
+            vars.iter()
+                .map(|var| {
+                    let id = if next {
+                        next_id(var.id())
+                    } else {
+                        var.id().to_string()
+                    };
+                    format!("({} {})", id, typ_to_smt2(var.typ()))
+                })
+                .collect::<Vec<_>>()
+        };
+        let app = |next: bool| {
+            if vars.is_empty() {
+                pred.clone()
+            } else {
+                let args: Vec<_> = vars
+                    .iter()
+                    .map(|var| {
+                        if next {
+                            next_id(var.id())
+                        } else {
+                            var.id().to_string()
+                        }
+                    })
+                    .collect();
+                format!("({} {})", pred, args.join(" "))
+            }
+        };
+        let clause = |binders: Vec<String>, lhs: String, rhs: String| {
+            let clause = format!("(=> {} {})", lhs, rhs);
+            if binders.is_empty() {
+                clause
+            } else {
+                format!("(forall ({}) {})", binders.join(" "), clause)
+            }
+        };
+
+        let init = clause(
+            binders(false),
+            expr_to_smt2(sys.init(), |var| var.id().to_string()),
+            app(false),
+        );
+
+        let trans = expr_to_smt2(sys.trans(), |svar| {
+            if svar.is_next() {
+                next_id(svar.id())
+            } else {
+                svar.id().to_string()
+            }
+        });
+        let mut trans_binders = binders(false);
+        trans_binders.extend(binders(true));
+        let trans = clause(
+            trans_binders,
+            format!("(and {} {})", app(false), trans),
+            app(true),
+        );
+
+        let queries = sys
+            .po_s()
+            .iter()
+            .map(|(name, def)| {
+                let def = expr_to_smt2(def, |var| var.id().to_string());
+                let query = clause(
+                    binders(false),
+                    format!("(and {} (not {}))", app(false), def),
+                    "false".into(),
+                );
+                (name.clone(), query)
+            })
+            .collect();
+
+        Ok(Self {
+            pred,
+            sorts,
+            init,
+            trans,
+            queries,
+        })
+    }
+}
+
+/// Writes a system as Horn clauses.
+pub fn write(w: &mut impl Write, sys: &Sys) -> Res<()> {
+    let clauses = Clauses::new(sys)?;
+
+    writeln!(w, "; Generated by mikino.")?;
+    writeln!(w, "(set-logic HORN)")?;
+
+    writeln!(w)?;
+    writeln!(w, "; Invariant predicate over the state variables.")?;
+    writeln!(
+        w,
+        "(declare-fun {} ({}) Bool)",
+        clauses.pred,
+        clauses.sorts.join(" ")
+    )?;
+
+    writeln!(w)?;
+    writeln!(w, "; Initial states.")?;
+    writeln!(w, "(assert {})", clauses.init)?;
+
+    writeln!(w)?;
+    writeln!(w, "; Transition relation.")?;
+    writeln!(w, "(assert {})", clauses.trans)?;
+
+    writeln!(w)?;
+    writeln!(w, "; Candidates.")?;
+    for (name, query) in &clauses.queries {
+        writeln!(w, "; `{}`", one_line(name))?;
+        writeln!(w, "(assert {})", query)?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "(check-sat)")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chc_of(txt: &str) -> String {
+        let sys = parse::trans(txt).unwrap();
+        let mut bytes = vec![];
+        write(&mut bytes, &sys).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn output() {
+        let chc = chc_of(
+            "svars { inv: int, b: bool } init { inv = 0 } trans { 'inv = inv + 1 ⋀ 'b = ¬b } \
+            candidates { \"pos\": inv ≥ 0 }",
+        );
+        let expected = "\
+; Generated by mikino.
+(set-logic HORN)
+
+; Invariant predicate over the state variables.
+(declare-fun inv_ (Bool Int) Bool)
+
+; Initial states.
+(assert (forall ((b Bool) (inv Int)) (=> (= inv 0) (inv_ b inv))))
+
+; Transition relation.
+(assert (forall ((b Bool) (inv Int) (b.next Bool) (inv.next Int)) \
+            (=> (and (inv_ b inv) (and (= inv.next (+ inv 1)) (= b.next (not b)))) \
+            (inv_ b.next inv.next))))
+
+; Candidates.
+; `pos`
+(assert (forall ((b Bool) (inv Int)) (=> (and (inv_ b inv) (not (>= inv 0))) false)))
+
+(check-sat)
+";
+        assert_eq!(chc, expected);
+    }
+
+    #[test]
+    fn next_clash() {
+        let sys = parse::trans(
+            "svars { |x|: int, |x.next|: int } init { |x| = 0 } trans { '|x| = |x| } \
+            candidates { \"x\": |x| ≥ 0 }",
+        )
+        .unwrap();
+        match Clauses::new(&sys) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(e.to_string().contains("clashes"), "{}", e),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Lustre export, in the dialect of Kind 2.
+//!
+//! Lustre nodes are functional while mikino systems are relational. The state variables thus
+//! become inputs of a `top` node, constrained by an assertion which is the initial predicate at the
+//! first instant and the transition relation afterwards. The current version of a variable `v` in
+//! the transition relation is `pre v`. Candidates are outputs of the node annotated as
+//! `--%PROPERTY`s.
+
+mikino_api::prelude!();
+
+use std::{collections::BTreeMap as Map, io::Write};
+
+use super::{infix_chain, infix_left, infix_right, legal_ids, typed_fold};
+use expr::{Cst, Op};
+use trans::Sys;
+
+/// Lustre reserved words, including Kind 2's.
+const RESERVED: &[&str] = &[
+    "node",
+    "function",
+    "returns",
+    "var",
+    "let",
+    "tel",
+    "const",
+    "type",
+    "assert",
+    "pre",
+    "fby",
+    "current",
+    "when",
+    "merge",
+    "if",
+    "then",
+    "else",
+    "and",
+    "or",
+    "not",
+    "xor",
+    "true",
+    "false",
+    "int",
+    "real",
+    "bool",
+    "div",
+    "mod",
+    "include",
+    "contract",
+    "guarantee",
+    "assume",
+    "mode",
+    "require",
+    "ensure",
+    "activate",
+    "restart",
+    "every",
+    "automaton",
+    "state",
+    "unless",
+    "until",
+    "resume",
+    "initial",
+    "end",
+    "imported",
+    "frame",
+    "top",
+];
+
+/// Lustre type.
+fn typ_to_lustre(typ: Typ) -> &'static str {
+    match typ {
+        Typ::Bool => "bool",
+        Typ::Int => "int",
+        Typ::Rat => "real",
+    }
+}
+
+/// Lustre constant.
+fn cst_to_lustre(cst: &Cst) -> String {
+    match cst {
+        Cst::B(b) => b.to_string(),
+        Cst::I(i) if i.sign() == Sign::Minus => format!("(- {})", -i),
+        Cst::I(i) => i.to_string(),
+        Cst::R(r) if r.numer().sign() == Sign::Minus => {
+            format!("(- {})", cst_to_lustre(&Cst::R(-r)))
+        }
+        Cst::R(r) => crate::hsmt::rat_to_hsmt(r.numer(), r.denom())
+            .unwrap_or_else(|| format!("({}.0 / {}.0)", r.numer(), r.denom())),
+    }
+}
+
+/// Lustre operator application.
+fn app_to_lustre(op: Op, args: Vec<(String, Typ)>) -> String {
+    let int_args = args.iter().all(|(_, typ)| *typ == Typ::Int);
+    let mut args: Vec<String> = args.into_iter().map(|(arg, _)| arg).collect();
+    match op {
+        Op::Ite if args.len() == 3 => format!("(if {} then {} else {})", args[0], args[1], args[2]),
+        Op::Not | Op::Sub if args.len() == 1 => {
+            format!("({} {})", if op == Op::Not { "not" } else { "-" }, args[0])
+        }
+        Op::And if args.is_empty() => cst_to_lustre(&Cst::B(true)),
+        Op::Or if args.is_empty() => cst_to_lustre(&Cst::B(false)),
+        Op::Implies => infix_right("=>", args),
+        Op::Eq | Op::Ge | Op::Le | Op::Gt | Op::Lt => {
+            let op = match op {
+                Op::Eq => "=",
+                Op::Ge => ">=",
+                Op::Le => "<=",
+                Op::Gt => ">",
+                _ => "<",
+            };
+            infix_chain(op, "and", args)
+        }
+        // Rational division of integers.
+        Op::Div if int_args => {
+            for arg in args.iter_mut() {
+                *arg = format!("real({})", arg)
+            }
+            infix_left("/", args)
+        }
+        _ => {
+            let op = match op {
+                Op::Add => "+",
+                Op::Sub => "-",
+                Op::Mul => "*",
+                Op::Div => "/",
+                // Kind 2 encodes them as SMT-LIB's `div` and `mod`, Euclidean like mikino's.
+                Op::IDiv => "div",
+                Op::Mod => "mod",
+                Op::And => "and",
+                Op::Or => "or",
+                _ => panic!("[unreachable] unexpected operator `{}`", op),
+            };
+            infix_left(op, args)
+        }
+    }
+}
+
+/// Writes a system as a Lustre node.
+pub fn write(w: &mut impl Write, sys: &Sys) -> Res<()> {
+    let ids = legal_ids(sys, RESERVED);
+    let id = |ids: &Map<String, String>, var: &str| ids[var].clone();
+
+    // Output names for the candidates, distinct from the inputs.
+    let mut outputs = Vec::with_capacity(sys.po_s().len());
+    for idx in 0..sys.po_s().len() {
+        let mut output = format!("candidate_{}", idx);
+        while ids.values().any(|id| *id == output) {
+            output.insert(0, '_')
+        }
+        outputs.push(output)
+    }
+
+    writeln!(w, "-- Generated by mikino.")?;
+    writeln!(w)?;
+    writeln!(w, "node top (")?;
+    let vars: Vec<_> = sys.decls().all().collect();
+    for (idx, var) in vars.iter().enumerate() {
+        let sep = if idx + 1 < vars.len() { ";" } else { "" };
+        writeln!(
+            w,
+            "  {}: {}{}",
+            ids[var.id()],
+            typ_to_lustre(var.typ()),
+            sep
+        )?;
+    }
+    writeln!(w, ") returns (")?;
+    for (idx, output) in outputs.iter().enumerate() {
+        let sep = if idx + 1 < outputs.len() { ";" } else { "" };
+        writeln!(w, "  {}: bool{}", output, sep)?;
+    }
+    writeln!(w, ");")?;
+    writeln!(w, "let")?;
+    writeln!(w, "  --%MAIN;")?;
+
+    let init = typed_fold(
+        sys.init(),
+        |var| id(&ids, var.id()),
+        cst_to_lustre,
+        app_to_lustre,
+    );
+    let trans = typed_fold(
+        sys.trans(),
+        |svar| {
+            if svar.is_next() {
+                id(&ids, svar.id())
+            } else {
+                format!("(pre {})", id(&ids, svar.id()))
+            }
+        },
+        cst_to_lustre,
+        app_to_lustre,
+    );
+    writeln!(w)?;
+    writeln!(w, "  -- Initial predicate, then transition relation.")?;
+    writeln!(w, "  assert {} -> {};", init, trans)?;
+
+    for ((name, def), output) in sys.po_s().iter().zip(&outputs) {
+        let def = typed_fold(def, |var| id(&ids, var.id()), cst_to_lustre, app_to_lustre);
+        writeln!(w)?;
+        writeln!(w, "  {} = {};", output, def)?;
+        writeln!(
+            w,
+            "  --%PROPERTY \"{}\" {};",
+            name.replace(['"', '\n'], "'"),
+            output
+        )?;
+    }
+    writeln!(w, "tel")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lustre_of(txt: &str) -> String {
+        let sys = parse::trans(txt).unwrap();
+        let mut bytes = vec![];
+        write(&mut bytes, &sys).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn output() {
+        let lustre = lustre_of(
+            "svars { pre: int, candidate_0: bool, r: rat } init { pre = -1, r = -0.25 } \
+            trans { 'pre = if candidate_0 { pre + 1 } else { pre % 2 }, 'r = pre / 2 } \
+            candidates { \"two\nlines\": pre ≤ 7 ⋀ 0.5 < r }",
+        );
+        let expected = "\
+-- Generated by mikino.
+
+node top (
+  candidate_0: bool;
+  pre_: int;
+  r: real
+) returns (
+  _candidate_0: bool
+);
+let
+  --%MAIN;
+
+  -- Initial predicate, then transition relation.
+  assert ((pre_ = (- 1)) and (r = (- 0.25))) -> \
+        ((pre_ = (if (pre candidate_0) then ((pre pre_) + 1) else ((pre pre_) mod 2))) and \
+        (r = (real((pre pre_)) / real(2))));
+
+  _candidate_0 = ((pre_ <= 7) and (0.5 < r));
+  --%PROPERTY \"two'lines\" _candidate_0;
+tel
+";
+        assert_eq!(lustre, expected);
+    }
+
+    #[test]
+    fn euclidean_division() {
+        let app = |op| app_to_lustre(op, vec![("a".into(), Typ::Int), ("b".into(), Typ::Int)]);
+        assert_eq!(app(Op::IDiv), "(a div b)");
+        assert_eq!(app(Op::Mod), "(a mod b)");
+    }
+
+    #[test]
+    fn constants() {
+        let rat = |num: i64, den: i64| Cst::R(Rat::new(num.into(), den.into()));
+        assert_eq!(cst_to_lustre(&rat(1, 3)), "(1.0 / 3.0)");
+        assert_eq!(cst_to_lustre(&rat(-1, 3)), "(- (1.0 / 3.0))");
+        assert_eq!(cst_to_lustre(&rat(5, 2)), "2.5");
+        assert_eq!(cst_to_lustre(&Cst::I((-3).into())), "(- 3)");
+    }
+}
@@ -0,0 +1,349 @@
+//! SMV export, in the dialect of nuXmv.
+//!
+//! The system becomes a `main` module with `integer`, `real` and `boolean` variables, its initial
+//! predicate and transition relation become `INIT` and `TRANS` constraints, and candidates become
+//! named `INVARSPEC`s.
+
+mikino_api::prelude!();
+
+use std::{collections::BTreeMap as Map, io::Write};
+
+use super::{infix_chain, infix_left, infix_right, legal_ids, one_line, typed_fold};
+use expr::{Cst, Op};
+use trans::Sys;
+
+/// SMV reserved words.
+const RESERVED: &[&str] = &[
+    "MODULE",
+    "DEFINE",
+    "MDEFINE",
+    "CONSTANTS",
+    "VAR",
+    "IVAR",
+    "FROZENVAR",
+    "INIT",
+    "TRANS",
+    "INVAR",
+    "SPEC",
+    "CTLSPEC",
+    "LTLSPEC",
+    "PSLSPEC",
+    "COMPUTE",
+    "NAME",
+    "INVARSPEC",
+    "FAIRNESS",
+    "JUSTICE",
+    "COMPASSION",
+    "ISA",
+    "ASSIGN",
+    "CONSTRAINT",
+    "SIMPWFF",
+    "CTLWFF",
+    "LTLWFF",
+    "PSLWFF",
+    "COMPWFF",
+    "IN",
+    "MIN",
+    "MAX",
+    "MIRROR",
+    "PRED",
+    "PREDICATES",
+    "process",
+    "array",
+    "of",
+    "boolean",
+    "integer",
+    "real",
+    "word",
+    "word1",
+    "bool",
+    "signed",
+    "unsigned",
+    "extend",
+    "resize",
+    "sizeof",
+    "uwconst",
+    "swconst",
+    "EX",
+    "AX",
+    "EF",
+    "AF",
+    "EG",
+    "AG",
+    "E",
+    "F",
+    "O",
+    "G",
+    "H",
+    "X",
+    "Y",
+    "Z",
+    "A",
+    "U",
+    "S",
+    "V",
+    "T",
+    "BU",
+    "EBF",
+    "ABF",
+    "EBG",
+    "ABG",
+    "case",
+    "esac",
+    "mod",
+    "next",
+    "init",
+    "union",
+    "in",
+    "xor",
+    "xnor",
+    "self",
+    "TRUE",
+    "FALSE",
+    "count",
+    "abs",
+    "max",
+    "min",
+    "toint",
+    "toreal",
+    "typeof",
+    "floor",
+];
+
+/// SMV type.
+fn typ_to_smv(typ: Typ) -> &'static str {
+    match typ {
+        Typ::Bool => "boolean",
+        Typ::Int => "integer",
+        Typ::Rat => "real",
+    }
+}
+
+/// SMV constant.
+fn cst_to_smv(cst: &Cst) -> String {
+    match cst {
+        Cst::B(true) => "TRUE".into(),
+        Cst::B(false) => "FALSE".into(),
+        Cst::I(i) if i.sign() == Sign::Minus => format!("(- {})", -i),
+        Cst::I(i) => i.to_string(),
+        Cst::R(r) if r.numer().sign() == Sign::Minus => format!("(- {})", cst_to_smv(&Cst::R(-r))),
+        // Decimal if possible, fractional `f'<num>/<den>` otherwise.
+        Cst::R(r) => crate::hsmt::rat_to_hsmt(r.numer(), r.denom())
+            .unwrap_or_else(|| format!("f'{}/{}", r.numer(), r.denom())),
+    }
+}
+
+/// Euclidean integer division or modulo, as in mikino and SMT-LIB.
+///
+/// The remainder of the Euclidean division is always positive, while nuXmv's `/` and `mod`
+/// truncate towards zero so their remainder has the sign of the dividend. Both agree when that
+/// remainder is positive, otherwise the quotient and the remainder are adjusted depending on the
+/// sign of the divisor.
+fn euclidean(op: Op, num: &str, den: &str) -> String {
+    let rem = format!("({} mod {})", num, den);
+    let (res, pos, neg) = if op == Op::Mod {
+        (
+            rem.clone(),
+            format!("({} + {})", rem, den),
+            format!("({} - {})", rem, den),
+        )
+    } else {
+        let quo = format!("({} / {})", num, den);
+        (
+            quo.clone(),
+            format!("({} - 1)", quo),
+            format!("({} + 1)", quo),
+        )
+    };
+    format!(
+        "case {} >= 0 : {}; {} > 0 : {}; TRUE : {}; esac",
+        rem, res, den, pos, neg
+    )
+}
+
+/// SMV operator application.
+fn app_to_smv(op: Op, args: Vec<(String, Typ)>) -> String {
+    let int_args = args.iter().all(|(_, typ)| *typ == Typ::Int);
+    let mut args: Vec<String> = args.into_iter().map(|(arg, _)| arg).collect();
+    match op {
+        Op::Ite if args.len() == 3 => {
+            format!("case {} : {}; TRUE : {}; esac", args[0], args[1], args[2])
+        }
+        Op::Not | Op::Sub if args.len() == 1 => {
+            format!("({} {})", if op == Op::Not { "!" } else { "-" }, args[0])
+        }
+        Op::And if args.is_empty() => cst_to_smv(&Cst::B(true)),
+        Op::Or if args.is_empty() => cst_to_smv(&Cst::B(false)),
+        Op::Implies => infix_right("->", args),
+        Op::Eq | Op::Ge | Op::Le | Op::Gt | Op::Lt => {
+            let op = match op {
+                Op::Eq => "=",
+                Op::Ge => ">=",
+                Op::Le => "<=",
+                Op::Gt => ">",
+                _ => "<",
+            };
+            infix_chain(op, "&", args)
+        }
+        // Rational division of integers.
+        Op::Div if int_args => {
+            for arg in args.iter_mut() {
+                *arg = format!("toreal({})", arg)
+            }
+            infix_left("/", args)
+        }
+        Op::IDiv | Op::Mod => {
+            let mut args = args.into_iter();
+            let mut res = args.next().unwrap_or_default();
+            for arg in args {
+                res = euclidean(op, &res, &arg)
+            }
+            res
+        }
+        _ => {
+            let op = match op {
+                Op::Add => "+",
+                Op::Sub => "-",
+                Op::Mul => "*",
+                Op::Div => "/",
+                Op::And => "&",
+                Op::Or => "|",
+                _ => panic!("[unreachable] unexpected operator `{}`", op),
+            };
+            infix_left(op, args)
+        }
+    }
+}
+
+/// Writes a system in SMV format.
+pub fn write(w: &mut impl Write, sys: &Sys) -> Res<()> {
+    let ids = legal_ids(sys, RESERVED);
+    let id = |ids: &Map<String, String>, var: &str| ids[var].clone();
+
+    writeln!(w, "-- Generated by mikino.")?;
+    writeln!(w, "MODULE main")?;
+
+    writeln!(w)?;
+    writeln!(w, "VAR")?;
+    for var in sys.decls().all() {
+        writeln!(w, "  {} : {};", ids[var.id()], typ_to_smv(var.typ()))?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "INIT")?;
+    let init = typed_fold(sys.init(), |var| id(&ids, var.id()), cst_to_smv, app_to_smv);
+    writeln!(w, "  {};", init)?;
+
+    writeln!(w)?;
+    writeln!(w, "TRANS")?;
+    let trans = typed_fold(
+        sys.trans(),
+        |svar| {
+            if svar.is_next() {
+                format!("next({})", id(&ids, svar.id()))
+            } else {
+                id(&ids, svar.id())
+            }
+        },
+        cst_to_smv,
+        app_to_smv,
+    );
+    writeln!(w, "  {};", trans)?;
+
+    for (idx, (name, def)) in sys.po_s().iter().enumerate() {
+        writeln!(w)?;
+        writeln!(w, "-- `{}`", one_line(name))?;
+        let def = typed_fold(def, |var| id(&ids, var.id()), cst_to_smv, app_to_smv);
+        writeln!(w, "INVARSPEC NAME candidate_{} := {};", idx, def)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn smv_of(txt: &str) -> String {
+        let sys = parse::trans(txt).unwrap();
+        let mut bytes = vec![];
+        write(&mut bytes, &sys).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn output() {
+        let smv = smv_of(
+            "svars { next: int, r: rat, b: bool } init { next = -1, r = -0.25 } \
+            trans { 'next = if b { next + 1 } else { next % 2 }, 'r = next / 2, 'b = ¬b } \
+            candidates { \"bounds\": next ≤ 7 ⋀ 0.5 < r, \"done\": b ⇒ next = 1 }",
+        );
+        let expected = "\
+-- Generated by mikino.
+MODULE main
+
+VAR
+  b : boolean;
+  next_ : integer;
+  r : real;
+
+INIT
+  ((next_ = (- 1)) & (r = (- 0.25)));
+
+TRANS
+  (((next(next_) = case b : (next_ + 1); TRUE : \
+        case (next_ mod 2) >= 0 : (next_ mod 2); 2 > 0 : ((next_ mod 2) + 2); \
+        TRUE : ((next_ mod 2) - 2); esac; esac) & \
+        (next(r) = (toreal(next_) / toreal(2)))) & (next(b) = (! b)));
+
+-- `bounds`
+INVARSPEC NAME candidate_0 := ((next_ <= 7) & (0.5 < r));
+
+-- `done`
+INVARSPEC NAME candidate_1 := (b -> (next_ = 1));
+";
+        assert_eq!(smv, expected);
+    }
+
+    #[test]
+    fn euclidean_division() {
+        let app = |op| app_to_smv(op, vec![("a".into(), Typ::Int), ("b".into(), Typ::Int)]);
+        assert_eq!(
+            app(Op::Mod),
+            "case (a mod b) >= 0 : (a mod b); b > 0 : ((a mod b) + b); \
+            TRUE : ((a mod b) - b); esac"
+        );
+        assert_eq!(
+            app(Op::IDiv),
+            "case (a mod b) >= 0 : (a / b); b > 0 : ((a / b) - 1); TRUE : ((a / b) + 1); esac"
+        );
+
+        // Evaluates the encoding with truncating `/` and `mod`, as nuXmv does.
+        let eval = |op: Op, a: i64, b: i64| {
+            let (quo, rem) = (a / b, a % b);
+            match (op, rem >= 0, b > 0) {
+                (Op::Mod, true, _) => rem,
+                (Op::Mod, false, true) => rem + b,
+                (Op::Mod, false, false) => rem - b,
+                (_, true, _) => quo,
+                (_, false, true) => quo - 1,
+                (_, false, false) => quo + 1,
+            }
+        };
+        for a in -7..=7 {
+            for b in [-3, -2, -1, 1, 2, 3] {
+                assert_eq!(eval(Op::IDiv, a, b), a.div_euclid(b), "{} div {}", a, b);
+                assert_eq!(eval(Op::Mod, a, b), a.rem_euclid(b), "{} mod {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn constants() {
+        let rat = |num: i64, den: i64| Cst::R(Rat::new(num.into(), den.into()));
+        assert_eq!(cst_to_smv(&rat(1, 3)), "f'1/3");
+        assert_eq!(cst_to_smv(&rat(-1, 3)), "(- f'1/3)");
+        assert_eq!(cst_to_smv(&rat(5, 2)), "2.5");
+        assert_eq!(cst_to_smv(&Cst::B(false)), "FALSE");
+    }
+}
@@ -0,0 +1,152 @@
+//! VMT-LIB export.
+//!
+//! A VMT-LIB file is an SMT-LIB 2 script where
+//!
+//! - each state variable `v` has a current and a next version, `v` and `v.next`, related by a
+//!   `:next` annotation;
+//! - the initial predicate and the transition relation are annotated with `:init` and `:trans`;
+//! - candidates are annotated with `:invar-property <index>`.
+
+mikino_api::prelude!();
+
+use std::io::Write;
+
+use super::{expr_to_smt2, one_line, typ_to_smt2};
+use trans::Sys;
+
+/// Next-state version of a variable identifier.
+pub fn next_id(id: &str) -> String {
+    if id.len() >= 2 && id.starts_with('|') && id.ends_with('|') {
+        format!("|{}.next|", &id[1..id.len() - 1])
+    } else {
+        format!("{}.next", id)
+    }
+}
+
+/// Writes a system in VMT-LIB format.
+pub fn write(w: &mut impl Write, sys: &Sys) -> Res<()> {
+    for var in sys.decls().all() {
+        let next = next_id(var.id());
+        if sys.decls().contains(&next) {
+            bail!(
+                "cannot export to VMT-LIB: next version `{}` of variable `{}` \
+                clashes with an existing variable",
+                next,
+                var.id(),
+            )
+        }
+    }
+
+    writeln!(w, "; Generated by mikino.")?;
+
+    writeln!(w)?;
+    writeln!(w, "; State variables.")?;
+    for (idx, var) in sys.decls().all().enumerate() {
+        let (id, sort) = (var.id(), typ_to_smt2(var.typ()));
+        writeln!(w, "(declare-fun {} () {})", id, sort)?;
+        writeln!(w, "(declare-fun {} () {})", next_id(id), sort)?;
+        writeln!(
+            w,
+            "(define-fun .sv{} () {} (! {} :next {}))",
+            idx,
+            sort,
+            id,
+            next_id(id)
+        )?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "; Initial predicate.")?;
+    writeln!(
+        w,
+        "(define-fun .init () Bool (! {} :init true))",
+        expr_to_smt2(sys.init(), |var| var.id().to_string())
+    )?;
+
+    writeln!(w)?;
+    writeln!(w, "; Transition relation.")?;
+    let trans = expr_to_smt2(sys.trans(), |svar| {
+        if svar.is_next() {
+            next_id(svar.id())
+        } else {
+            svar.id().to_string()
+        }
+    });
+    writeln!(w, "(define-fun .trans () Bool (! {} :trans true))", trans)?;
+
+    writeln!(w)?;
+    writeln!(w, "; Candidates.")?;
+    for (idx, (name, def)) in sys.po_s().iter().enumerate() {
+        writeln!(w, "; `{}`", one_line(name))?;
+        writeln!(
+            w,
+            "(define-fun .prop{} () Bool (! {} :invar-property {}))",
+            idx,
+            expr_to_smt2(def, |var| var.id().to_string()),
+            idx
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vmt_of(txt: &str) -> Res<String> {
+        let sys = parse::trans(txt)?;
+        let mut bytes = vec![];
+        write(&mut bytes, &sys)?;
+        Ok(String::from_utf8(bytes).unwrap())
+    }
+
+    #[test]
+    fn next_ids() {
+        assert_eq!(next_id("x"), "x.next");
+        assert_eq!(next_id("|x y|"), "|x y.next|");
+    }
+
+    #[test]
+    fn output() {
+        let vmt = vmt_of(
+            "svars { x: int, b: bool } init { x = -2 } trans { 'x = if b { x + 1 } else { x } } \
+            candidates { \"x is\nbig\": x ≥ -2 }",
+        )
+        .unwrap();
+        let expected = "\
+; Generated by mikino.
+
+; State variables.
+(declare-fun b () Bool)
+(declare-fun b.next () Bool)
+(define-fun .sv0 () Bool (! b :next b.next))
+(declare-fun x () Int)
+(declare-fun x.next () Int)
+(define-fun .sv1 () Int (! x :next x.next))
+
+; Initial predicate.
+(define-fun .init () Bool (! (= x (- 2)) :init true))
+
+; Transition relation.
+(define-fun .trans () Bool (! (= x.next (ite b (+ x 1) x)) :trans true))
+
+; Candidates.
+; `x is big`
+(define-fun .prop0 () Bool (! (>= x (- 2)) :invar-property 0))
+";
+        assert_eq!(vmt, expected);
+    }
+
+    #[test]
+    fn next_clash() {
+        let err = vmt_of(
+            "svars { |x|: int, |x.next|: int } init { |x| = 0 } trans { '|x| = |x| } \
+            candidates { \"x\": |x| ≥ 0 }",
+        );
+        match err {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(e.to_string().contains("clashes"), "{}", e),
+        }
+    }
+}
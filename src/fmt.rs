@@ -0,0 +1,785 @@
+//! Canonical formatting of hsmt systems and scripts.
+//!
+//! The formatter works on tokens so that comments survive. The layout only depends on the
+//! structure of the input, not on its line breaks:
+//!
+//! - commands and system sections start on their own line;
+//! - the items of `svars`, `vars`, `init`, `trans`, `candidates`, `assert`, `eval` and
+//!   `get_values` blocks go on their own line, each followed by a comma;
+//! - the branches of if-then-else-s, in expressions and in scripts, go on their own lines;
+//! - everything else stays on one line, tokens are separated by canonical spacing, *e.g.*
+//!   `a ⋀ (b ≥ 0)`, `v: int`, `check_sat!()`;
+//! - blocks are indented by four spaces;
+//! - comments at the end of a line stay there, other comments get their own line;
+//! - blank lines between commands and items are kept, at most one, and the file ends with exactly
+//!   one newline.
+//!
+//! The result is parsed again: systems must yield the same system, scripts must still parse.
+//! Formatting the result again must not change it.
+
+mikino_api::prelude!();
+
+/// Script commands that can be directly followed by their `(...)` arguments.
+const COMMANDS: [&str; 14] = [
+    "vars",
+    "assert",
+    "check_sat",
+    "get_model",
+    "get_value",
+    "get_values",
+    "eval",
+    "reset",
+    "echo",
+    "println",
+    "set_option",
+    "set_options",
+    "panic",
+    "exit",
+];
+
+/// Commands and system sections taking a comma-separated list of items between braces.
+const ITEM_BLOCKS: [&str; 8] = [
+    "svars",
+    "vars",
+    "init",
+    "trans",
+    "candidates",
+    "assert",
+    "eval",
+    "get_values",
+];
+
+/// Keywords and operators written as words, after which a `-` is a unary minus.
+const OP_WORDS: [&str; 6] = ["if", "else", "and", "or", "not", "let"];
+
+/// Formatting tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'txt> {
+    /// Identifier, number, keyword, primed variable or macro name such as `echo!`.
+    Word(&'txt str),
+    /// Double-quoted string.
+    Str(&'txt str),
+    /// Comment, without its trailing newline.
+    Comment(&'txt str),
+    /// Opening bracket.
+    Open(char),
+    /// Closing bracket.
+    Close(char),
+    /// Comma.
+    Comma,
+    /// Colon.
+    Colon,
+    /// Semicolon.
+    Semi,
+    /// Operator.
+    Op(&'txt str),
+}
+impl<'txt> Token<'txt> {
+    /// Text of the token.
+    fn as_str(&self) -> &'txt str {
+        match self {
+            Self::Word(s) | Self::Str(s) | Self::Comment(s) | Self::Op(s) => s,
+            Self::Open('{') => "{",
+            Self::Open('(') => "(",
+            Self::Open(_) => "[",
+            Self::Close('}') => "}",
+            Self::Close(')') => ")",
+            Self::Close(_) => "]",
+            Self::Comma => ",",
+            Self::Colon => ":",
+            Self::Semi => ";",
+        }
+    }
+
+    /// True if the token ends an operand, meaning a `-` after it is a binary minus.
+    fn ends_operand(&self) -> bool {
+        match self {
+            Self::Word(w) => !OP_WORDS.contains(w),
+            Self::Str(_) | Self::Close(_) => true,
+            _ => false,
+        }
+    }
+
+    /// True if the token is a binary operator, meaning a line ending with it continues on the next
+    /// line.
+    fn is_binop(&self) -> bool {
+        match self {
+            Self::Op(op) => !matches!(*op, "¬" | "!" | "'"),
+            Self::Word(w) => matches!(*w, "and" | "or"),
+            Self::Colon => true,
+            _ => false,
+        }
+    }
+}
+
+/// True for characters that can appear in identifiers and numbers.
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// A token and its position in the input.
+#[derive(Debug, Clone)]
+struct Tok<'txt> {
+    /// Actual token.
+    token: Token<'txt>,
+    /// Line of the token.
+    line: usize,
+    /// True if the token is the first one of its line.
+    first: bool,
+    /// True if the token is the first one of its line and follows a blank line.
+    blank: bool,
+}
+
+/// Splits some text in tokens.
+fn tokenize(txt: &str) -> Res<Vec<Tok<'_>>> {
+    let mut toks = vec![];
+    let mut chars = txt.char_indices().peekable();
+    let mut line = 1;
+    // True if no token was found on the current line yet.
+    let mut first = true;
+    // True if a blank line follows the last token.
+    let mut blank = false;
+
+    while let Some((start, c)) = chars.next() {
+        // Position right after the character(s) consumed so far.
+        macro_rules! end {
+            () => {
+                chars.peek().map(|(idx, _)| *idx).unwrap_or(txt.len())
+            };
+        }
+        let token = match c {
+            '\n' => {
+                blank = blank || first;
+                first = true;
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek().map(|(_, c)| *c) == Some('/') => {
+                while chars.peek().map(|(_, c)| *c != '\n').unwrap_or(false) {
+                    chars.next();
+                }
+                Token::Comment(txt[start..end!()].trim_end())
+            }
+            '"' => {
+                let mut escaped = false;
+                loop {
+                    match chars.next() {
+                        Some((_, '\n')) => line += 1,
+                        Some((_, '"')) if !escaped => break,
+                        Some((_, c)) => {
+                            escaped = c == '\\' && !escaped;
+                            continue;
+                        }
+                        None => bail!("line {}: unterminated string", line),
+                    }
+                    escaped = false
+                }
+                let s = &txt[start..end!()];
+                if s.contains('\n') {
+                    bail!("line {}: multi-line strings are not supported", line)
+                }
+                Token::Str(s)
+            }
+            '|' if chars.peek().map(|(_, c)| *c) == Some('|') => {
+                chars.next();
+                Token::Op(&txt[start..end!()])
+            }
+            '|' => {
+                loop {
+                    match chars.next() {
+                        Some((_, '|')) => break,
+                        Some((_, '\n')) => bail!(
+                            "line {}: multi-line quoted identifiers are not supported",
+                            line
+                        ),
+                        Some(_) => (),
+                        None => bail!("line {}: unterminated quoted identifier", line),
+                    }
+                }
+                Token::Word(&txt[start..end!()])
+            }
+            '{' | '(' | '[' => Token::Open(c),
+            '}' | ')' | ']' => Token::Close(match c {
+                '}' => '}',
+                ')' => ')',
+                _ => ']',
+            }),
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            ';' => Token::Semi,
+            '\'' if chars.peek().map(|(_, c)| is_word_char(*c)) == Some(true) => {
+                while chars.peek().map(|(_, c)| is_word_char(*c)) == Some(true) {
+                    chars.next();
+                }
+                Token::Word(&txt[start..end!()])
+            }
+            c if is_word_char(c) => {
+                // Dashes are legal inside option names such as `produce-models`.
+                let dash_ok = !c.is_ascii_digit();
+                loop {
+                    match chars.peek().map(|(_, c)| *c) {
+                        Some(c) if is_word_char(c) => {
+                            chars.next();
+                        }
+                        Some('-') if dash_ok => {
+                            let mut ahead = chars.clone();
+                            ahead.next();
+                            match ahead.peek() {
+                                Some((_, c)) if c.is_ascii_alphabetic() => {
+                                    chars.next();
+                                }
+                                _ => break,
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                if chars.peek().map(|(_, c)| *c) == Some('!') {
+                    chars.next();
+                }
+                Token::Word(&txt[start..end!()])
+            }
+            '⊤' | '⊥' => Token::Word(&txt[start..end!()]),
+            '=' if chars.peek().map(|(_, c)| *c) == Some('>') => {
+                chars.next();
+                Token::Op(&txt[start..end!()])
+            }
+            '=' | '<' | '>' if chars.peek().map(|(_, c)| *c) == Some('=') => {
+                chars.next();
+                Token::Op(&txt[start..end!()])
+            }
+            '&' if chars.peek().map(|(_, c)| *c) == Some('&') => {
+                chars.next();
+                Token::Op(&txt[start..end!()])
+            }
+            _ => Token::Op(&txt[start..end!()]),
+        };
+        toks.push(Tok {
+            token,
+            line,
+            first,
+            blank: first && blank,
+        });
+        if first {
+            blank = false
+        }
+        first = false;
+    }
+
+    Ok(toks)
+}
+
+/// Closing bracket of an opening bracket.
+fn closing(open: char) -> char {
+    match open {
+        '{' => '}',
+        '(' => ')',
+        _ => ']',
+    }
+}
+
+/// Tokens with their brackets matched.
+#[derive(Debug, Clone)]
+enum Node<'txt> {
+    /// A token other than a bracket.
+    Leaf(Tok<'txt>),
+    /// An opening bracket and the nodes up to the matching closing bracket.
+    Group(Tok<'txt>, Vec<Node<'txt>>),
+}
+impl<'txt> Node<'txt> {
+    /// First token of the node.
+    fn first(&self) -> &Tok<'txt> {
+        match self {
+            Self::Leaf(tok) | Self::Group(tok, _) => tok,
+        }
+    }
+
+    /// True if the node is a comment.
+    fn is_comment(&self) -> bool {
+        matches!(self, Self::Leaf(tok) if matches!(tok.token, Token::Comment(_)))
+    }
+
+    /// The token of the node if it is a leaf.
+    fn leaf(&self) -> Option<&Token<'txt>> {
+        match self {
+            Self::Leaf(tok) => Some(&tok.token),
+            Self::Group(_, _) => None,
+        }
+    }
+
+    /// The opening bracket of the node if it is a group.
+    fn open(&self) -> Option<char> {
+        match self {
+            Self::Group(tok, _) => match tok.token {
+                Token::Open(open) => Some(open),
+                _ => None,
+            },
+            Self::Leaf(_) => None,
+        }
+    }
+}
+
+/// Matches the brackets of some tokens.
+fn tree(toks: Vec<Tok<'_>>) -> Res<Vec<Node<'_>>> {
+    // Opening brackets currently open, with the nodes before them.
+    let mut open = vec![];
+    let mut nodes = vec![];
+    for tok in toks {
+        match tok.token {
+            Token::Open(_) => open.push((tok, std::mem::take(&mut nodes))),
+            Token::Close(close) => {
+                let (open, outer) = open
+                    .pop()
+                    .ok_or_else(|| format!("line {}: unbalanced `{}`", tok.line, close))?;
+                let expected = match open.token {
+                    Token::Open(open) => closing(open),
+                    _ => unreachable!("[unreachable] only opening brackets are open"),
+                };
+                if close != expected {
+                    bail!(
+                        "line {}: `{}` closes `{}` from line {}",
+                        tok.line,
+                        close,
+                        open.token.as_str(),
+                        open.line,
+                    )
+                }
+                let group = Node::Group(open, std::mem::replace(&mut nodes, outer));
+                nodes.push(group)
+            }
+            _ => nodes.push(Node::Leaf(tok)),
+        }
+    }
+    if let Some((open, _)) = open.pop() {
+        bail!("line {}: unclosed `{}`", open.line, open.token.as_str())
+    }
+    Ok(nodes)
+}
+
+/// True if a command continues with some node.
+///
+/// A command ends with a `;`, or with brackets followed by something else than an `else`, an
+/// `otherwise`, a `;`, or the first branch of a script if-then-else.
+fn continues(cmd: &[Node], next: &Node) -> bool {
+    let last = match cmd.iter().rev().find(|node| !node.is_comment()) {
+        Some(last) => last,
+        None => return true,
+    };
+    match last {
+        Node::Leaf(tok) => tok.token != Token::Semi,
+        Node::Group(_, _) => match next {
+            Node::Leaf(tok) => matches!(
+                tok.token,
+                Token::Word("else") | Token::Word("otherwise") | Token::Semi
+            ),
+            Node::Group(_, _) => {
+                next.open() == Some('{')
+                    && last.open() != Some('{')
+                    && cmd[0].leaf() == Some(&Token::Word("if"))
+            }
+        },
+    }
+}
+
+/// Splits some nodes in commands.
+///
+/// Comments at the end of the line of a command belong to the command, other comments between
+/// commands are on their own.
+fn commands<'a, 'txt>(nodes: &'a [Node<'txt>]) -> Vec<&'a [Node<'txt>]> {
+    let mut res = vec![];
+    // Start of the current command, if any.
+    let mut start = None;
+    // Last node of the current command.
+    let mut last = 0;
+    for (idx, node) in nodes.iter().enumerate() {
+        if node.is_comment() {
+            match start {
+                Some(_) if !node.first().first && idx == last + 1 => last = idx,
+                Some(_) => (),
+                None => res.push(&nodes[idx..=idx]),
+            }
+            continue;
+        }
+        match start {
+            Some(s) if !continues(&nodes[s..=last], node) => {
+                res.push(&nodes[s..=last]);
+                for comment in last + 1..idx {
+                    res.push(&nodes[comment..=comment])
+                }
+                start = Some(idx)
+            }
+            Some(_) => (),
+            None => start = Some(idx),
+        }
+        last = idx
+    }
+    if let Some(s) = start {
+        res.push(&nodes[s..=last]);
+        for comment in last + 1..nodes.len() {
+            res.push(&nodes[comment..=comment])
+        }
+    }
+    res
+}
+
+/// An item of a comma-separated list.
+#[derive(Default)]
+struct Item<'a, 'txt> {
+    /// Nodes of the item, without the comments ending it.
+    code: Vec<&'a Node<'txt>>,
+    /// Comments ending the item, they go after its comma.
+    after: Vec<&'a Node<'txt>>,
+}
+impl<'a, 'txt> Item<'a, 'txt> {
+    /// First node of the item, if any.
+    fn first(&self) -> Option<&'a Node<'txt>> {
+        self.code.first().or_else(|| self.after.first()).copied()
+    }
+}
+
+/// Splits some nodes in comma-separated items.
+///
+/// Comments on the line of a comma belong to the item before it.
+fn items<'a, 'txt>(nodes: &'a [Node<'txt>]) -> Vec<Item<'a, 'txt>> {
+    let mut res = vec![Item::default()];
+    // True right after a comma, until the next line.
+    let mut after_comma = false;
+    for node in nodes {
+        if node.leaf() == Some(&Token::Comma) {
+            res.push(Item::default());
+            after_comma = true;
+            continue;
+        }
+        if node.is_comment() && after_comma && !node.first().first {
+            let len = res.len();
+            res[len - 2].after.push(node);
+            continue;
+        }
+        after_comma = false;
+        res.last_mut()
+            .expect("[unreachable] items cannot be empty")
+            .code
+            .push(node)
+    }
+    for item in &mut res {
+        let len = item
+            .code
+            .iter()
+            .rev()
+            .take_while(|node| node.is_comment())
+            .count();
+        let mut after = item.code.split_off(item.code.len() - len);
+        after.append(&mut item.after);
+        item.after = after;
+    }
+    res.retain(|item| item.first().is_some());
+    res
+}
+
+/// How to lay out the content of braces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Braces {
+    /// Script commands, one per line.
+    Commands,
+    /// Comma-separated items, one per line.
+    Items,
+    /// Branch of an if-then-else expression, on its own lines.
+    Branch,
+    /// On the same line.
+    Inline,
+}
+
+/// Writes nodes in the canonical layout.
+struct Printer<'txt> {
+    /// Text written so far.
+    res: String,
+    /// Indentation of the current line.
+    indent: usize,
+    /// True at the start of a line.
+    fresh: bool,
+    /// Last token of the current line, if any.
+    prev: Option<Token<'txt>>,
+    /// Token before the last one on the current line, if any.
+    prev_prev: Option<Token<'txt>>,
+}
+impl<'txt> Printer<'txt> {
+    /// Constructor.
+    fn new(capa: usize) -> Self {
+        Self {
+            res: String::with_capacity(capa),
+            indent: 0,
+            fresh: true,
+            prev: None,
+            prev_prev: None,
+        }
+    }
+
+    /// Writes a token, `indent` is the indentation if the token starts a line.
+    fn put(&mut self, token: &Token<'txt>, indent: usize) {
+        if self.fresh {
+            for _ in 0..indent {
+                self.res.push_str("    ")
+            }
+            self.indent = indent;
+            self.fresh = false
+        } else if let Some(prev) = &self.prev {
+            if space_between(self.prev_prev.as_ref(), prev, token) {
+                self.res.push(' ')
+            }
+        }
+        self.res.push_str(token.as_str());
+        self.prev_prev = self.prev.replace(token.clone());
+    }
+
+    /// Ends the current line, if any.
+    fn newline(&mut self) {
+        if !self.fresh {
+            self.res.push('\n');
+            self.fresh = true;
+            self.prev = None;
+            self.prev_prev = None;
+        }
+    }
+
+    /// Ends the current line, if any, and writes a blank line.
+    fn blank(&mut self) {
+        self.newline();
+        if !self.res.is_empty() && !self.res.ends_with("\n\n") {
+            self.res.push('\n')
+        }
+    }
+
+    /// Writes some commands, each on its own line.
+    fn commands(&mut self, nodes: &[Node<'txt>], indent: usize) {
+        for (idx, cmd) in commands(nodes).into_iter().enumerate() {
+            if idx > 0 && cmd[0].first().blank {
+                self.blank()
+            }
+            let braces = match cmd[0].leaf() {
+                Some(Token::Word("if")) => Braces::Commands,
+                Some(Token::Word(word)) if ITEM_BLOCKS.contains(&word.trim_end_matches('!')) => {
+                    Braces::Items
+                }
+                None if cmd[0].open() == Some('{') => Braces::Commands,
+                _ => Braces::Inline,
+            };
+            self.inline(cmd, indent, braces);
+            self.newline()
+        }
+    }
+
+    /// Writes some comma-separated items, each on its own line and followed by a comma.
+    fn items(&mut self, nodes: &[Node<'txt>], indent: usize) {
+        for (idx, item) in items(nodes).into_iter().enumerate() {
+            if idx > 0 && item.first().map(|node| node.first().blank) == Some(true) {
+                self.blank()
+            }
+            if !item.code.is_empty() {
+                self.inline(item.code, indent, Braces::Branch);
+                self.put(&Token::Comma, indent);
+            }
+            self.inline(item.after, indent, Braces::Branch);
+            self.newline()
+        }
+    }
+
+    /// Writes some nodes on the current line.
+    ///
+    /// Only comments, and braces laid out on their own lines, break the line. Lines start with
+    /// indentation `base`, plus one if the previous line ends with a binary operator.
+    fn inline<'a>(
+        &mut self,
+        nodes: impl IntoIterator<Item = &'a Node<'txt>>,
+        base: usize,
+        braces: Braces,
+    ) where
+        'txt: 'a,
+    {
+        // True if the last token written, ignoring comments, is a binary operator.
+        let mut after_binop = false;
+        for node in nodes {
+            let indent = if after_binop { base + 1 } else { base };
+            match node {
+                Node::Leaf(tok) if node.is_comment() => {
+                    if tok.first {
+                        self.newline()
+                    }
+                    self.put(&tok.token, indent);
+                    self.newline()
+                }
+                Node::Leaf(tok) => {
+                    self.put(&tok.token, indent);
+                    after_binop = tok.token.is_binop()
+                }
+                Node::Group(open, nodes) => {
+                    self.put(&open.token, indent);
+                    let outer = self.indent;
+                    let open = node
+                        .open()
+                        .expect("[unreachable] groups are opened by brackets");
+                    match (open, braces) {
+                        (_, _) if nodes.is_empty() => (),
+                        ('{', Braces::Commands) => {
+                            self.newline();
+                            self.commands(nodes, outer + 1);
+                        }
+                        ('{', Braces::Items) => {
+                            self.newline();
+                            self.items(nodes, outer + 1);
+                        }
+                        ('{', Braces::Branch) => {
+                            self.newline();
+                            self.inline(nodes, outer + 1, Braces::Branch);
+                            self.newline()
+                        }
+                        _ => self.inline(nodes, outer + 1, Braces::Branch),
+                    }
+                    self.put(&Token::Close(closing(open)), outer);
+                    after_binop = false
+                }
+            }
+        }
+    }
+}
+
+/// True if there should be a space between two tokens on the same line.
+fn space_between(prev_prev: Option<&Token>, prev: &Token, next: &Token) -> bool {
+    let prev_is_unary = match prev {
+        Token::Op("¬") | Token::Op("!") | Token::Op("'") => true,
+        Token::Op("-") => !prev_prev.map(Token::ends_operand).unwrap_or(false),
+        _ => false,
+    };
+    match (prev, next) {
+        (_, Token::Comment(_)) => true,
+        (_, Token::Comma) | (_, Token::Colon) | (_, Token::Semi) => false,
+        (Token::Open('{'), Token::Close('}')) => false,
+        (_, Token::Close('}')) | (Token::Open('{'), _) => true,
+        (_, Token::Close(_)) | (Token::Open(_), _) => false,
+        _ if prev_is_unary => false,
+        (Token::Word(word), Token::Open('(')) => !(word.ends_with('!') || COMMANDS.contains(word)),
+        _ => true,
+    }
+}
+
+/// Formats some hsmt text, without checking the result.
+fn format_txt(txt: &str) -> Res<String> {
+    let nodes = tree(tokenize(txt)?)?;
+    let mut printer = Printer::new(txt.len());
+    printer.commands(&nodes, 0);
+    printer.newline();
+    Ok(printer.res)
+}
+
+/// Formats an hsmt system or script.
+///
+/// Fails if the input is neither a system nor a script, or if formatting changed its meaning.
+pub fn format(txt: &str) -> Res<String> {
+    let formatted = format_txt(txt)?;
+    match parse::trans(txt) {
+        Ok(sys) => {
+            let new_sys = parse::trans(&formatted)
+                .chain_err(|| "[fatal] formatted system does not parse, please report this bug")?;
+            if sys.to_ml_string() != new_sys.to_ml_string() {
+                bail!("[fatal] formatting changed the system, please report this bug")
+            }
+        }
+        Err(sys_err) => {
+            if let Err(script_err) = parse::script(txt) {
+                let err = if txt.contains("svars") {
+                    sys_err
+                } else {
+                    script_err
+                };
+                return Err(err.chain_err(|| "input is neither a valid system nor a valid script"));
+            }
+            parse::script(&formatted)
+                .chain_err(|| "[fatal] formatted script does not parse, please report this bug")?;
+        }
+    }
+    if format_txt(&formatted)? != formatted {
+        bail!("[fatal] formatting is not idempotent, please report this bug")
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SYS: &str = "\
+svars {
+    x: int,
+    b: bool,
+}
+init {
+    x = 0,
+}
+trans {
+    'x = if b {
+        x + 1
+    } else {
+        x
+    },
+    'b = ¬b,
+}
+candidates {
+    \"pos\": x ≥ 0,
+}
+";
+
+    #[test]
+    fn layout_independent() {
+        let one_line = "svars { x : int , b:bool } init { x=0 } \
+            trans { 'x = if b { x+1 } else { x }, 'b = ¬ b } candidates { \"pos\" : x ≥ 0 }";
+        assert_eq!(format(one_line).unwrap(), SYS);
+        let spread = "svars {\n  x\n  :\n  int,\n\n\n  b: bool\n}\n\ninit { x = 0, }\ntrans {\n\
+            'x =\n if b { x + 1 }\n else { x }\n,\n 'b = ¬b }\ncandidates { \"pos\":\nx ≥ 0 }";
+        let mut expected = SYS.replace("    x: int,\n", "    x: int,\n\n");
+        expected = expected.replace("}\ninit", "}\n\ninit");
+        assert_eq!(format(spread).unwrap(), expected);
+        assert_eq!(format(SYS).unwrap(), SYS);
+    }
+
+    #[test]
+    fn comments() {
+        let txt = "//! System.\n\n/// Variables.\nsvars { x: int, // counter\n// flag\nb: bool }\n\
+            init { x = 0 // start\n}\ntrans { 'x = x + 1 ⋀ // next\n'b = b, }\n\
+            candidates { \"pos\": x ≥ 0 }\n";
+        let expected =
+            "//! System.\n\n/// Variables.\nsvars {\n    x: int, // counter\n    // flag\n\
+            \x20   b: bool,\n}\ninit {\n    x = 0, // start\n}\ntrans {\n    'x = x + 1 ⋀ // next\n\
+            \x20       'b = b,\n}\ncandidates {\n    \"pos\": x ≥ 0,\n}\n";
+        assert_eq!(format(txt).unwrap(), expected);
+    }
+
+    #[test]
+    fn scripts() {
+        let txt = "vars { n: int } assert(n > 0) let res = check_sat!() ; \
+            if res { get_model!() echo!(\"sat\") } else { exit!( 1 ) }";
+        let expected = "vars {\n    n: int,\n}\nassert(n > 0)\nlet res = check_sat!();\n\
+            if res {\n    get_model!()\n    echo!(\"sat\")\n} else {\n    exit!(1)\n}\n";
+        assert_eq!(format(txt).unwrap(), expected);
+    }
+
+    #[test]
+    fn idempotent() {
+        for demo in [mikino_api::TRANS_DEMO, mikino_api::SCRIPT_DEMO] {
+            let formatted = format(demo).unwrap();
+            assert_eq!(format(&formatted).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        let err = |txt: &str| format(txt).unwrap_err().to_string();
+        assert_eq!(
+            err("svars { x: int )"),
+            "line 1: `)` closes `{` from line 1"
+        );
+        assert_eq!(err("init {\n    x = 0,\n"), "line 1: unclosed `{`");
+        assert_eq!(err("}"), "line 1: unbalanced `}`");
+        assert_eq!(err("echo!(\"unterminated)"), "line 1: unterminated string");
+        assert!(format("svars { x: int } init { y = 0 }").is_err());
+    }
+}
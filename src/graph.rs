@@ -0,0 +1,305 @@
+//! Variable dependency graph.
+//!
+//! The transition relation is split in its top-level conjuncts.
+//!
+//! - A definition `'y = e` (or `e = 'y`) where `e` does not mention `'y` makes the next value of
+//!   `y` depend on the current variables of `e`, and on the next value of the variables `'x`
+//!   appearing in `e`.
+//! - Any other conjunct makes the next value of each variable `'y` it mentions depend on all the
+//!   current variables it mentions. The next values it mentions are coupled: they constrain each
+//!   other within a single transition.
+
+mikino_api::prelude!();
+
+use std::{
+    collections::{BTreeMap as Map, BTreeSet as Set},
+    io::Write,
+};
+
+use expr::{Op, PExpr, SExpr};
+use trans::Sys;
+
+/// Dependencies of a system.
+pub struct Graph<'sys> {
+    /// System analyzed.
+    pub sys: &'sys Sys,
+    /// Maps each variable to the variables whose current value its next value depends on.
+    pub deps: Map<String, Set<String>>,
+    /// Maps each variable to the variables whose next value its next value depends on.
+    pub next_deps: Map<String, Set<String>>,
+    /// Pairs of variables whose next values are coupled, smallest first.
+    pub coupled: Set<(String, String)>,
+    /// Maps each candidate to the variables it reads.
+    pub reads: Map<&'sys String, Set<String>>,
+}
+impl<'sys> Graph<'sys> {
+    /// Analyzes a system.
+    pub fn new(sys: &'sys Sys) -> Self {
+        let mut conjuncts = vec![];
+        conjuncts_of(sys.trans(), &mut conjuncts);
+
+        let mut deps: Map<String, Set<String>> = Map::new();
+        let mut next_deps: Map<String, Set<String>> = Map::new();
+        let mut coupled = Set::new();
+        for conjunct in conjuncts {
+            if let Some((y, def)) = definition(conjunct) {
+                let (curr, next) = vars_of(def);
+                deps.entry(y.clone()).or_default().extend(curr);
+                if !next.is_empty() {
+                    next_deps.entry(y).or_default().extend(next);
+                }
+                continue;
+            }
+
+            let (curr, next) = vars_of(conjunct);
+            for y in &next {
+                deps.entry(y.clone())
+                    .or_default()
+                    .extend(curr.iter().cloned());
+                for z in next
+                    .range::<String, _>((std::ops::Bound::Excluded(y), std::ops::Bound::Unbounded))
+                {
+                    coupled.insert((y.clone(), z.clone()));
+                }
+            }
+        }
+
+        let reads = sys
+            .po_s()
+            .iter()
+            .map(|(name, def)| {
+                let mut vars = Set::new();
+                def.fold(
+                    |var| {
+                        vars.insert(var.id().to_string());
+                    },
+                    |_| (),
+                    |_, _| (),
+                );
+                (name, vars)
+            })
+            .collect();
+
+        Self {
+            sys,
+            deps,
+            next_deps,
+            coupled,
+            reads,
+        }
+    }
+
+    /// Writes the graph in Graphviz's DOT format.
+    ///
+    /// Variables whose next value is not constrained by the transition relation, *i.e.* inputs,
+    /// are dashed. Candidates are boxes. Next-state dependencies are dashed, and undirected for
+    /// couplings.
+    pub fn write_dot(&self, w: &mut impl Write) -> Res<()> {
+        writeln!(w, "digraph mikino {{")?;
+        writeln!(w, "    rankdir = LR;")?;
+
+        writeln!(w)?;
+        writeln!(w, "    // State variables.")?;
+        for var in self.sys.decls().all() {
+            let style = if self.deps.contains_key(var.id()) {
+                ""
+            } else {
+                ", style = dashed"
+            };
+            writeln!(
+                w,
+                "    {} [label = {}{}];",
+                quoted(var.id()),
+                quoted(&format!("{}: {}", var.id(), var.typ())),
+                style,
+            )?;
+        }
+
+        writeln!(w)?;
+        writeln!(w, "    // Next-state dependencies.")?;
+        for (y, xs) in &self.deps {
+            for x in xs {
+                writeln!(w, "    {} -> {};", quoted(x), quoted(y))?;
+            }
+        }
+
+        if !self.next_deps.is_empty() {
+            writeln!(w)?;
+            writeln!(w, "    // Dependencies between next values.")?;
+            for (y, xs) in &self.next_deps {
+                for x in xs {
+                    writeln!(w, "    {} -> {} [style = dashed];", quoted(x), quoted(y))?;
+                }
+            }
+        }
+
+        if !self.coupled.is_empty() {
+            writeln!(w)?;
+            writeln!(w, "    // Next-state couplings.")?;
+            for (y, z) in &self.coupled {
+                writeln!(
+                    w,
+                    "    {} -> {} [dir = none, style = dashed];",
+                    quoted(y),
+                    quoted(z)
+                )?;
+            }
+        }
+
+        writeln!(w)?;
+        writeln!(w, "    // Candidates.")?;
+        for (idx, (name, vars)) in self.reads.iter().enumerate() {
+            let node = format!("candidate_{}", idx);
+            writeln!(w, "    {} [label = {}, shape = box];", node, quoted(name))?;
+            for var in vars {
+                writeln!(w, "    {} -> {};", quoted(var), node)?;
+            }
+        }
+
+        writeln!(w, "}}")?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// Pushes the top-level conjuncts of an expression.
+fn conjuncts_of<'e>(expr: &'e SExpr, conjuncts: &mut Vec<&'e SExpr>) {
+    match expr {
+        PExpr::App { op: Op::And, args } => {
+            for arg in args {
+                conjuncts_of(arg, conjuncts)
+            }
+        }
+        _ => conjuncts.push(expr),
+    }
+}
+
+/// Splits a definition `'y = e` in `y` and `e`, if `e` does not mention `'y`.
+fn definition(expr: &SExpr) -> Option<(String, &SExpr)> {
+    let (lft, rgt) = match expr {
+        PExpr::App { op: Op::Eq, args } if args.len() == 2 => (&args[0], &args[1]),
+        _ => return None,
+    };
+    for (var, def) in [(lft, rgt), (rgt, lft)] {
+        if let PExpr::Var(svar) = var {
+            if svar.is_next() {
+                let (_, next) = vars_of(def);
+                if !next.contains(svar.id()) {
+                    return Some((svar.id().to_string(), def));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Current and next variables of an expression.
+fn vars_of(expr: &SExpr) -> (Set<String>, Set<String>) {
+    let (mut curr, mut next) = (Set::new(), Set::new());
+    expr.fold(
+        |svar| {
+            let id = svar.id().to_string();
+            if svar.is_next() {
+                next.insert(id);
+            } else {
+                curr.insert(id);
+            }
+        },
+        |_| (),
+        |_, _| (),
+    );
+    (curr, next)
+}
+
+/// DOT string literal.
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sys() -> Sys {
+        parse::trans(
+            "svars { x: int, y: int, z: int, i: int, b: bool } init { x = 0 } \
+            trans { 'y = x + 1, 'x = 'y * 2 + z, 'z > i ⇒ 'b } \
+            candidates { \"p\": x ≥ y, \"q\": b }",
+        )
+        .unwrap()
+    }
+
+    fn set(ids: &[&str]) -> Set<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn dependencies() {
+        let sys = sys();
+        let graph = Graph::new(&sys);
+
+        // `'y = x + 1` gives `x -> y`.
+        assert_eq!(graph.deps["y"], set(&["x"]));
+        assert_eq!(graph.deps["x"], set(&["z"]));
+        assert_eq!(graph.next_deps["x"], set(&["y"]));
+        assert!(!graph.next_deps.contains_key("y"));
+        // Not a definition, `'z` and `'b` are coupled.
+        assert_eq!(graph.deps["z"], set(&["i"]));
+        assert_eq!(graph.deps["b"], set(&["i"]));
+        let coupled: Vec<_> = graph.coupled.iter().cloned().collect();
+        assert_eq!(coupled, vec![("b".to_string(), "z".to_string())]);
+        // `i` is an input.
+        assert!(!graph.deps.contains_key("i"));
+
+        let reads: Vec<_> = graph
+            .reads
+            .iter()
+            .map(|(name, vars)| (name.as_str(), vars.clone()))
+            .collect();
+        assert_eq!(reads, vec![("p", set(&["x", "y"])), ("q", set(&["b"]))]);
+    }
+
+    #[test]
+    fn dot() {
+        let sys = sys();
+        let mut bytes = vec![];
+        Graph::new(&sys).write_dot(&mut bytes).unwrap();
+        let expected = "\
+digraph mikino {
+    rankdir = LR;
+
+    // State variables.
+    \"b\" [label = \"b: bool\"];
+    \"i\" [label = \"i: int\", style = dashed];
+    \"x\" [label = \"x: int\"];
+    \"y\" [label = \"y: int\"];
+    \"z\" [label = \"z: int\"];
+
+    // Next-state dependencies.
+    \"i\" -> \"b\";
+    \"z\" -> \"x\";
+    \"x\" -> \"y\";
+    \"i\" -> \"z\";
+
+    // Dependencies between next values.
+    \"y\" -> \"x\" [style = dashed];
+
+    // Next-state couplings.
+    \"b\" -> \"z\" [dir = none, style = dashed];
+
+    // Candidates.
+    candidate_0 [label = \"p\", shape = box];
+    \"x\" -> candidate_0;
+    \"y\" -> candidate_0;
+    candidate_1 [label = \"q\", shape = box];
+    \"b\" -> candidate_1;
+}
+";
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quoted("|a \"b\" \\c|"), "\"|a \\\"b\\\" \\\\c|\"");
+    }
+}
@@ -0,0 +1,373 @@
+//! Hsmt printing.
+//!
+//! Mikino's expressions print as SMT-LIB-like prefix expressions, which are not legal hsmt. This
+//! module prints (stateless and stateful) expressions as fully parenthesized hsmt expressions that
+//! mikino can parse back.
+
+mikino_api::prelude!();
+
+use expr::{Cst, Op, PExpr};
+
+/// Hsmt representation of a constant.
+///
+/// Boolean constants are printed as `⊤` and `⊥`: the hsmt parser reads `true` and `false` as
+/// variables in some contexts. Rational constants are printed as decimals, [`expr_to_hsmt`] only
+/// prints constants that have one.
+fn cst_to_hsmt(cst: &Cst) -> String {
+    match cst {
+        Cst::B(true) => "⊤".into(),
+        Cst::B(false) => "⊥".into(),
+        Cst::I(i) if i.sign() == Sign::Minus => format!("(- {})", -i),
+        Cst::I(i) => i.to_string(),
+        Cst::R(r) if r.numer().sign() == Sign::Minus => {
+            format!("(- {})", cst_to_hsmt(&Cst::R(-r)))
+        }
+        Cst::R(r) => rat_to_hsmt(r.numer(), r.denom())
+            .expect("[unreachable] denominators are cleared before printing"),
+    }
+}
+
+/// Decimal representation of a positive rational, also legal in formats with decimal literals.
+///
+/// `None` if the rational has no finite decimal representation.
+pub fn rat_to_hsmt(num: &Int, den: &Int) -> Option<String> {
+    let (two, five, ten) = (Int::from(2), Int::from(5), Int::from(10));
+
+    // `den` must be `2^twos * 5^fives` for the decimal representation to be finite.
+    let (mut rest, mut twos, mut fives) = (den.clone(), 0, 0);
+    while (&rest % &two).is_zero() {
+        rest /= &two;
+        twos += 1;
+    }
+    while (&rest % &five).is_zero() {
+        rest /= &five;
+        fives += 1;
+    }
+    if !rest.is_one() {
+        return None;
+    }
+
+    let scale = std::cmp::max(twos, fives);
+    let mut pow = Int::one();
+    for _ in 0..scale {
+        pow *= &ten;
+    }
+    let digits = (num * (&pow / den)).to_string();
+    let res = if scale == 0 {
+        format!("{}.0", digits)
+    } else if digits.len() <= scale {
+        format!("0.{:0>width$}", digits, width = scale)
+    } else {
+        let (int, dec) = digits.split_at(digits.len() - scale);
+        format!("{}.{}", int, dec)
+    };
+    Some(res)
+}
+
+/// Hsmt representation of a (boolean) expression.
+///
+/// Variables are printed by `var`. The result is fully parenthesized, and n-ary relations such as
+/// `(= a b c)` are expanded as conjunctions `((a = b) ⋀ (b = c))`.
+///
+/// Hsmt has no literals for rationals such as `1/3`, and mikino's parser does not read divisions
+/// of constants back correctly. So both sides of the arithmetic relations are multiplied by the
+/// denominators of their constants first: `x ≥ 1/3` is printed as `((3.0 * x) ≥ 1.0)`.
+pub fn expr_to_hsmt<V: Clone>(expr: &PExpr<V>, mut var: impl FnMut(&V) -> String) -> String {
+    scale(expr, &Int::one()).fold(&mut var, cst_to_hsmt, app_to_hsmt)
+}
+
+/// Least common multiple of two positive integers.
+fn lcm(lft: &Int, rgt: &Int) -> Int {
+    let (mut gcd, mut rest) = (lft.clone(), rgt.clone());
+    while !rest.is_zero() {
+        let next = &gcd % &rest;
+        gcd = rest;
+        rest = next;
+    }
+    lft / gcd * rgt
+}
+
+/// Value of an application of `/` to constants, if any.
+fn div_value<V>(args: &[PExpr<V>]) -> Option<Rat> {
+    let mut vals = args.iter().map(|arg| match arg {
+        PExpr::Cst(Cst::I(i)) => Some(Rat::from_integer(i.clone())),
+        PExpr::Cst(Cst::R(r)) => Some(r.clone()),
+        _ => None,
+    });
+    let mut res = vals.next()??;
+    for val in vals {
+        let val = val?;
+        if val.is_zero() {
+            return None;
+        }
+        res /= val;
+    }
+    Some(res)
+}
+
+/// Denominator of a rational without a finite decimal representation, one for other rationals.
+fn fraction_denom(rat: &Rat) -> Int {
+    match rat_to_hsmt(rat.numer(), rat.denom()) {
+        Some(_) => Int::one(),
+        None => rat.denom().clone(),
+    }
+}
+
+/// Smallest factor that clears the fractions (see [`fraction_denom`]) of the constants of an
+/// arithmetic expression.
+///
+/// Non-arithmetic expressions clear their own denominators, see [`scale`].
+fn denom<V>(expr: &PExpr<V>) -> Int {
+    match expr {
+        PExpr::Cst(Cst::R(r)) => fraction_denom(r),
+        PExpr::Cst(_) | PExpr::Var(_) => Int::one(),
+        PExpr::App { op, args } => match op {
+            Op::Add | Op::Sub | Op::Ite => args
+                .iter()
+                .fold(Int::one(), |acc, arg| lcm(&acc, &denom(arg))),
+            Op::Mul => args.iter().fold(Int::one(), |acc, arg| acc * denom(arg)),
+            Op::Div => match div_value(args) {
+                Some(val) => fraction_denom(&val),
+                None => args.first().map(denom).unwrap_or_else(Int::one),
+            },
+            _ => Int::one(),
+        },
+    }
+}
+
+/// Multiplies an expression by `coef`, a multiple of its [`denom`], so that it mentions no
+/// fractions.
+///
+/// `coef` is only different from one for rational expressions. Arithmetic relations multiply
+/// their arguments by the least common multiple of their denominators.
+fn scale<V: Clone>(expr: &PExpr<V>, coef: &Int) -> PExpr<V> {
+    let times = |expr: PExpr<V>| {
+        if coef.is_one() {
+            expr
+        } else {
+            PExpr::App {
+                op: Op::Mul,
+                args: vec![PExpr::Cst(Cst::R(Rat::from_integer(coef.clone()))), expr],
+            }
+        }
+    };
+    let app = |op, args| PExpr::App { op, args };
+    let one = Int::one();
+    match expr {
+        PExpr::Cst(Cst::R(r)) => PExpr::Cst(Cst::R(r * coef)),
+        PExpr::Cst(Cst::I(i)) => PExpr::Cst(Cst::I(i * coef)),
+        PExpr::Cst(Cst::B(_)) => expr.clone(),
+        PExpr::Var(_) => times(expr.clone()),
+        PExpr::App { op, args } => match op {
+            Op::Add | Op::Sub => app(*op, args.iter().map(|arg| scale(arg, coef)).collect()),
+            Op::Ite => app(
+                *op,
+                args.iter()
+                    .enumerate()
+                    .map(|(idx, arg)| scale(arg, if idx == 0 { &one } else { coef }))
+                    .collect(),
+            ),
+            Op::Mul => {
+                // `coef` is a multiple of the product of the arguments' denominators, the first
+                // argument takes what's left.
+                let denoms: Vec<_> = args.iter().map(denom).collect();
+                let rest = coef / denoms.iter().fold(Int::one(), |acc, d| acc * d);
+                let args = args
+                    .iter()
+                    .zip(denoms)
+                    .enumerate()
+                    .map(|(idx, (arg, d))| scale(arg, &if idx == 0 { &rest * d } else { d }))
+                    .collect();
+                app(*op, args)
+            }
+            Op::Div => {
+                if let Some(val) = div_value(args) {
+                    return PExpr::Cst(Cst::R(val * coef));
+                }
+                let denoms: Vec<_> = args.iter().skip(1).map(denom).collect();
+                if args.iter().all(|arg| denom(arg).is_one()) {
+                    // Arguments might be integers, which cannot be multiplied by a rational.
+                    let args = args.iter().map(|arg| scale(arg, &one)).collect();
+                    return times(app(*op, args));
+                }
+                let num_coef = denoms.iter().fold(coef.clone(), |acc, d| acc * d);
+                let mut denoms = denoms.into_iter();
+                let args = args
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, arg)| match idx {
+                        0 => scale(arg, &num_coef),
+                        _ => scale(arg, &denoms.next().unwrap_or_else(Int::one)),
+                    })
+                    .collect();
+                app(*op, args)
+            }
+            Op::Eq | Op::Ge | Op::Le | Op::Gt | Op::Lt => {
+                let coef = args
+                    .iter()
+                    .fold(Int::one(), |acc, arg| lcm(&acc, &denom(arg)));
+                app(*op, args.iter().map(|arg| scale(arg, &coef)).collect())
+            }
+            _ => app(*op, args.iter().map(|arg| scale(arg, &one)).collect()),
+        },
+    }
+}
+
+/// Hsmt representation of an operator application, given the hsmt representation of its arguments.
+fn app_to_hsmt(op: Op, mut args: Vec<String>) -> String {
+    let op_str = op.hsmt_str()[0];
+    match op {
+        Op::Ite if args.len() == 3 => {
+            format!("(if {} {{ {} }} else {{ {} }})", args[0], args[1], args[2])
+        }
+        Op::Not | Op::Sub if args.len() == 1 => format!("({} {})", op_str, args[0]),
+
+        // Empty conjunction/disjunction.
+        Op::And if args.is_empty() => cst_to_hsmt(&Cst::B(true)),
+        Op::Or if args.is_empty() => cst_to_hsmt(&Cst::B(false)),
+
+        // Chainable relations.
+        Op::Eq | Op::Ge | Op::Le | Op::Gt | Op::Lt if args.len() > 2 => {
+            let pairs: Vec<_> = args
+                .windows(2)
+                .map(|pair| format!("({} {} {})", pair[0], op_str, pair[1]))
+                .collect();
+            format!("({})", pairs.join(&format!(" {} ", Op::And.hsmt_str()[0])))
+        }
+
+        // Right-associative.
+        Op::Implies => {
+            let mut args = args.into_iter().rev();
+            let mut res = args.next().unwrap_or_else(|| cst_to_hsmt(&Cst::B(true)));
+            for arg in args {
+                res = format!("({} {} {})", arg, op_str, res)
+            }
+            res
+        }
+
+        // Left-associative.
+        _ => {
+            if args.len() == 1 {
+                return args.pop().expect("[unreachable] pop on vec of len `1`");
+            }
+            let mut args = args.into_iter();
+            let mut res = args.next().unwrap_or_default();
+            for arg in args {
+                res = format!("({} {} {})", res, op_str, arg)
+            }
+            res
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type E = PExpr<&'static str>;
+
+    fn var(id: &'static str) -> E {
+        PExpr::Var(id)
+    }
+    fn rat(num: i64, den: i64) -> E {
+        PExpr::Cst(Cst::R(Rat::new(num.into(), den.into())))
+    }
+    fn app(op: Op, args: Vec<E>) -> E {
+        PExpr::App { op, args }
+    }
+
+    /// Prints `expr`, checks it against `hsmt`, and parses it back as the initial predicate of a
+    /// system to check it against `smt`.
+    fn round_trip(expr: E, hsmt: &str, smt: &str) {
+        let printed = expr_to_hsmt(&expr, |id| id.to_string());
+        assert_eq!(printed, hsmt);
+        let txt = format!(
+            "svars {{ x: rat, y: rat, n: int, c: bool }} init {{ {} }} \
+            trans {{ 'x = x ∧ 'y = y ∧ 'n = n ∧ 'c = c }} candidates {{ \"p\": c }}",
+            printed
+        );
+        let sys = parse::trans(&txt).unwrap();
+        assert_eq!(sys.init().to_string(), smt);
+    }
+
+    #[test]
+    fn decimals() {
+        let dec = |num: i64, den: i64| rat_to_hsmt(&num.into(), &den.into());
+        assert_eq!(dec(3, 1).as_deref(), Some("3.0"));
+        assert_eq!(dec(5, 2).as_deref(), Some("2.5"));
+        assert_eq!(dec(1, 20).as_deref(), Some("0.05"));
+        assert_eq!(dec(1, 3), None);
+        assert_eq!(dec(7, 30), None);
+    }
+
+    #[test]
+    fn decimal_constants() {
+        round_trip(
+            app(Op::Ge, vec![var("x"), rat(-1, 4)]),
+            "(x ≥ (- 0.25))",
+            "(>= x (- (/ 1 4)))",
+        );
+    }
+
+    #[test]
+    fn fractions_are_multiplied_through() {
+        round_trip(
+            app(Op::Ge, vec![var("x"), rat(1, 3)]),
+            "((3.0 * x) ≥ 1.0)",
+            "(>= (* (/ 3 1) x) (/ 1 1))",
+        );
+        round_trip(
+            app(
+                Op::Eq,
+                vec![
+                    app(Op::Add, vec![var("x"), rat(-1, 6)]),
+                    app(Op::Mul, vec![rat(2, 3), var("y")]),
+                ],
+            ),
+            "(((6.0 * x) + (- 1.0)) = (4.0 * y))",
+            "(= (+ (* (/ 6 1) x) (- (/ 1 1))) (* (/ 4 1) y))",
+        );
+        round_trip(
+            app(
+                Op::Gt,
+                vec![app(Op::Ite, vec![var("c"), rat(1, 3), var("x")]), rat(1, 2)],
+            ),
+            "((if c { 1.0 } else { (3.0 * x) }) > 1.5)",
+            "(> (ite c (/ 1 1) (* (/ 3 1) x)) (/ 3 2))",
+        );
+    }
+
+    #[test]
+    fn fractions_in_divisions() {
+        // Integer division, the coefficient goes outside.
+        round_trip(
+            app(
+                Op::Eq,
+                vec![
+                    app(Op::Div, vec![var("n"), PExpr::Cst(Cst::I(2.into()))]),
+                    rat(1, 3),
+                ],
+            ),
+            "((3.0 * (n / 2)) = 1.0)",
+            "(= (* (/ 3 1) (/ n 2)) (/ 1 1))",
+        );
+        // Division of constants, folded.
+        round_trip(
+            app(
+                Op::Le,
+                vec![var("x"), app(Op::Div, vec![rat(1, 1), rat(3, 1)])],
+            ),
+            "((3.0 * x) ≤ 1.0)",
+            "(<= (* (/ 3 1) x) (/ 1 1))",
+        );
+        // Fraction in the denominator.
+        round_trip(
+            app(
+                Op::Le,
+                vec![app(Op::Div, vec![var("x"), rat(1, 3)]), var("y")],
+            ),
+            "(((3.0 * x) / 1.0) ≤ y)",
+            "(<= (/ (* (/ 3 1) x) (/ 1 1)) y)",
+        );
+    }
+}
@@ -0,0 +1,727 @@
+//! Self-contained HTML verification report.
+
+mikino_api::prelude!();
+
+use std::{io::Write, time::Duration};
+
+use check::{cexs::Cex, BaseRes, BmcRes, StepRes};
+use trans::Sys;
+
+/// CSS for the report, classes mirror the fields of [`crate::Styles`].
+const CSS: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 70em; color: #222; }
+h1 { font-size: 1.6em; }
+h2 { font-size: 1.3em; margin-top: 1.5em; border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; margin: .5em 0; }
+th, td { border: 1px solid #ccc; padding: .2em .6em; text-align: left; }
+th { background: #f4f4f4; }
+pre { background: #f8f8f8; border: 1px solid #ddd; padding: .8em; overflow-x: auto; }
+code, pre, td.val { font-family: monospace; }
+details { margin: .5em 0; }
+summary { cursor: pointer; }
+.bold { font-weight: bold; }
+.under { text-decoration: underline; }
+.red { color: #c0392b; }
+.green { color: #27ae60; }
+.gray { color: #888; }
+.ita { font-style: italic; }
+.code { color: #b8860b; }
+";
+
+/// Hsmt keywords highlighted in the system's source.
+const KEYWORDS: &[&str] = &[
+    "svars",
+    "state",
+    "vars",
+    "init",
+    "trans",
+    "candidates",
+    "po_s",
+    "if",
+    "else",
+    "bool",
+    "int",
+    "rat",
+    "true",
+    "false",
+];
+
+/// Escapes HTML special characters.
+pub fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Wraps some (unescaped) text in a `span` with some class.
+fn span(class: &str, s: &str) -> String {
+    format!("<span class=\"{}\">{}</span>", class, escape(s))
+}
+
+/// Syntax-highlights some hsmt source.
+pub fn highlight(src: &str) -> String {
+    let mut res = String::with_capacity(src.len() * 2);
+    let mut rest = src;
+
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("//") {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            res.push_str(&span("gray", &rest[..len]));
+            len
+        } else if c == '"' {
+            let len = rest[1..].find('"').map(|idx| idx + 2).unwrap_or(rest.len());
+            res.push_str(&span("code", &rest[..len]));
+            len
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            if KEYWORDS.contains(&word) {
+                res.push_str(&span("bold", word))
+            } else if c.is_numeric() {
+                res.push_str(&span("code", word))
+            } else {
+                res.push_str(&escape(word))
+            }
+            len
+        } else if c == '\'' {
+            res.push_str(&span("under", "'"));
+            1
+        } else {
+            res.push_str(&escape(&rest[..c.len_utf8()]));
+            c.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+
+    res
+}
+
+/// Final verdict for a candidate.
+#[derive(Debug, Clone, Copy)]
+pub enum Verdict<'a> {
+    /// Holds in all reachable states.
+    Proved,
+    /// Falsified by some engine, with the counterexample.
+    Falsified(crate::cex::Origin, &'a Cex),
+    /// Falsified by Spacer, BMC did not reproduce the counterexample.
+    SpacerFalsified,
+    /// Not inductive, BMC did not run or did not find a falsification.
+    NotInductive(&'a Cex),
+    /// No conclusion.
+    Unknown,
+}
+
+/// HTML report of a check run.
+pub struct Report<'a, 'sys> {
+    /// System checked.
+    pub sys: &'sys Sys,
+    /// Path to the system's file.
+    pub input: &'a str,
+    /// System's source.
+    pub src: &'a str,
+    /// Base check result, if induction ran.
+    pub base: Option<&'a BaseRes<'sys>>,
+    /// Step check result, if induction ran.
+    pub step: Option<&'a StepRes<'sys>>,
+    /// Spacer result, if Spacer ran.
+    pub spacer: Option<&'a crate::spacer::SpacerRes<'sys>>,
+    /// BMC result, if BMC ran, with the first and last depths checked if any.
+    pub bmc: Option<(&'a BmcRes<'sys>, Option<(Unroll, Unroll)>)>,
+    /// Time spent in each phase of the run.
+    pub times: Vec<(&'static str, Duration)>,
+    /// Detailed statistics, if requested.
+    pub stats: Option<&'a crate::stats::Stats>,
+    /// Solver that won each query, with `--portfolio`.
+    pub wins: &'a [crate::portfolio::Win],
+}
+impl<'a, 'sys> Report<'a, 'sys> {
+    /// Verdict for a candidate.
+    pub fn verdict(&self, candidate: &str) -> Verdict<'a> {
+        use crate::cex::Origin;
+        macro_rules! cex_of {
+            ($res:expr) => {
+                $res.and_then(|res| {
+                    res.cexs
+                        .iter()
+                        .find(|(name, _)| name.as_str() == candidate)
+                        .map(|(_, cex)| cex)
+                })
+            };
+        }
+        if let Some(cex) = cex_of!(self.base) {
+            return Verdict::Falsified(Origin::Base, cex);
+        }
+        if let Some(cex) = cex_of!(self.bmc.map(|(res, _)| res)) {
+            return Verdict::Falsified(Origin::Bmc, cex);
+        }
+        if let Some(cex) = cex_of!(self.step) {
+            return Verdict::NotInductive(cex);
+        }
+        if let Some(spacer) = self.spacer {
+            if spacer.proved().any(|(name, _)| name == candidate) {
+                return Verdict::Proved;
+            }
+            if spacer.falsified().any(|name| name == candidate) {
+                return Verdict::SpacerFalsified;
+            }
+        }
+        if self.step.is_some() {
+            Verdict::Proved
+        } else {
+            Verdict::Unknown
+        }
+    }
+
+    /// Writes the report to a file.
+    pub fn write(&self, path: &str) -> Res<()> {
+        use std::fs::OpenOptions;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .chain_err(|| format!("while opening file `{}` in write mode", path))?;
+        self.write_to(&mut file)
+            .chain_err(|| format!("while writing HTML report to file `{}`", path))
+    }
+
+    /// Writes the report.
+    pub fn write_to(&self, w: &mut impl Write) -> Res<()> {
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(w, "<title>mikino report: {}</title>", escape(self.input))?;
+        writeln!(w, "<style>\n{}</style>\n</head>\n<body>", CSS)?;
+        writeln!(
+            w,
+            "<h1>Mikino report for <code>{}</code></h1>",
+            escape(self.input)
+        )?;
+        writeln!(
+            w,
+            "<p class=\"gray\">generated by mikino v{}</p>",
+            env!("CARGO_PKG_VERSION")
+        )?;
+
+        self.write_verdicts(w)?;
+        self.write_cexs(w)?;
+        self.write_stats(w)?;
+        self.write_wins(w)?;
+
+        if !self.src.is_empty() {
+            writeln!(w, "<h2>System</h2>")?;
+            writeln!(w, "<pre>{}</pre>", highlight(self.src))?;
+        }
+
+        writeln!(w, "</body>\n</html>")?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Writes the summary and the table of verdicts.
+    fn write_verdicts(&self, w: &mut impl Write) -> Res<()> {
+        let verdicts: Vec<_> = self
+            .sys
+            .po_s()
+            .iter()
+            .map(|(name, def)| (name, def, self.verdict(name)))
+            .collect();
+
+        let summary = if verdicts
+            .iter()
+            .any(|(_, _, v)| matches!(v, Verdict::Falsified(_, _) | Verdict::SpacerFalsified))
+        {
+            span("red", "unsafe")
+        } else if verdicts
+            .iter()
+            .all(|(_, _, v)| matches!(v, Verdict::Proved))
+        {
+            span("green", "safe")
+        } else {
+            span("red", "might be unsafe")
+        };
+        writeln!(w, "<h2>Verdicts</h2>")?;
+        writeln!(
+            w,
+            "<p>System is <span class=\"bold\">{}</span>.</p>",
+            summary
+        )?;
+
+        writeln!(w, "<table>")?;
+        writeln!(
+            w,
+            "<tr><th>candidate</th><th>definition</th><th>verdict</th></tr>"
+        )?;
+        for (name, def, verdict) in verdicts {
+            let verdict = match verdict {
+                Verdict::Proved => span("green", "proved"),
+                Verdict::Falsified(origin, cex) => span(
+                    "red",
+                    &format!(
+                        "falsified ({}, depth {})",
+                        origin.as_str(),
+                        cex.trace.keys().next_back().copied().unwrap_or(0),
+                    ),
+                ),
+                Verdict::SpacerFalsified => match self.bmc {
+                    Some((_, Some((first, last)))) => span(
+                        "red",
+                        &format!(
+                            "falsified (spacer, no counterexample in {})",
+                            crate::bmc_depths_desc(first, last)
+                        ),
+                    ),
+                    _ => span("red", "falsified (spacer)"),
+                },
+                Verdict::NotInductive(_) => match self.bmc {
+                    Some((_, Some((first, last)))) => span(
+                        "red",
+                        &format!(
+                            "not inductive, no falsification in {}",
+                            crate::bmc_depths_desc(first, last)
+                        ),
+                    ),
+                    _ => span("red", "not inductive"),
+                },
+                Verdict::Unknown => match self.bmc {
+                    Some((_, Some((first, last)))) => span(
+                        "red",
+                        &format!(
+                            "no falsification in {}",
+                            crate::bmc_depths_desc(first, last)
+                        ),
+                    ),
+                    _ => span("red", "unknown"),
+                },
+            };
+            writeln!(
+                w,
+                "<tr><td class=\"bold\">{}</td><td><code>{}</code></td><td>{}</td></tr>",
+                escape(name),
+                escape(&def.to_string()),
+                verdict,
+            )?;
+        }
+        writeln!(w, "</table>")?;
+        Ok(())
+    }
+
+    /// Writes the counterexamples as collapsible tables.
+    fn write_cexs(&self, w: &mut impl Write) -> Res<()> {
+        let cexs: Vec<_> = self
+            .sys
+            .po_s()
+            .keys()
+            .filter_map(|name| match self.verdict(name) {
+                Verdict::Falsified(origin, cex) => Some((name, origin.as_str(), false, cex)),
+                Verdict::NotInductive(cex) => Some((name, "step", true, cex)),
+                Verdict::Proved | Verdict::SpacerFalsified | Verdict::Unknown => None,
+            })
+            .collect();
+        if cexs.is_empty() {
+            return Ok(());
+        }
+
+        let vars: Vec<_> = self.sys.decls().all().collect();
+        writeln!(w, "<h2>Counterexamples</h2>")?;
+        for (name, origin, is_step, cex) in cexs {
+            writeln!(
+                w,
+                "<details>\n<summary><span class=\"red\">{}</span> ({})</summary>",
+                escape(name),
+                if is_step {
+                    "induction step counterexample"
+                } else {
+                    origin
+                },
+            )?;
+            writeln!(w, "<table>")?;
+            write!(w, "<tr><th>step</th>")?;
+            for var in &vars {
+                write!(w, "<th>{}</th>", escape(var.id()))?;
+            }
+            writeln!(w, "</tr>")?;
+            for (step, values) in &cex.trace {
+                let step = match (is_step, *step) {
+                    (false, step) => step.to_string(),
+                    (true, 0) => "k".into(),
+                    (true, step) => format!("k + {}", step),
+                };
+                write!(w, "<tr><td class=\"under\">{}</td>", step)?;
+                for var in &vars {
+                    let val = values.get(var).map(crate::cex::cst_to_cell);
+                    write!(
+                        w,
+                        "<td class=\"val\">{}</td>",
+                        escape(&val.unwrap_or_default())
+                    )?;
+                }
+                writeln!(w, "</tr>")?;
+            }
+            writeln!(w, "</table>")?;
+            if !cex.unexpected.is_empty() {
+                writeln!(
+                    w,
+                    "<p>The solver produced the following unexpected values:</p>\n<ul>"
+                )?;
+                for (desc, val) in &cex.unexpected {
+                    writeln!(
+                        w,
+                        "<li><code>{} = {}</code></li>",
+                        escape(desc),
+                        escape(val)
+                    )?;
+                }
+                writeln!(w, "</ul>")?;
+            }
+            writeln!(w, "</details>")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the run statistics.
+    fn write_stats(&self, w: &mut impl Write) -> Res<()> {
+        writeln!(w, "<h2>Statistics</h2>")?;
+        writeln!(w, "<table>")?;
+        writeln!(
+            w,
+            "<tr><td>state variables</td><td>{}</td></tr>",
+            self.sys.decls().all().count()
+        )?;
+        writeln!(
+            w,
+            "<tr><td>candidates</td><td>{}</td></tr>",
+            self.sys.po_s().len()
+        )?;
+        match self.bmc {
+            Some((_, Some((first, last)))) => writeln!(
+                w,
+                "<tr><td>BMC depths checked</td><td>{} to {}</td></tr>",
+                first, last
+            )?,
+            Some((_, None)) => writeln!(w, "<tr><td>BMC depths checked</td><td>none</td></tr>")?,
+            None => (),
+        }
+        let mut total = Duration::from_secs(0);
+        for (desc, time) in &self.times {
+            total += *time;
+            writeln!(
+                w,
+                "<tr><td>{} time</td><td>{:.3}s</td></tr>",
+                desc,
+                time.as_secs_f64()
+            )?;
+        }
+        writeln!(
+            w,
+            "<tr><td class=\"bold\">total time</td><td>{:.3}s</td></tr>",
+            total.as_secs_f64()
+        )?;
+        writeln!(w, "</table>")?;
+
+        if let Some(stats) = self.stats {
+            writeln!(w, "<table>")?;
+            writeln!(w, "<tr><th>engine</th><th>time</th></tr>")?;
+            for (engine, time) in &stats.engines {
+                writeln!(
+                    w,
+                    "<tr><td>{}</td><td>{:.3}s</td></tr>",
+                    engine,
+                    time.as_secs_f64()
+                )?;
+            }
+            for (depth, time) in &stats.bmc_depths {
+                writeln!(
+                    w,
+                    "<tr><td>bmc depth {}</td><td>{:.3}s</td></tr>",
+                    depth,
+                    time.as_secs_f64()
+                )?;
+            }
+            writeln!(w, "</table>")?;
+
+            writeln!(w, "<table>")?;
+            for (desc, val) in [
+                ("solver instances", stats.solvers.to_string()),
+                ("solver calls", stats.solver_calls.to_string()),
+                ("check-sats", stats.check_sats.to_string()),
+                (
+                    "check-sat time",
+                    format!("{:.3}s", stats.check_sat_time.as_secs_f64()),
+                ),
+                ("solver restarts", stats.restarts.to_string()),
+                (
+                    "peak counterexample length (states)",
+                    stats.peak_cex_len.to_string(),
+                ),
+            ] {
+                writeln!(w, "<tr><td>{}</td><td>{}</td></tr>", desc, val)?;
+            }
+            writeln!(w, "</table>")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the solvers that won the queries of the portfolio, if any.
+    fn write_wins(&self, w: &mut impl Write) -> Res<()> {
+        if self.wins.is_empty() {
+            return Ok(());
+        }
+        writeln!(w, "<h2>Portfolio</h2>")?;
+        writeln!(w, "<table>")?;
+        writeln!(w, "<tr><th>solver</th><th>wins</th><th>time</th></tr>")?;
+        for (name, (count, time)) in crate::portfolio::tally(self.wins) {
+            writeln!(
+                w,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{:.3}s</td></tr>",
+                escape(name),
+                count,
+                time.as_secs_f64()
+            )?;
+        }
+        writeln!(w, "</table>")?;
+        writeln!(w, "<table>")?;
+        writeln!(
+            w,
+            "<tr><th>engine</th><th>query</th><th>winner</th><th>time</th></tr>"
+        )?;
+        for win in self.wins {
+            writeln!(
+                w,
+                "<tr><td>{}</td><td>{}</td><td><code>{}</code></td><td>{:.3}s</td></tr>",
+                win.engine,
+                escape(&win.query),
+                escape(&win.winner),
+                win.time.as_secs_f64()
+            )?;
+        }
+        writeln!(w, "</table>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spacer::{SpacerRes, Verdict as SpacerVerdict};
+    use check::CheckRes;
+    use expr::{Cst, Var};
+    use std::collections::BTreeMap as Map;
+
+    /// System with candidates `a` and `<&"b">`.
+    fn sys() -> Sys {
+        let sys = parse::trans(
+            "svars { x: int } init { x = 0 } trans { 'x = x + 1 } \
+            candidates { \"a\": x ≥ 0, \"b\": x ≤ 3 }",
+        )
+        .unwrap();
+        let mut po_s = sys.po_s().clone();
+        let b = po_s.remove("b").unwrap();
+        po_s.insert("<&\"b\">".into(), b);
+        Sys::new(
+            sys.decls().clone(),
+            sys.init().clone(),
+            sys.trans().clone(),
+            po_s,
+        )
+    }
+
+    /// Check result with a counterexample of some depth for some candidates.
+    fn res<'sys>(sys: &'sys Sys, cexs: &[(&str, usize)]) -> CheckRes<'sys> {
+        let mut res = CheckRes::new(sys);
+        for (name, depth) in cexs {
+            let (name, _) = sys.po_s().get_key_value(*name).unwrap();
+            let mut cex = Cex::new();
+            for step in 0..=*depth {
+                cex.insert(step, Var::new("x", Typ::Int), Cst::I(step.into()))
+                    .unwrap();
+            }
+            res.okay.remove(name);
+            res.cexs.insert(name, cex);
+        }
+        res
+    }
+
+    fn new_report<'a, 'sys>(
+        sys: &'sys Sys,
+        base: Option<&'a BaseRes<'sys>>,
+        step: Option<&'a StepRes<'sys>>,
+        spacer: Option<&'a SpacerRes<'sys>>,
+        bmc: Option<(&'a BmcRes<'sys>, Option<(Unroll, Unroll)>)>,
+    ) -> Report<'a, 'sys> {
+        Report {
+            sys,
+            input: "<sys>.mkn",
+            src: "",
+            base,
+            step,
+            spacer,
+            bmc,
+            times: vec![],
+            stats: None,
+            wins: &[],
+        }
+    }
+
+    fn spacer<'sys>(sys: &'sys Sys, falsified: bool) -> SpacerRes<'sys> {
+        let mut verdicts = Map::new();
+        for name in sys.po_s().keys() {
+            let verdict = if name == "a" {
+                SpacerVerdict::Proved(None)
+            } else if falsified {
+                SpacerVerdict::Falsified
+            } else {
+                SpacerVerdict::Unknown
+            };
+            verdicts.insert(name, verdict);
+        }
+        SpacerRes { verdicts }
+    }
+
+    const B: &str = "<&\"b\">";
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            escape("<a href=\"x\">&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape("x ≥ 0"), "x ≥ 0");
+    }
+
+    #[test]
+    fn highlighting() {
+        assert_eq!(
+            highlight("svars // <&\n'x = \"a<b\" + 1 & y"),
+            "<span class=\"bold\">svars</span> <span class=\"gray\">// &lt;&amp;</span>\n\
+            <span class=\"under\">&#39;</span>x = <span class=\"code\">&quot;a&lt;b&quot;</span> + \
+            <span class=\"code\">1</span> &amp; y",
+        );
+    }
+
+    #[test]
+    fn induction_verdicts() {
+        use crate::cex::Origin;
+        let sys = sys();
+
+        let base: BaseRes = res(&sys, &[(B, 0)]).into();
+        let step: StepRes = res(&sys, &[(B, 1)]).into();
+        let report = new_report(&sys, Some(&base), Some(&step), None, None);
+        assert!(matches!(report.verdict("a"), Verdict::Proved));
+        assert!(matches!(
+            report.verdict(B),
+            Verdict::Falsified(Origin::Base, _)
+        ));
+
+        let base: BaseRes = res(&sys, &[]).into();
+        let step: StepRes = res(&sys, &[("a", 1), (B, 1)]).into();
+        let bmc: BmcRes = res(&sys, &[(B, 3)]).into();
+        let report = new_report(
+            &sys,
+            Some(&base),
+            Some(&step),
+            None,
+            Some((&bmc, Some((0, 5)))),
+        );
+        assert!(matches!(report.verdict("a"), Verdict::NotInductive(_)));
+        match report.verdict(B) {
+            Verdict::Falsified(Origin::Bmc, cex) => assert_eq!(cex.trace.len(), 4),
+            _ => panic!("expected a BMC falsification"),
+        }
+
+        let bmc: BmcRes = res(&sys, &[]).into();
+        let report = new_report(&sys, None, None, None, Some((&bmc, Some((0, 5)))));
+        assert!(matches!(report.verdict("a"), Verdict::Unknown));
+    }
+
+    #[test]
+    fn spacer_verdicts() {
+        use crate::cex::Origin;
+        let sys = sys();
+
+        let spacer_res = spacer(&sys, true);
+        let bmc: BmcRes = res(&sys, &[]).into();
+        let report = new_report(
+            &sys,
+            None,
+            None,
+            Some(&spacer_res),
+            Some((&bmc, Some((0, 5)))),
+        );
+        assert!(matches!(report.verdict("a"), Verdict::Proved));
+        assert!(matches!(report.verdict(B), Verdict::SpacerFalsified));
+
+        let bmc: BmcRes = res(&sys, &[(B, 4)]).into();
+        let report = new_report(
+            &sys,
+            None,
+            None,
+            Some(&spacer_res),
+            Some((&bmc, Some((0, 5)))),
+        );
+        assert!(matches!(
+            report.verdict(B),
+            Verdict::Falsified(Origin::Bmc, _)
+        ));
+
+        let spacer_res = spacer(&sys, false);
+        let report = new_report(&sys, None, None, Some(&spacer_res), None);
+        assert!(matches!(report.verdict(B), Verdict::Unknown));
+    }
+
+    #[test]
+    fn spacer_falsification_without_cex() {
+        let sys = sys();
+        let spacer_res = spacer(&sys, true);
+        let bmc: BmcRes = res(&sys, &[]).into();
+        let mut report = new_report(
+            &sys,
+            None,
+            None,
+            Some(&spacer_res),
+            Some((&bmc, Some((0, 5)))),
+        );
+        report.src = "candidates { \"<&\": x > 0 }";
+        let mut bytes = vec![];
+        report.write_to(&mut bytes).unwrap();
+        let html = String::from_utf8(bytes).unwrap();
+
+        assert!(html.contains("System is <span class=\"bold\"><span class=\"red\">unsafe</span>"));
+        assert!(html.contains(
+            "<td class=\"bold\">&lt;&amp;&quot;b&quot;&gt;</td><td><code>(&lt;= x 3)</code></td>\
+            <td><span class=\"red\">falsified (spacer, no counterexample in 5 step(s) or less)\
+            </span></td>"
+        ));
+        assert!(html.contains("<title>mikino report: &lt;sys&gt;.mkn</title>"));
+        assert!(html.contains(
+            "<span class=\"code\">&quot;&lt;&amp;&quot;</span>: x &gt; <span class=\"code\">0</span>"
+        ));
+        assert!(!html.contains(B));
+        assert!(!html.contains("<h2>Counterexamples</h2>"));
+        assert!(html.contains("<tr><td>BMC depths checked</td><td>0 to 5</td></tr>"));
+    }
+
+    #[test]
+    fn no_depth_checked() {
+        let sys = sys();
+        let spacer_res = spacer(&sys, true);
+        let bmc: BmcRes = res(&sys, &[]).into();
+        let report = new_report(&sys, None, None, Some(&spacer_res), Some((&bmc, None)));
+        let mut bytes = vec![];
+        report.write_to(&mut bytes).unwrap();
+        let html = String::from_utf8(bytes).unwrap();
+
+        assert!(html.contains("<tr><td>BMC depths checked</td><td>none</td></tr>"));
+        assert!(html.contains("<td><span class=\"red\">falsified (spacer)</span></td>"));
+    }
+}
@@ -0,0 +1,39 @@
+//! System import from the input formats of other model-checkers.
+
+mikino_api::prelude!();
+
+use trans::Sys;
+
+pub mod aiger;
+pub mod sexp;
+pub mod vmt;
+
+/// Extension of a file, if any.
+fn extension(input: &str) -> Option<&str> {
+    std::path::Path::new(input)
+        .extension()
+        .and_then(|ext| ext.to_str())
+}
+
+/// True if the format of `input`, decided by its extension, is binary.
+pub fn is_binary(input: &str) -> bool {
+    extension(input) == Some("aig")
+}
+
+/// Parses a system, the format is decided by the extension of the `input` file.
+///
+/// - `vmt`: VMT-LIB system;
+/// - `aag`/`aig`: ASCII/binary AIGER circuit;
+/// - anything else: mikino system.
+pub fn parse(input: &str, bytes: &[u8]) -> Res<Sys> {
+    let txt =
+        || std::str::from_utf8(bytes).map_err(|_| format!("file `{}` is not valid UTF-8", input));
+    match extension(input) {
+        Some("vmt") => {
+            vmt::parse(txt()?).chain_err(|| format!("while parsing VMT-LIB file `{}`", input))
+        }
+        Some(ext @ ("aag" | "aig")) => aiger::parse(bytes, ext == "aig")
+            .chain_err(|| format!("while parsing AIGER file `{}`", input)),
+        _ => Ok(parse::trans(txt()?)?),
+    }
+}
@@ -0,0 +1,651 @@
+//! AIGER import, ASCII (`aag`) and binary (`aig`).
+//!
+//! - latches become boolean state variables, initialized to their reset value if any;
+//! - inputs become boolean state variables unconstrained by the transition relation;
+//! - bad-state properties, or outputs if there are none, become candidates stating the bad state
+//!   is not reachable;
+//! - invariant constraints are assumed to hold in all states.
+//!
+//! AND gates used more than once become auxiliary state variables, defined in the initial
+//! predicate and in the transition relation for both the current and the next state. Other AND
+//! gates are inlined. This keeps the size of the system linear in the size of the circuit.
+//!
+//! Justice and fairness properties are rejected.
+
+mikino_api::prelude!();
+
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+use expr::{Cst, Expr, Op, PExpr, SExpr, SVar, Var};
+use trans::{Decls, Sys};
+
+/// An AIGER literal: twice a variable index, plus one if negated.
+type Lit = usize;
+
+/// A latch: its literal, the literal of its next value, and its reset literal.
+#[derive(Debug, Clone, Copy)]
+struct Latch {
+    lit: Lit,
+    next: Lit,
+    reset: Lit,
+}
+
+/// An AND gate: `lhs = rhs_0 ⋀ rhs_1`.
+#[derive(Debug, Clone, Copy)]
+struct And {
+    lhs: Lit,
+    rhs: [Lit; 2],
+}
+
+/// An and-inverter graph.
+#[derive(Debug, Default)]
+struct Aig {
+    max_var: usize,
+    inputs: Vec<Lit>,
+    latches: Vec<Latch>,
+    outputs: Vec<Lit>,
+    bads: Vec<Lit>,
+    constraints: Vec<Lit>,
+    ands: Vec<And>,
+    /// Symbol table: maps `i`, `l`, `o` and `b` and an index to a name.
+    symbols: Map<(char, usize), String>,
+}
+
+/// Reads an AIGER file, binary if `binary`.
+pub fn parse(bytes: &[u8], binary: bool) -> Res<Sys> {
+    Reader::new(bytes).aig(binary)?.into_sys()
+}
+
+/// Byte-level AIGER reader.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    /// Reads a line, without the newline.
+    fn line(&mut self, desc: &str) -> Res<&'a str> {
+        if self.pos >= self.bytes.len() {
+            bail!(
+                "line {}: unexpected end of file, expected {}",
+                self.line,
+                desc
+            )
+        }
+        let start = self.pos;
+        while self.pos < self.bytes.len() && self.bytes[self.pos] != b'\n' {
+            self.pos += 1
+        }
+        let line = &self.bytes[start..self.pos];
+        // Skip newline.
+        self.pos += 1;
+        self.line += 1;
+        std::str::from_utf8(line)
+            .map(|line| line.trim_end_matches('\r'))
+            .map_err(|_| format!("line {}: expected {}", self.line - 1, desc).into())
+    }
+
+    /// Reads a line of whitespace-separated unsigned integers.
+    fn nums(&mut self, desc: &str, min: usize, max: usize) -> Res<Vec<usize>> {
+        let line = self.line(desc)?;
+        let nums = line
+            .split_whitespace()
+            .map(|n| n.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                format!(
+                    "line {}: expected {}, found `{}`",
+                    self.line - 1,
+                    desc,
+                    line
+                )
+            })?;
+        if nums.len() < min || nums.len() > max {
+            bail!(
+                "line {}: expected {}, found `{}`",
+                self.line - 1,
+                desc,
+                line
+            )
+        }
+        Ok(nums)
+    }
+
+    /// Reads an unsigned LEB128-style integer, for binary AND gates.
+    fn varint(&mut self) -> Res<usize> {
+        let (mut res, mut shift) = (0usize, 0);
+        loop {
+            let byte = *self
+                .bytes
+                .get(self.pos)
+                .ok_or("unexpected end of file in binary AND gates")?;
+            self.pos += 1;
+            res |= ((byte & 0x7f) as usize)
+                .checked_shl(shift)
+                .ok_or("illegal binary AND gate encoding")?;
+            if byte & 0x80 == 0 {
+                return Ok(res);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads a whole and-inverter graph.
+    fn aig(mut self, binary: bool) -> Res<Aig> {
+        let header = self.line("AIGER header")?;
+        let mut header = header.split_whitespace();
+        let magic = if binary { "aig" } else { "aag" };
+        if header.next() != Some(magic) {
+            bail!("line 1: expected AIGER header starting with `{}`", magic)
+        }
+        let header = header
+            .map(|n| n.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "line 1: illegal AIGER header")?;
+        if header.len() < 5 || header.len() > 9 {
+            bail!("line 1: illegal AIGER header, expected `M I L O A [B C J F]`")
+        }
+        let count = |idx: usize| header.get(idx).cloned().unwrap_or(0);
+        let (max_var, n_inputs, n_latches, n_outputs, n_ands) =
+            (count(0), count(1), count(2), count(3), count(4));
+        let (n_bads, n_constraints, n_justice, n_fairness) =
+            (count(5), count(6), count(7), count(8));
+        if n_justice > 0 || n_fairness > 0 {
+            bail!("justice and fairness properties (liveness) are not supported")
+        }
+        let n_defined = n_inputs
+            .checked_add(n_latches)
+            .and_then(|n| n.checked_add(n_ands))
+            .ok_or("line 1: illegal AIGER header, `I + L + A` overflows")?;
+        if max_var < n_defined {
+            bail!(
+                "line 1: illegal AIGER header, maximum variable index `{}` is less than `I + L + A`",
+                max_var
+            )
+        }
+        if binary && max_var != n_defined {
+            bail!(
+                "line 1: illegal binary AIGER header, maximum variable index `{}` must be `I + L + A`",
+                max_var
+            )
+        }
+        // Binary inputs and latches take no space in the file, a header announcing more of them
+        // than the file has bytes is malformed and must not make us allocate for nothing. ASCII
+        // files have a line per input, latch and gate, and may not use all variable indices.
+        if binary && max_var > self.bytes.len() {
+            bail!(
+                "line 1: illegal AIGER header, maximum variable index `{}` is too large for a file \
+                of {} bytes",
+                max_var,
+                self.bytes.len()
+            )
+        }
+
+        let mut aig = Aig {
+            max_var,
+            ..Aig::default()
+        };
+
+        for idx in 0..n_inputs {
+            if binary {
+                aig.inputs.push(2 * (idx + 1))
+            } else {
+                aig.inputs.push(self.nums("an input", 1, 1)?[0])
+            }
+        }
+        for idx in 0..n_latches {
+            let (lit, rest) = if binary {
+                (2 * (n_inputs + idx + 1), self.nums("a latch", 1, 2)?)
+            } else {
+                let mut nums = self.nums("a latch", 2, 3)?;
+                (nums.remove(0), nums)
+            };
+            let reset = rest.get(1).cloned().unwrap_or(0);
+            if reset != 0 && reset != 1 && reset != lit {
+                bail!(
+                    "line {}: latch reset value must be `0`, `1` or the latch itself",
+                    self.line - 1
+                )
+            }
+            aig.latches.push(Latch {
+                lit,
+                next: rest[0],
+                reset,
+            })
+        }
+        for _ in 0..n_outputs {
+            aig.outputs.push(self.nums("an output", 1, 1)?[0])
+        }
+        for _ in 0..n_bads {
+            aig.bads.push(self.nums("a bad state property", 1, 1)?[0])
+        }
+        for _ in 0..n_constraints {
+            aig.constraints
+                .push(self.nums("an invariant constraint", 1, 1)?[0])
+        }
+        for idx in 0..n_ands {
+            if binary {
+                let lhs = 2 * (n_inputs + n_latches + idx + 1);
+                let delta_0 = self.varint()?;
+                let delta_1 = self.varint()?;
+                let rhs_0 = lhs
+                    .checked_sub(delta_0)
+                    .ok_or("illegal binary AND gate encoding")?;
+                let rhs_1 = rhs_0
+                    .checked_sub(delta_1)
+                    .ok_or("illegal binary AND gate encoding")?;
+                aig.ands.push(And {
+                    lhs,
+                    rhs: [rhs_0, rhs_1],
+                })
+            } else {
+                let nums = self.nums("an AND gate", 3, 3)?;
+                aig.ands.push(And {
+                    lhs: nums[0],
+                    rhs: [nums[1], nums[2]],
+                })
+            }
+        }
+
+        // Symbol table, until the comment section or the end of the file.
+        while self.pos < self.bytes.len() {
+            let line = self.line("a symbol")?;
+            if line == "c" {
+                break;
+            }
+            let mut chars = line.chars();
+            let kind = chars.next();
+            let rest = chars.as_str();
+            let (idx, name) = rest.split_once(' ').unwrap_or((rest, ""));
+            match (kind, idx.parse::<usize>()) {
+                (Some(kind @ ('i' | 'l' | 'o' | 'b' | 'c' | 'j' | 'f')), Ok(idx)) => {
+                    aig.symbols.insert((kind, idx), name.to_string());
+                }
+                _ if line.is_empty() => (),
+                _ => bail!("line {}: illegal symbol `{}`", self.line - 1, line),
+            }
+        }
+
+        Ok(aig)
+    }
+}
+
+/// Turns a symbol into a legal, unused mikino identifier, `default` if there is no symbol.
+fn ident(symbol: Option<&String>, default: String, used: &mut Set<String>) -> String {
+    let mut id: String = symbol
+        .map(|s| {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or(default);
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.insert(0, '_')
+    }
+    if parse::is_kw(&id) || id == "true" || id == "false" {
+        id.push('_')
+    }
+    let mut res = id.clone();
+    let mut cnt = 2;
+    while used.contains(&res) {
+        res = format!("{}_{}", id, cnt);
+        cnt += 1;
+    }
+    used.insert(res.clone());
+    res
+}
+
+/// Turns a stateless expression into a stateful one, over the current or next state variables.
+fn to_sexpr(expr: &Expr, next: bool) -> SExpr {
+    expr.fold(
+        |var| PExpr::Var(SVar::new(var.clone(), next)),
+        |cst| PExpr::Cst(cst.clone()),
+        |op, args| PExpr::App { op, args },
+    )
+}
+
+/// Conjunction of some expressions.
+fn conj<V>(mut exprs: Vec<PExpr<V>>) -> PExpr<V> {
+    match exprs.len() {
+        0 => PExpr::Cst(Cst::B(true)),
+        1 => exprs.pop().expect("[unreachable] pop on vec of len `1`"),
+        _ => PExpr::App {
+            op: Op::And,
+            args: exprs,
+        },
+    }
+}
+
+/// Negation of an expression.
+fn not<V>(expr: PExpr<V>) -> PExpr<V> {
+    match expr {
+        PExpr::Cst(Cst::B(b)) => PExpr::Cst(Cst::B(!b)),
+        PExpr::App {
+            op: Op::Not,
+            mut args,
+        } if args.len() == 1 => args.pop().expect("[unreachable] pop on vec of len `1`"),
+        expr => PExpr::App {
+            op: Op::Not,
+            args: vec![expr],
+        },
+    }
+}
+
+impl Aig {
+    /// Builds the system.
+    fn into_sys(self) -> Res<Sys> {
+        let check_lit = |lit: Lit| -> Res<()> {
+            if lit / 2 > self.max_var {
+                bail!(
+                    "literal `{}` is above maximum variable index `{}`",
+                    lit,
+                    self.max_var
+                )
+            }
+            Ok(())
+        };
+
+        let mut decls = Decls::new();
+        let mut used = Set::new();
+        // Expression for each variable index, gates not handled yet are absent. Indices are
+        // sparse in ASCII files, hence the map.
+        let mut exprs: Map<usize, Expr> = Map::new();
+        exprs.insert(0, PExpr::Cst(Cst::B(false)));
+
+        let mut new_var = |exprs: &mut Map<usize, Expr>, lit: Lit, id: String| -> Res<Var> {
+            check_lit(lit)?;
+            if lit & 1 == 1 || lit == 0 || exprs.contains_key(&(lit / 2)) {
+                bail!("illegal or redefined literal `{}`", lit)
+            }
+            decls.register(id.clone(), Typ::Bool);
+            let var = Var::new(id, Typ::Bool);
+            exprs.insert(lit / 2, PExpr::Var(var.clone()));
+            Ok(var)
+        };
+
+        for (idx, lit) in self.inputs.iter().enumerate() {
+            let id = ident(
+                self.symbols.get(&('i', idx)),
+                format!("i{}", idx),
+                &mut used,
+            );
+            new_var(&mut exprs, *lit, id)?;
+        }
+        let mut latch_vars = Vec::with_capacity(self.latches.len());
+        for (idx, latch) in self.latches.iter().enumerate() {
+            let id = ident(
+                self.symbols.get(&('l', idx)),
+                format!("l{}", idx),
+                &mut used,
+            );
+            latch_vars.push(new_var(&mut exprs, latch.lit, id)?);
+        }
+
+        // AND gates by variable index, and how many times each variable is used.
+        let mut ands: Map<usize, And> = Map::new();
+        let mut uses: Map<usize, usize> = Map::new();
+        for and in &self.ands {
+            check_lit(and.lhs)?;
+            if and.lhs & 1 == 1
+                || exprs.contains_key(&(and.lhs / 2))
+                || ands.contains_key(&(and.lhs / 2))
+            {
+                bail!("illegal or redefined AND gate literal `{}`", and.lhs)
+            }
+            for rhs in and.rhs {
+                check_lit(rhs)?;
+                *uses.entry(rhs / 2).or_insert(0) += 1
+            }
+            ands.insert(and.lhs / 2, *and);
+        }
+        let roots = self
+            .latches
+            .iter()
+            .map(|latch| latch.next)
+            .chain(self.outputs.iter().cloned())
+            .chain(self.bads.iter().cloned())
+            .chain(self.constraints.iter().cloned());
+        for lit in roots {
+            check_lit(lit)?;
+            *uses.entry(lit / 2).or_insert(0) += 1
+        }
+
+        // Definitions of the gates that become auxiliary state variables.
+        let mut gate_defs: Vec<(Var, Expr)> = vec![];
+
+        // Handle gates in topological order, using an explicit stack.
+        let lit_expr = |exprs: &Map<usize, Expr>, lit: Lit| -> Option<Expr> {
+            exprs
+                .get(&(lit / 2))
+                .cloned()
+                .map(|expr| if lit & 1 == 0 { expr } else { not(expr) })
+        };
+        for root in ands.keys().cloned().collect::<Vec<_>>() {
+            let mut stack = vec![(root, false)];
+            let mut visiting = Set::new();
+            while let Some((var, kids_done)) = stack.pop() {
+                if exprs.contains_key(&var) {
+                    continue;
+                }
+                let and = match ands.get(&var) {
+                    Some(and) => *and,
+                    None => bail!("literal `{}` is used but never defined", 2 * var),
+                };
+                if !kids_done {
+                    if !visiting.insert(var) {
+                        bail!("AND gate `{}` is cyclic", 2 * var)
+                    }
+                    stack.push((var, true));
+                    for rhs in and.rhs {
+                        if !exprs.contains_key(&(rhs / 2)) {
+                            stack.push((rhs / 2, false))
+                        }
+                    }
+                    continue;
+                }
+                let args: Vec<_> = and
+                    .rhs
+                    .iter()
+                    .map(|rhs| {
+                        lit_expr(&exprs, *rhs)
+                            .ok_or_else(|| format!("AND gate `{}` is cyclic", 2 * var))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let def = PExpr::App { op: Op::And, args };
+                if uses.get(&var).copied().unwrap_or(0) > 1 {
+                    let id = ident(None, format!("g{}", var), &mut used);
+                    decls.register(id.clone(), Typ::Bool);
+                    let gate = Var::new(id, Typ::Bool);
+                    gate_defs.push((gate.clone(), def));
+                    exprs.insert(var, PExpr::Var(gate));
+                } else {
+                    exprs.insert(var, def);
+                }
+            }
+        }
+        let lit_expr = |lit: Lit| -> Res<Expr> {
+            lit_expr(&exprs, lit)
+                .ok_or_else(|| format!("literal `{}` is never defined", lit).into())
+        };
+
+        // Gate definitions, in the current state.
+        let defs: Vec<Expr> = gate_defs
+            .iter()
+            .map(|(gate, def)| PExpr::App {
+                op: Op::Eq,
+                args: vec![PExpr::Var(gate.clone()), def.clone()],
+            })
+            .collect();
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|lit| lit_expr(*lit))
+            .collect::<Res<Vec<_>>>()?;
+
+        let mut init = defs.clone();
+        for (latch, var) in self.latches.iter().zip(&latch_vars) {
+            if latch.reset == latch.lit {
+                continue;
+            }
+            let var = PExpr::Var(var.clone());
+            init.push(if latch.reset == 1 { var } else { not(var) })
+        }
+        init.extend(constraints.iter().cloned());
+        let init = conj(init);
+
+        let mut trans = vec![];
+        for def in &defs {
+            trans.push(to_sexpr(def, false));
+            trans.push(to_sexpr(def, true));
+        }
+        for (latch, var) in self.latches.iter().zip(&latch_vars) {
+            trans.push(PExpr::App {
+                op: Op::Eq,
+                args: vec![
+                    PExpr::Var(SVar::new_next(var.clone())),
+                    to_sexpr(&lit_expr(latch.next)?, false),
+                ],
+            })
+        }
+        for constraint in &constraints {
+            trans.push(to_sexpr(constraint, true))
+        }
+        let trans = conj(trans);
+
+        let (kind, desc, props) = if self.bads.is_empty() {
+            ('o', "output", &self.outputs)
+        } else {
+            ('b', "bad", &self.bads)
+        };
+        if props.is_empty() {
+            bail!("no bad state property or output found, nothing to check")
+        }
+        let mut po_s = Map::new();
+        for (idx, lit) in props.iter().enumerate() {
+            let name = match self.symbols.get(&(kind, idx)) {
+                Some(name) if !name.is_empty() => format!("{} {} ({})", desc, idx, name),
+                _ => format!("{} {}", desc, idx),
+            };
+            po_s.insert(name, not(lit_expr(*lit)?));
+        }
+
+        Ok(Sys::new(decls, init, trans, po_s))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Toggling latch `l`, bad when it is set.
+    const TOGGLE_AAG: &str = "aag 1 0 1 0 0 1\n2 3\n2\nl0 l\n";
+    /// Same as [`TOGGLE_AAG`], in the binary format.
+    const TOGGLE_AIG: &[u8] = b"aig 1 0 1 0 0 1\n3\n2\nl0 l\n";
+
+    fn err(bytes: &[u8], binary: bool) -> String {
+        match parse(bytes, binary) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn ascii() {
+        let sys = parse(TOGGLE_AAG.as_bytes(), false).unwrap();
+        let vars: Vec<_> = sys.decls().all().map(|var| var.id().to_string()).collect();
+        assert_eq!(vars, vec!["l"]);
+        let po_s: Vec<_> = sys.po_s().keys().cloned().collect();
+        assert_eq!(po_s, vec!["bad 0"]);
+    }
+
+    #[test]
+    fn binary_matches_ascii() {
+        let ascii = parse(TOGGLE_AAG.as_bytes(), false).unwrap();
+        let binary = parse(TOGGLE_AIG, true).unwrap();
+        assert_eq!(ascii.init().to_string(), binary.init().to_string());
+        assert_eq!(ascii.trans().to_string(), binary.trans().to_string());
+        assert_eq!(
+            ascii.po_s().keys().collect::<Vec<_>>(),
+            binary.po_s().keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn shared_gates_become_variables() {
+        // `g = i0 ⋀ i1` feeds both outputs.
+        let sys = parse(b"aag 3 2 0 2 1\n2\n4\n6\n7\n6 2 4\n", false).unwrap();
+        assert_eq!(sys.decls().all().count(), 3);
+        assert_eq!(sys.po_s().len(), 2);
+    }
+
+    #[test]
+    fn sparse_ascii() {
+        // Variable indices `1` to `4` and `6` to `8` are unused, `9 = i ⋀ ¬l`.
+        let sys = parse(
+            b"aag 9 1 1 0 1 1\n10\n14 18\n14\n18 10 15\ni0 i\nl0 l\n",
+            false,
+        )
+        .unwrap();
+        let vars: Vec<_> = sys.decls().all().map(|var| var.id().to_string()).collect();
+        assert_eq!(vars, vec!["i", "l"]);
+        assert_eq!(sys.trans().to_string(), "(= l@1 (and i@0 (not l@0)))");
+
+        let sys = parse(b"aag 18446744073709551615 0 0 1 0\n0\n", false).unwrap();
+        assert_eq!(sys.po_s().len(), 1);
+    }
+
+    #[test]
+    fn header_overflow() {
+        let e = err(
+            b"aig 18446744073709551615 18446744073709551615 0 1 0\n0\n",
+            true,
+        );
+        assert!(e.contains("too large"), "{}", e);
+        let e = err(b"aag 5 18446744073709551615 1 1 0\n", false);
+        assert!(e.contains("overflows"), "{}", e);
+    }
+
+    #[test]
+    fn header_too_large_for_file() {
+        let e = err(b"aig 1000000000 1000000000 0 1 0\n2\n", true);
+        assert!(e.contains("too large"), "{}", e);
+    }
+
+    #[test]
+    fn header_inconsistent() {
+        let e = err(b"aag 1 1 1 1 0\n2\n4 2\n2\n", false);
+        assert!(e.contains("less than"), "{}", e);
+        let e = err(b"aig 3 0 1 0 0 1\n3\n2\n", true);
+        assert!(e.contains("must be"), "{}", e);
+    }
+
+    #[test]
+    fn malformed_body() {
+        // Literal above the maximum variable index.
+        let e = err(b"aag 1 1 0 1 0\n2\n8\n", false);
+        assert!(e.contains("above maximum"), "{}", e);
+        // Missing output line.
+        let e = err(b"aag 1 1 0 1 0\n2\n", false);
+        assert!(e.contains("end of file"), "{}", e);
+        // Cyclic gates.
+        let e = err(b"aag 2 0 0 1 2\n2\n2 4 1\n4 2 1\n", false);
+        assert!(e.contains("cyclic"), "{}", e);
+        // Liveness.
+        let e = err(b"aag 1 1 0 0 0 0 0 1 0\n2\n", false);
+        assert!(e.contains("liveness"), "{}", e);
+    }
+}
@@ -0,0 +1,150 @@
+//! S-expression parsing, for SMT-LIB-based input formats.
+
+mikino_api::prelude!();
+
+/// An s-expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sexp {
+    /// An atom: symbol, keyword, numeral, decimal or string literal, with its line.
+    Atom(String, usize),
+    /// A list, with the line of its opening parenthesis.
+    List(Vec<Sexp>, usize),
+}
+impl Sexp {
+    /// Line of the s-expression, starts at `1`.
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Atom(_, line) | Self::List(_, line) => *line,
+        }
+    }
+
+    /// Atom accessor.
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            Self::Atom(atom, _) => Some(atom),
+            Self::List(_, _) => None,
+        }
+    }
+
+    /// List accessor.
+    pub fn as_list(&self) -> Option<&[Sexp]> {
+        match self {
+            Self::Atom(_, _) => None,
+            Self::List(list, _) => Some(list),
+        }
+    }
+
+    /// Atom accessor, fails with an error mentioning `desc` if not an atom.
+    pub fn atom(&self, desc: &str) -> Res<&str> {
+        self.as_atom().ok_or_else(|| {
+            format!("line {}: expected {}, found `{}`", self.line(), desc, self).into()
+        })
+    }
+
+    /// List accessor, fails with an error mentioning `desc` if not a list.
+    pub fn list(&self, desc: &str) -> Res<&[Sexp]> {
+        self.as_list().ok_or_else(|| {
+            format!("line {}: expected {}, found `{}`", self.line(), desc, self).into()
+        })
+    }
+}
+impl std::fmt::Display for Sexp {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Atom(atom, _) => atom.fmt(fmt),
+            Self::List(list, _) => {
+                write!(fmt, "(")?;
+                for (idx, elm) in list.iter().enumerate() {
+                    if idx > 0 {
+                        write!(fmt, " ")?
+                    }
+                    elm.fmt(fmt)?
+                }
+                write!(fmt, ")")
+            }
+        }
+    }
+}
+
+/// Parses a sequence of s-expressions.
+///
+/// Handles `;` comments, `|quoted symbols|` and `"string literals"`. Quoted symbols keep their
+/// `|` delimiters.
+pub fn parse(txt: &str) -> Res<Vec<Sexp>> {
+    let mut chars = txt.chars().peekable();
+    let mut line = 1;
+    // Stack of lists being parsed, with the line they start at.
+    let mut stack: Vec<(Vec<Sexp>, usize)> = vec![];
+    let mut res = vec![];
+
+    macro_rules! push {
+        ($sexp:expr) => {{
+            let sexp = $sexp;
+            if let Some((list, _)) = stack.last_mut() {
+                list.push(sexp)
+            } else {
+                res.push(sexp)
+            }
+        }};
+    }
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\n' => line += 1,
+            _ if char.is_whitespace() => (),
+            ';' => {
+                for char in chars.by_ref() {
+                    if char == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+            '(' => stack.push((vec![], line)),
+            ')' => {
+                let (list, start) = stack
+                    .pop()
+                    .ok_or_else(|| format!("line {}: unexpected closing parenthesis", line))?;
+                push!(Sexp::List(list, start))
+            }
+            '|' | '"' => {
+                let start = line;
+                let mut atom = char.to_string();
+                loop {
+                    let next = chars
+                        .next()
+                        .ok_or_else(|| format!("line {}: unclosed `{}` delimiter", start, char))?;
+                    if next == '\n' {
+                        line += 1
+                    }
+                    atom.push(next);
+                    if next == char {
+                        // `""` is an escaped double quote in string literals.
+                        if char == '"' && chars.peek() == Some(&'"') {
+                            atom.push(chars.next().expect("[unreachable] peeked char"));
+                            continue;
+                        }
+                        break;
+                    }
+                }
+                push!(Sexp::Atom(atom, start))
+            }
+            _ => {
+                let mut atom = char.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || ['(', ')', '|', '"', ';'].contains(next) {
+                        break;
+                    }
+                    atom.push(*next);
+                    chars.next();
+                }
+                push!(Sexp::Atom(atom, line))
+            }
+        }
+    }
+
+    if let Some((_, start)) = stack.pop() {
+        bail!("line {}: unclosed parenthesis", start)
+    }
+    Ok(res)
+}
@@ -0,0 +1,706 @@
+//! VMT-LIB import.
+//!
+//! Supports the subset of VMT-LIB mikino can represent:
+//!
+//! - `Bool`, `Int` and `Real` state variables, declared with `declare-fun` or `declare-const`;
+//! - parameter-less `define-fun`s, annotated or not with `:next`, `:init`, `:trans` and
+//!   `:invar-property`;
+//! - boolean and linear/non-linear integer/real arithmetic, `let`-bindings and `!` annotations.
+//!
+//! Declared symbols that are not the current or next version of a state variable are inputs. They
+//! become state variables unconstrained by the transition relation. Everything else (bit-vectors,
+//! arrays, uninterpreted functions, liveness properties...) is rejected with an error.
+
+mikino_api::prelude!();
+
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+use super::sexp::{self, Sexp};
+use expr::{Cst, Expr, Op, PExpr, SExpr, SVar, Var};
+use trans::{Decls, Sys};
+
+/// Parses a VMT-LIB system.
+pub fn parse(txt: &str) -> Res<Sys> {
+    let sexps = sexp::parse(txt)?;
+    let mut vmt = Vmt::new();
+    for sexp in &sexps {
+        vmt.command(sexp)?
+    }
+    vmt.into_sys()
+}
+
+/// Translates a stateless SMT-LIB term, `vars` maps symbols to the variables they stand for.
+///
+/// Used to read back terms produced by solvers, such as invariants.
+pub fn term<'a>(sexp: &'a Sexp, vars: &'a Map<String, Var>) -> Res<Expr> {
+    let mut vmt = Vmt::new();
+    for (symbol, var) in vars {
+        vmt.vars
+            .insert(symbol.as_str(), SVar::new_curr(var.clone()));
+    }
+    let expr = vmt.term(sexp, &Map::new())?;
+    stateless(&expr, "term")
+}
+
+/// Turns an SMT-LIB symbol into a legal, unused mikino identifier.
+fn mikino_id(symbol: &str, used: &Set<String>) -> String {
+    let symbol = if symbol.len() >= 2 && symbol.starts_with('|') && symbol.ends_with('|') {
+        &symbol[1..symbol.len() - 1]
+    } else {
+        symbol
+    };
+    let mut id: String = symbol
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.insert(0, '_')
+    }
+    if parse::is_kw(&id) || id == "true" || id == "false" {
+        id.push('_')
+    }
+    let mut res = id.clone();
+    let mut cnt = 2;
+    while used.contains(&res) {
+        res = format!("{}_{}", id, cnt);
+        cnt += 1;
+    }
+    res
+}
+
+/// Parses a sort.
+fn sort(sexp: &Sexp) -> Res<Typ> {
+    match sexp.as_atom() {
+        Some("Bool") => Ok(Typ::Bool),
+        Some("Int") => Ok(Typ::Int),
+        Some("Real") => Ok(Typ::Rat),
+        _ => bail!(
+            "line {}: sort `{}` is not supported, mikino only handles `Bool`, `Int` and `Real`",
+            sexp.line(),
+            sexp,
+        ),
+    }
+}
+
+/// Parses a constant, if `atom` is one.
+fn cst(atom: &str, line: usize) -> Res<Option<Cst>> {
+    if atom == "true" || atom == "false" {
+        return Ok(Some(Cst::B(atom == "true")));
+    }
+    if atom.starts_with("#b") || atom.starts_with("#x") {
+        bail!(
+            "line {}: bit-vector constant `{}` is not supported",
+            line,
+            atom
+        )
+    }
+    let mut split = atom.splitn(2, '.');
+    let (int, dec) = (split.next().unwrap_or(""), split.next());
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if int.is_empty() || !is_digits(int) {
+        return Ok(None);
+    }
+    let int: Int = int
+        .parse()
+        .map_err(|_| format!("line {}: illegal numeral `{}`", line, atom))?;
+    match dec {
+        None => Ok(Some(Cst::I(int))),
+        Some(dec) if is_digits(dec) => {
+            let mut numer = int;
+            let mut denom = Int::one();
+            for digit in dec.chars() {
+                numer = numer * 10 + Int::from(digit as u8 - b'0');
+                denom *= 10;
+            }
+            Ok(Some(Cst::R(Rat::new(numer, denom))))
+        }
+        Some(_) => Ok(None),
+    }
+}
+
+/// Turns a stateful expression into a stateless one, fails if it mentions next-state variables.
+fn stateless(expr: &SExpr, desc: &str) -> Res<Expr> {
+    match expr {
+        PExpr::Cst(cst) => Ok(PExpr::Cst(cst.clone())),
+        PExpr::Var(svar) if svar.is_next() => bail!(
+            "{} cannot mention the next version of state variable `{}`",
+            desc,
+            svar.id()
+        ),
+        PExpr::Var(svar) => Ok(PExpr::Var(Var::clone(svar))),
+        PExpr::App { op, args } => {
+            let args = args
+                .iter()
+                .map(|arg| stateless(arg, desc))
+                .collect::<Res<Vec<_>>>()?;
+            Ok(PExpr::App { op: *op, args })
+        }
+    }
+}
+
+/// Builds an operator application.
+///
+/// Integer constants are turned into rationals when other arguments are rationals, as SMT-LIB
+/// solvers usually allow `Int` numerals in `Real` terms. Divisions of constants are evaluated.
+fn app(op: Op, mut args: Vec<SExpr>, line: usize) -> Res<SExpr> {
+    let has_rat = args.iter().any(|arg| arg.typ() == Typ::Rat);
+    if has_rat || op == Op::Div {
+        for arg in args.iter_mut() {
+            if let PExpr::Cst(Cst::I(i)) = arg {
+                *arg = PExpr::Cst(Cst::R(Rat::from_integer(i.clone())))
+            }
+        }
+    }
+    if let (Op::Div, [PExpr::Cst(Cst::R(num)), PExpr::Cst(Cst::R(den))]) = (op, args.as_slice()) {
+        if den.is_zero() {
+            bail!("line {}: division by zero", line)
+        }
+        return Ok(PExpr::Cst(Cst::R(num / den)));
+    }
+    PExpr::new_op(op, args).chain_err(|| format!("line {}: ill-typed `{}` application", line, op))
+}
+
+/// VMT-LIB definition: its sort and its body, stripped from annotations.
+struct Def<'txt> {
+    typ: Typ,
+    body: &'txt Sexp,
+}
+
+/// VMT-LIB parsing context.
+struct Vmt<'txt> {
+    /// Declared symbols.
+    decls: Map<&'txt str, Typ>,
+    /// Parameter-less definitions.
+    defs: Map<&'txt str, Def<'txt>>,
+    /// Translation of the definitions already translated.
+    def_cache: Map<&'txt str, SExpr>,
+    /// Definitions being translated, used to detect cyclic definitions.
+    def_stack: Set<&'txt str>,
+    /// Maps current-state symbols to their next-state version.
+    nexts: Map<&'txt str, &'txt str>,
+    /// Initial predicates.
+    init: Vec<&'txt Sexp>,
+    /// Transition relations.
+    trans: Vec<&'txt Sexp>,
+    /// Invariant properties, with their index.
+    props: Vec<(String, &'txt Sexp)>,
+    /// Maps symbols to state variables, populated once all commands are processed.
+    vars: Map<&'txt str, SVar>,
+}
+impl<'txt> Vmt<'txt> {
+    /// Constructor.
+    fn new() -> Self {
+        Self {
+            decls: Map::new(),
+            defs: Map::new(),
+            def_cache: Map::new(),
+            def_stack: Set::new(),
+            nexts: Map::new(),
+            init: vec![],
+            trans: vec![],
+            props: vec![],
+            vars: Map::new(),
+        }
+    }
+
+    /// Handles a top-level command.
+    fn command(&mut self, sexp: &'txt Sexp) -> Res<()> {
+        let line = sexp.line();
+        let list = sexp.list("a command")?;
+        let (cmd, args) = match list.split_first() {
+            Some((cmd, args)) => (cmd.atom("a command name")?, args),
+            None => bail!("line {}: expected a command, found `()`", line),
+        };
+
+        match cmd {
+            "set-info" | "set-logic" | "set-option" | "check-sat" | "exit" => (),
+
+            "declare-fun" | "declare-const" => {
+                let (symbol, typ) = match (cmd, args) {
+                    ("declare-fun", [symbol, params, typ]) => {
+                        if !params.list("a parameter list")?.is_empty() {
+                            bail!(
+                                "line {}: uninterpreted function `{}` is not supported",
+                                line,
+                                symbol
+                            )
+                        }
+                        (symbol, typ)
+                    }
+                    ("declare-const", [symbol, typ]) => (symbol, typ),
+                    _ => bail!("line {}: illegal `{}` command", line, cmd),
+                };
+                let symbol = symbol.atom("a symbol")?;
+                self.check_fresh(symbol, line)?;
+                self.decls.insert(symbol, sort(typ)?);
+            }
+
+            "define-fun" => {
+                let (symbol, params, typ, body) = match args {
+                    [symbol, params, typ, body] => (symbol.atom("a symbol")?, params, typ, body),
+                    _ => bail!("line {}: illegal `define-fun` command", line),
+                };
+                if !params.list("a parameter list")?.is_empty() {
+                    bail!(
+                        "line {}: `define-fun` with parameters is not supported (`{}`)",
+                        line,
+                        symbol,
+                    )
+                }
+                self.check_fresh(symbol, line)?;
+                let typ = sort(typ)?;
+                let body = self.annotations(body)?;
+                self.defs.insert(symbol, Def { typ, body });
+            }
+
+            "assert" => match args {
+                [arg] if arg.as_atom() == Some("true") => (),
+                _ => bail!(
+                    "line {}: top-level assertions are not supported, \
+                    use `:init`/`:trans` annotations instead",
+                    line
+                ),
+            },
+
+            _ => bail!("line {}: unsupported command `{}`", line, cmd),
+        }
+        Ok(())
+    }
+
+    /// Fails if a symbol is already declared or defined.
+    fn check_fresh(&self, symbol: &str, line: usize) -> Res<()> {
+        if self.decls.contains_key(symbol) || self.defs.contains_key(symbol) {
+            bail!("line {}: symbol `{}` is already defined", line, symbol)
+        }
+        Ok(())
+    }
+
+    /// Handles the VMT annotations of a definition body, yields the body without annotations.
+    fn annotations(&mut self, body: &'txt Sexp) -> Res<&'txt Sexp> {
+        let (term, attrs) = match body.as_list() {
+            Some([bang, term, attrs @ ..]) if bang.as_atom() == Some("!") => (term, attrs),
+            _ => return Ok(body),
+        };
+        let mut attrs = attrs.iter();
+        while let Some(key) = attrs.next() {
+            let line = key.line();
+            let key = key.atom("an attribute keyword")?;
+            let val = attrs.next();
+            match key {
+                ":next" => {
+                    let curr = term.atom("a state variable for `:next` annotation")?;
+                    let next = val
+                        .ok_or_else(|| format!("line {}: `:next` expects a symbol", line))?
+                        .atom("a symbol for `:next` annotation")?;
+                    if self.nexts.insert(curr, next).is_some() {
+                        bail!(
+                            "line {}: state variable `{}` has more than one next version",
+                            line,
+                            curr
+                        )
+                    }
+                }
+                ":init" => self.init.push(term),
+                ":trans" => self.trans.push(term),
+                ":invar-property" => {
+                    let idx = val.and_then(Sexp::as_atom).ok_or_else(|| {
+                        format!("line {}: `:invar-property` expects an index", line)
+                    })?;
+                    self.props.push((idx.to_string(), term))
+                }
+                ":live-property" => bail!("line {}: liveness properties are not supported", line),
+                // Other annotations, such as `:named`, are irrelevant.
+                _ => (),
+            }
+        }
+        Ok(term)
+    }
+
+    /// Builds the state variables from the declarations and the `:next` annotations.
+    fn build_vars(&mut self) -> Res<Decls> {
+        let mut decls = Decls::new();
+        let mut used = Set::new();
+        let next_to_curr: Map<&str, &str> = self.nexts.iter().map(|(c, n)| (*n, *c)).collect();
+
+        for (curr, next) in &self.nexts {
+            let typ = *self.decls.get(curr).ok_or_else(|| {
+                format!("`:next` annotation mentions undeclared symbol `{}`", curr)
+            })?;
+            let next_typ = *self.decls.get(next).ok_or_else(|| {
+                format!("`:next` annotation mentions undeclared symbol `{}`", next)
+            })?;
+            if typ != next_typ {
+                bail!(
+                    "state variable `{}` has type `{}` but its next version `{}` has type `{}`",
+                    curr,
+                    typ,
+                    next,
+                    next_typ,
+                )
+            }
+            if next_to_curr.contains_key(curr) {
+                bail!(
+                    "`{}` cannot be both a state variable and the next version of `{}`",
+                    curr,
+                    next_to_curr[curr],
+                )
+            }
+        }
+
+        for (symbol, typ) in &self.decls {
+            if next_to_curr.contains_key(symbol) {
+                continue;
+            }
+            let id = mikino_id(symbol, &used);
+            used.insert(id.clone());
+            decls.register(id.clone(), *typ);
+            let var = Var::new(id, *typ);
+            if let Some(next) = self.nexts.get(symbol) {
+                self.vars.insert(next, SVar::new_next(var.clone()));
+            }
+            self.vars.insert(symbol, SVar::new_curr(var));
+        }
+        Ok(decls)
+    }
+
+    /// Translates a term.
+    fn term(&mut self, sexp: &'txt Sexp, env: &Map<&'txt str, SExpr>) -> Res<SExpr> {
+        let line = sexp.line();
+        let list = match sexp {
+            Sexp::Atom(atom, _) => return self.symbol(atom, line, env),
+            Sexp::List(list, _) => list,
+        };
+        let (head, args) = match list.split_first() {
+            Some((head, args)) => (head, args),
+            None => bail!("line {}: expected a term, found `()`", line),
+        };
+        let head = match head.as_atom() {
+            Some(head) => head,
+            None => bail!(
+                "line {}: indexed operators such as `{}` are not supported",
+                line,
+                head
+            ),
+        };
+
+        match head {
+            "!" => {
+                let term = args
+                    .first()
+                    .ok_or_else(|| format!("line {}: empty annotation", line))?;
+                return self.term(term, env);
+            }
+            "let" => {
+                let (bindings, body) = match args {
+                    [bindings, body] => (bindings.list("let-bindings")?, body),
+                    _ => bail!("line {}: illegal `let` term", line),
+                };
+                let mut inner = env.clone();
+                for binding in bindings {
+                    match binding.list("a let-binding")? {
+                        [symbol, term] => {
+                            let term = self.term(term, env)?;
+                            inner.insert(symbol.atom("a symbol")?, term);
+                        }
+                        _ => bail!("line {}: illegal let-binding `{}`", binding.line(), binding),
+                    }
+                }
+                return self.term(body, &inner);
+            }
+            _ => (),
+        }
+
+        let mut args = args
+            .iter()
+            .map(|arg| self.term(arg, env))
+            .collect::<Res<Vec<_>>>()?;
+
+        let op = match head {
+            "ite" => Op::Ite,
+            "=>" => {
+                // Right-associative.
+                let mut args = args.into_iter().rev();
+                let mut res = args
+                    .next()
+                    .ok_or_else(|| format!("line {}: `=>` expects arguments", line))?;
+                for arg in args {
+                    res = app(Op::Implies, vec![arg, res], line)?
+                }
+                return Ok(res);
+            }
+            "+" => Op::Add,
+            "-" => Op::Sub,
+            "*" => Op::Mul,
+            "/" => Op::Div,
+            "div" => Op::IDiv,
+            "mod" => Op::Mod,
+            ">=" => Op::Ge,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            "<" => Op::Lt,
+            "=" => Op::Eq,
+            "not" => Op::Not,
+            "and" => Op::And,
+            "or" => Op::Or,
+            "distinct" => {
+                let mut diffs = vec![];
+                for (idx, lft) in args.iter().enumerate() {
+                    for rgt in &args[idx + 1..] {
+                        let eq = app(Op::Eq, vec![lft.clone(), rgt.clone()], line)?;
+                        diffs.push(app(Op::Not, vec![eq], line)?)
+                    }
+                }
+                return match diffs.len() {
+                    0 => bail!("line {}: `distinct` expects at least two arguments", line),
+                    1 => Ok(diffs.pop().expect("[unreachable] pop on vec of len `1`")),
+                    _ => app(Op::And, diffs, line),
+                };
+            }
+            "xor" => {
+                let mut args = args.into_iter();
+                let mut res = args
+                    .next()
+                    .ok_or_else(|| format!("line {}: `xor` expects arguments", line))?;
+                for arg in args {
+                    let eq = app(Op::Eq, vec![res, arg], line)?;
+                    res = app(Op::Not, vec![eq], line)?
+                }
+                return Ok(res);
+            }
+            "abs" if args.len() == 1 => {
+                let arg = args.pop().expect("[unreachable] pop on vec of len `1`");
+                let zero = match arg.typ() {
+                    Typ::Rat => Cst::R(Rat::zero()),
+                    _ => Cst::I(Int::zero()),
+                };
+                let cnd = app(Op::Ge, vec![arg.clone(), PExpr::Cst(zero)], line)?;
+                let neg = app(Op::Sub, vec![arg.clone()], line)?;
+                return app(Op::Ite, vec![cnd, arg, neg], line);
+            }
+            "to_real" | "to_int" | "is_int" => bail!(
+                "line {}: `{}` is not supported, mikino has no int/real conversions",
+                line,
+                head
+            ),
+            _ if self.defs.contains_key(head) => bail!(
+                "line {}: `{}` is a constant, it cannot be applied to arguments",
+                line,
+                head
+            ),
+            _ => bail!("line {}: unsupported operator `{}`", line, head),
+        };
+        app(op, args, line)
+    }
+
+    /// Translates a symbol.
+    fn symbol(&mut self, atom: &'txt str, line: usize, env: &Map<&'txt str, SExpr>) -> Res<SExpr> {
+        if let Some(expr) = env.get(atom) {
+            return Ok(expr.clone());
+        }
+        if let Some(svar) = self.vars.get(atom) {
+            return Ok(PExpr::new_var(svar.clone()));
+        }
+        if let Some(expr) = self.def_cache.get(atom) {
+            return Ok(expr.clone());
+        }
+        if let Some(def) = self.defs.get(atom) {
+            let (typ, body) = (def.typ, def.body);
+            if !self.def_stack.insert(atom) {
+                bail!("line {}: definition of `{}` is cyclic", line, atom)
+            }
+            let expr = self
+                .term(body, &Map::new())
+                .chain_err(|| format!("in definition of `{}`", atom))?;
+            self.def_stack.remove(atom);
+            if expr.typ() != typ {
+                bail!(
+                    "definition of `{}` has type `{}`, expected `{}`",
+                    atom,
+                    expr.typ(),
+                    typ,
+                )
+            }
+            self.def_cache.insert(atom, expr.clone());
+            return Ok(expr);
+        }
+        if let Some(cst) = cst(atom, line)? {
+            return Ok(PExpr::Cst(cst));
+        }
+        bail!("line {}: unknown symbol `{}`", line, atom)
+    }
+
+    /// Conjunction of some terms.
+    fn conj(&mut self, terms: &[&'txt Sexp]) -> Res<SExpr> {
+        let mut conj = Vec::with_capacity(terms.len());
+        for term in terms {
+            let line = term.line();
+            let term = self.term(term, &Map::new())?;
+            if term.typ() != Typ::Bool {
+                bail!(
+                    "line {}: expected a `Bool` term, found `{}`",
+                    line,
+                    term.typ()
+                )
+            }
+            conj.push(term)
+        }
+        match conj.len() {
+            0 => Ok(PExpr::Cst(Cst::B(true))),
+            1 => Ok(conj.pop().expect("[unreachable] pop on vec of len `1`")),
+            _ => app(Op::And, conj, terms[0].line()),
+        }
+    }
+
+    /// Builds the system.
+    fn into_sys(mut self) -> Res<Sys> {
+        let decls = self.build_vars()?;
+
+        let init = self.init.clone();
+        let init = self.conj(&init).chain_err(|| "in initial predicate")?;
+        let init = stateless(&init, "initial predicate")?;
+
+        let trans = self.trans.clone();
+        let trans = self.conj(&trans).chain_err(|| "in transition relation")?;
+
+        let mut po_s = Map::new();
+        for (idx, term) in self.props.clone() {
+            let name = format!("property {}", idx);
+            let def = self
+                .conj(&[term])
+                .chain_err(|| format!("in invariant property {}", idx))?;
+            let def = stateless(&def, &format!("invariant property {}", idx))?;
+            if po_s.insert(name, def).is_some() {
+                bail!("invariant property {} is defined twice", idx)
+            }
+        }
+        if po_s.is_empty() {
+            bail!("no `:invar-property` found, nothing to check")
+        }
+
+        Ok(Sys::new(decls, init, trans, po_s))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::export;
+
+    const SYS: &str = "\
+svars { cnt: int, r: rat, reset: bool, next: int }
+init { cnt = 0, r ≥ -1.5, next = 7 }
+trans { 'cnt = if 'reset { 0 } else { cnt + 1 }, 'r = r / 2.0, 'next = next - 3 }
+candidates { \"cnt is positive\": cnt ≥ 0, \"r bounded\": r ≤ 7.0 ⋀ ¬reset }
+";
+
+    /// Counter `x` with state variable declarations only, commands are appended to it.
+    const HEADER: &str = "\
+(declare-fun x () Int)
+(declare-fun x.next () Int)
+(define-fun .sv0 () Int (! x :next x.next))
+";
+
+    fn vmt_of(sys: &Sys) -> String {
+        let mut bytes = vec![];
+        export::vmt::write(&mut bytes, sys).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn err(txt: &str) -> String {
+        match parse(txt) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    fn vars(sys: &Sys) -> Vec<String> {
+        sys.decls()
+            .all()
+            .map(|var| format!("{}: {}", var.id(), var.typ()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let sys = parse::trans(SYS).unwrap();
+        let vmt = vmt_of(&sys);
+        let imported = parse(&vmt).unwrap();
+        assert_eq!(vars(&imported), vars(&sys));
+        let po_s: Vec<_> = imported.po_s().keys().cloned().collect();
+        assert_eq!(po_s, vec!["property 0", "property 1"]);
+        // Candidate names are not part of VMT-LIB, everything else survives.
+        let expected = vmt
+            .replace("`cnt is positive`", "`property 0`")
+            .replace("`r bounded`", "`property 1`");
+        assert_eq!(vmt_of(&imported), expected);
+    }
+
+    #[test]
+    fn inputs_definitions_and_lets() {
+        let txt = format!(
+            "{}(declare-const i Int)\n\
+            (define-fun incr () Int (+ x i))\n\
+            (define-fun .init () Bool (! (= x 0) :init true))\n\
+            (define-fun .trans () Bool (! (let ((y incr)) (= x.next y)) :trans true))\n\
+            (define-fun .prop () Bool (! (>= x 0) :invar-property 3))\n",
+            HEADER
+        );
+        let sys = parse(&txt).unwrap();
+        assert_eq!(vars(&sys), vec!["i: int", "x: int"]);
+        let po_s: Vec<_> = sys.po_s().keys().cloned().collect();
+        assert_eq!(po_s, vec!["property 3"]);
+        assert_eq!(sys.trans().to_string(), "(= x@1 (+ x@0 i@0))");
+    }
+
+    #[test]
+    fn malformed() {
+        let check = |body: &str, expected: &str| {
+            let e = err(&format!("{}{}", HEADER, body));
+            assert!(e.contains(expected), "expected `{}` in `{}`", expected, e);
+        };
+        let prop = "(define-fun .prop () Bool (! (>= x 0) :invar-property 0))";
+
+        check("(declare-fun y () Int", "line 4: unclosed parenthesis");
+        check(")", "line 4: unexpected closing parenthesis");
+        check("(declare-fun y () (_ BitVec 8))", "is not supported");
+        check("(declare-fun f (Int) Int)", "uninterpreted function `f`");
+        check(
+            &format!(
+                "(define-fun .init () Bool (! (= x.next 0) :init true)){}",
+                prop
+            ),
+            "initial predicate cannot mention the next version",
+        );
+        check(
+            "(define-fun a () Bool b)\n(define-fun b () Bool a)\n\
+            (define-fun .prop () Bool (! a :invar-property 0))",
+            "definition of `a` is cyclic",
+        );
+        check(
+            &format!(
+                "(define-fun .trans () Bool (! (= x.next y) :trans true)){}",
+                prop
+            ),
+            "unknown symbol `y`",
+        );
+        check(
+            &format!(
+                "(define-fun .trans () Bool (! (= x.next (+ x true)) :trans true)){}",
+                prop
+            ),
+            "ill-typed `+` application",
+        );
+        check("(assert (= x 0))", "top-level assertions are not supported");
+        check("", "no `:invar-property` found");
+    }
+}
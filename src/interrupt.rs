@@ -0,0 +1,508 @@
+//! Interruptions.
+//!
+//! On unix, SIGINT and SIGTERM are handled by a dedicated thread (see [`watch`]): it kills the
+//! solvers mikino spawned, prints what the run established so far, and exits. Engines report what
+//! they establish as they go with [`running`], [`proved`], [`falsified`] and [`bmc_depths`].
+//!
+//! Only the solvers whose process id was recorded are killed. The portfolio and replays record the
+//! solvers they spawn with [`solver_spawned`]. Other solvers are spawned by `rsmt2`, which hides
+//! their process id: [`rsmt2_solver_spawned`] records them by looking for mikino's children. Under
+//! a memory limit, they run through mikino's solver wrapper (see [`crate::solver::wrap`]), which
+//! also writes its process id on a pipe before executing the solver; a thread records the ids read
+//! on the pipe.
+
+mikino_api::prelude!();
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicI32, Ordering},
+    Mutex, MutexGuard,
+};
+
+use crate::Styles;
+
+/// What the run established so far.
+struct Partial {
+    /// System being checked, if any.
+    input: Option<String>,
+    /// Engine running, if any.
+    engine: Option<&'static str>,
+    /// Candidates proved.
+    proved: Vec<String>,
+    /// Falsified candidates, with the engine that falsified them and the length of the
+    /// counterexample, if any.
+    falsified: Vec<(String, &'static str, Option<usize>)>,
+    /// First and last depths BMC checked, if any.
+    bmc_depths: Option<(Unroll, Unroll)>,
+}
+
+/// What the run established so far.
+static PARTIAL: Mutex<Partial> = Mutex::new(Partial {
+    input: None,
+    engine: None,
+    proved: vec![],
+    falsified: vec![],
+    bmc_depths: None,
+});
+
+/// True once a signal was received.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Process ids of the solvers spawned so far.
+static SOLVERS: Mutex<Vec<u32>> = Mutex::new(vec![]);
+
+/// Write end of the pipe solver wrappers report their process id on, negative if none.
+static PID_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Accesses the partial result, even if a thread panicked while holding it.
+fn partial() -> MutexGuard<'static, Partial> {
+    PARTIAL
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Records the process id of a solver.
+///
+/// Also forgets about the solvers that are done.
+pub fn solver_spawned(pid: u32) {
+    let mut solvers = SOLVERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    solvers.retain(|pid| is_running(*pid));
+    if !solvers.contains(&pid) {
+        solvers.push(pid)
+    }
+}
+
+/// Records the solvers `rsmt2` spawned, which does not give access to their process id.
+///
+/// Solvers are the only processes mikino keeps running, this records all of mikino's running
+/// children. Children are listed in `/proc`, unwrapped solvers are not recorded on systems without
+/// it.
+pub fn rsmt2_solver_spawned() {
+    for pid in children() {
+        solver_spawned(pid)
+    }
+}
+
+/// Process ids of mikino's children, listed in `/proc`.
+fn children() -> Vec<u32> {
+    let dir = match std::fs::read_dir("/proc") {
+        Ok(dir) => dir,
+        Err(_) => return vec![],
+    };
+    let parent = std::process::id();
+    dir.filter_map(|entry| {
+        let pid: u32 = entry.ok()?.file_name().to_str()?.parse().ok()?;
+        // `<pid> (<command>) <state> <parent pid> ...`, the command can contain spaces and
+        // parentheses.
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let (_, fields) = stat.rsplit_once(')')?;
+        let ppid: u32 = fields.split_whitespace().nth(1)?.parse().ok()?;
+        Some(pid).filter(|_| ppid == parent)
+    })
+    .collect()
+}
+
+/// File descriptor solver wrappers report their process id on, if any.
+pub fn pid_fd() -> Option<i32> {
+    let fd = PID_FD.load(Ordering::SeqCst);
+    if fd < 0 {
+        None
+    } else {
+        Some(fd)
+    }
+}
+
+/// Starts checking a system, forgets about the previous one.
+pub fn checking(input: &str) {
+    let mut partial = partial();
+    partial.input = Some(input.into());
+    partial.engine = None;
+    partial.proved.clear();
+    partial.falsified.clear();
+    partial.bmc_depths = None;
+}
+
+/// Sets the engine running, if any.
+pub fn running(engine: Option<&'static str>) {
+    partial().engine = engine
+}
+
+/// Registers a proved candidate.
+pub fn proved(candidate: &str) {
+    let mut partial = partial();
+    if !partial.proved.iter().any(|c| c == candidate) {
+        partial.proved.push(candidate.into())
+    }
+}
+
+/// Registers a falsified candidate, with the length of its counterexample if any.
+pub fn falsified(candidate: &str, engine: &'static str, len: Option<usize>) {
+    let mut partial = partial();
+    if !partial.falsified.iter().any(|(c, _, _)| c == candidate) {
+        partial.falsified.push((candidate.into(), engine, len))
+    }
+}
+
+/// Registers the depths BMC checked, from `first` to `last`.
+pub fn bmc_depths(first: Unroll, last: Unroll) {
+    partial().bmc_depths = Some((first, last))
+}
+
+/// True if mikino was interrupted.
+///
+/// Also true if a signal is about to be handled: solvers sharing mikino's terminal receive SIGINT
+/// too, and errors caused by their death must not hide the partial result.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst) || signal_pending()
+}
+
+/// Blocks forever if mikino was interrupted, letting the signal thread exit.
+pub fn wait_if_interrupted() {
+    if is_interrupted() {
+        loop {
+            std::thread::park()
+        }
+    }
+}
+
+/// Handles SIGINT and SIGTERM in a dedicated thread.
+///
+/// Must run before mikino spawns other threads, so that they do not receive the signals.
+#[cfg(unix)]
+pub fn watch(styles: Styles) {
+    let set = match signals() {
+        Some(set) => set,
+        None => return,
+    };
+    if unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) } != 0 {
+        return;
+    }
+    listen();
+    std::thread::spawn(move || {
+        let mut signal = 0;
+        if unsafe { libc::sigwait(&set, &mut signal) } != 0 {
+            return;
+        }
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        kill_solvers();
+        report(&styles, signal);
+        std::process::exit(128 + signal)
+    });
+}
+/// Signals are not handled on this platform.
+#[cfg(not(unix))]
+pub fn watch(_styles: Styles) {}
+
+/// Set of the signals mikino handles.
+#[cfg(unix)]
+fn signals() -> Option<libc::sigset_t> {
+    let mut set = std::mem::MaybeUninit::<libc::sigset_t>::uninit();
+    unsafe {
+        if libc::sigemptyset(set.as_mut_ptr()) != 0
+            || libc::sigaddset(set.as_mut_ptr(), libc::SIGINT) != 0
+            || libc::sigaddset(set.as_mut_ptr(), libc::SIGTERM) != 0
+        {
+            return None;
+        }
+        Some(set.assume_init())
+    }
+}
+
+/// True if SIGINT or SIGTERM is pending.
+#[cfg(unix)]
+fn signal_pending() -> bool {
+    let mut set = std::mem::MaybeUninit::<libc::sigset_t>::uninit();
+    unsafe {
+        libc::sigpending(set.as_mut_ptr()) == 0 && {
+            let set = set.assume_init();
+            libc::sigismember(&set, libc::SIGINT) == 1
+                || libc::sigismember(&set, libc::SIGTERM) == 1
+        }
+    }
+}
+/// Signals are not handled on this platform.
+#[cfg(not(unix))]
+fn signal_pending() -> bool {
+    false
+}
+
+/// Opens the pipe solver wrappers report their process id on, and records the ids they write.
+///
+/// Only the read end is closed on `exec`, solver wrappers inherit the write end.
+#[cfg(unix)]
+fn listen() {
+    use std::io::BufRead;
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return;
+        }
+        if libc::fcntl(fds[0], libc::F_SETFD, libc::FD_CLOEXEC) != 0 {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+            return;
+        }
+    }
+    let pipe = unsafe { std::fs::File::from_raw_fd(fds[0]) };
+    PID_FD.store(fds[1], Ordering::SeqCst);
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(pipe).lines() {
+            match line.map(|line| line.trim().parse()) {
+                Ok(Ok(pid)) => solver_spawned(pid),
+                Ok(Err(_)) => (),
+                Err(_) => break,
+            }
+        }
+    });
+}
+
+/// True if a process is a child of mikino that has not terminated.
+///
+/// Does not reap the process, `rsmt2` and the portfolio wait for their solvers themselves.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let mut info = std::mem::MaybeUninit::<libc::siginfo_t>::zeroed();
+    let res = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            info.as_mut_ptr(),
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    // With `WNOHANG`, the process id of the info stays zero if the child has not terminated.
+    res == 0 && unsafe { info.assume_init().si_pid() } == 0
+}
+/// Solvers are not tracked on this platform.
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+/// Kills the solvers mikino spawned that are still running.
+///
+/// Checking that a solver is running first makes sure its process id was not reused.
+#[cfg(unix)]
+fn kill_solvers() {
+    let solvers = SOLVERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    kill(&solvers)
+}
+
+/// Kills the processes of `pids` that are running children of mikino.
+#[cfg(unix)]
+fn kill(pids: &[u32]) {
+    for pid in pids.iter().copied().filter(|pid| is_running(*pid)) {
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+    }
+}
+
+/// Prints the partial result.
+#[cfg(unix)]
+fn report(styles: &Styles, signal: i32) {
+    let signal = match signal {
+        libc::SIGINT => "SIGINT",
+        libc::SIGTERM => "SIGTERM",
+        _ => "signal",
+    };
+    // Erase the progress line, if any.
+    if atty::is(atty::Stream::Stderr) {
+        eprint!("\r\x1b[K")
+    }
+    let stdout = std::io::stdout();
+    let _ = write_report(&mut stdout.lock(), styles, signal);
+}
+
+/// Writes the partial result, `signal` is the name of the signal received.
+#[cfg(unix)]
+fn write_report(out: &mut impl std::io::Write, styles: &Styles, signal: &str) -> Res<()> {
+    let partial = partial();
+    writeln!(out)?;
+    match partial.engine {
+        Some(engine) => writeln!(
+            out,
+            "|===| {} by {} while running {}",
+            styles.red.paint("Interrupted"),
+            signal,
+            styles.bold.paint(engine),
+        ),
+        None => writeln!(
+            out,
+            "|===| {} by {}",
+            styles.red.paint("Interrupted"),
+            signal
+        ),
+    }?;
+    if let Some(input) = &partial.input {
+        writeln!(out, "| partial result for `{}`", input)?;
+        if partial.proved.is_empty() {
+            writeln!(out, "| - no candidate proved so far")?;
+        } else {
+            writeln!(
+                out,
+                "| - the following candidate(s) {} in all reachable states",
+                styles.green.paint("hold")
+            )?;
+            for candidate in &partial.proved {
+                writeln!(out, "|   `{}`", styles.green.paint(candidate))?;
+            }
+        }
+        if partial.falsified.is_empty() {
+            writeln!(out, "| - no counterexample found so far")?;
+        } else {
+            writeln!(
+                out,
+                "| - the following candidate(s) are {}",
+                styles.red.paint("falsifiable")
+            )?;
+            for (candidate, engine, len) in &partial.falsified {
+                match len {
+                    Some(len) => writeln!(
+                        out,
+                        "|   `{}` ({}, {} state(s))",
+                        styles.red.paint(candidate),
+                        engine,
+                        len
+                    ),
+                    None => writeln!(out, "|   `{}` ({})", styles.red.paint(candidate), engine),
+                }?;
+            }
+        }
+        if let Some((first, last)) = partial.bmc_depths {
+            writeln!(
+                out,
+                "| - BMC checked {}",
+                crate::bmc_depths_desc(first, last)
+            )?;
+        }
+    }
+    writeln!(out, "|===|")?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    use std::{
+        os::unix::process::ExitStatusExt,
+        process::{Child, Command},
+        time::{Duration, Instant},
+    };
+
+    /// A child process standing for a solver.
+    fn solver() -> Child {
+        Command::new("sleep").arg("30").spawn().unwrap()
+    }
+
+    /// True if a process id is recorded.
+    fn recorded(pid: u32) -> bool {
+        SOLVERS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains(&pid)
+    }
+
+    #[test]
+    fn solvers() {
+        let mut kid = solver();
+        let pid = kid.id();
+        assert!(is_running(pid));
+        solver_spawned(pid);
+        assert!(recorded(pid));
+        kill(&[pid]);
+        assert_eq!(kid.wait().unwrap().signal(), Some(libc::SIGKILL));
+        assert!(!is_running(pid));
+
+        // Solvers that are done are forgotten when recording a new one.
+        let mut other = solver();
+        solver_spawned(other.id());
+        assert!(!recorded(pid));
+        assert!(recorded(other.id()));
+        other.kill().unwrap();
+        other.wait().unwrap();
+
+        // Processes that are not children of mikino are never running, and never killed.
+        assert!(!is_running(1));
+        assert!(!is_running(std::process::id()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rsmt2_solvers() {
+        let mut kid = solver();
+        assert!(children().contains(&kid.id()));
+        rsmt2_solver_spawned();
+        assert!(recorded(kid.id()));
+        kid.kill().unwrap();
+        kid.wait().unwrap();
+        assert!(!children().contains(&kid.id()));
+    }
+
+    #[test]
+    fn wrapped_solvers() {
+        listen();
+        let fd = pid_fd().unwrap();
+        let mut kid = solver();
+        // What the solver wrapper writes on the pipe.
+        let line = format!("{}\n", kid.id());
+        let written = unsafe { libc::write(fd, line.as_ptr() as *const libc::c_void, line.len()) };
+        assert_eq!(written, line.len() as isize);
+        let start = Instant::now();
+        while !recorded(kid.id()) && start.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(10))
+        }
+        assert!(recorded(kid.id()));
+        kid.kill().unwrap();
+        kid.wait().unwrap();
+    }
+
+    #[test]
+    fn partial_result() {
+        let styles = Styles::new(false);
+        let report = || {
+            let mut bytes = vec![];
+            write_report(&mut bytes, &styles, "SIGINT").unwrap();
+            String::from_utf8(bytes).unwrap()
+        };
+
+        checking("previous.mkn");
+        proved("previous");
+        checking("sys.mkn");
+        assert_eq!(
+            report(),
+            "\n\
+            |===| Interrupted by SIGINT\n\
+            | partial result for `sys.mkn`\n\
+            | - no candidate proved so far\n\
+            | - no counterexample found so far\n\
+            |===|\n"
+        );
+
+        running(Some("BMC"));
+        proved("a");
+        proved("a");
+        falsified("b", "base", Some(1));
+        falsified("c", "spacer", None);
+        falsified("c", "bmc", Some(3));
+        bmc_depths(2, 5);
+        assert_eq!(
+            report(),
+            "\n\
+            |===| Interrupted by SIGINT while running BMC\n\
+            | partial result for `sys.mkn`\n\
+            | - the following candidate(s) hold in all reachable states\n\
+            |   `a`\n\
+            | - the following candidate(s) are falsifiable\n\
+            |   `b` (base, 1 state(s))\n\
+            |   `c` (spacer)\n\
+            | - BMC checked depths 2 to 5\n\
+            |===|\n"
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Line edition for the REPL.
+//!
+//! When the standard input and output are terminals, lines are read in raw mode so that keys edit
+//! the line as in a shell:
+//!
+//! - left and right (`ctrl-b`, `ctrl-f`) move the cursor, home and end (`ctrl-a`, `ctrl-e`) move
+//!   it to the start and end of the line;
+//! - up and down (`ctrl-p`, `ctrl-n`) browse the lines entered so far;
+//! - backspace and delete erase a character, `ctrl-u` and `ctrl-k` erase up to the start and end
+//!   of the line;
+//! - `ctrl-c` drops the line, `ctrl-d` on an empty line ends the input.
+//!
+//! Otherwise, and on platforms other than unix, lines are read as they come.
+
+mikino_api::prelude!();
+
+use std::io::{BufRead, Read, Write};
+
+/// Result of reading a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    /// A line, without its newline.
+    Text(String),
+    /// The user hit `ctrl-c`.
+    Interrupted,
+    /// End of the input.
+    Eof,
+}
+
+/// A key, or a sequence of bytes that stands for a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillStart,
+    KillEnd,
+    Interrupt,
+    Eof,
+    /// Anything else, ignored.
+    Other,
+}
+
+/// Reads a key, `None` at the end of the input.
+fn key(input: &mut impl Read) -> Res<Option<Key>> {
+    let mut next = || -> Res<Option<u8>> {
+        let mut byte = [0];
+        match input.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    };
+    let byte = match next()? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        127 | 8 => Key::Backspace,
+        1 => Key::Home,
+        2 => Key::Left,
+        3 => Key::Interrupt,
+        4 => Key::Eof,
+        5 => Key::End,
+        6 => Key::Right,
+        11 => Key::KillEnd,
+        14 => Key::Down,
+        16 => Key::Up,
+        21 => Key::KillStart,
+        27 => match next()? {
+            Some(b'O') => match next()? {
+                Some(byte) => escape(&[], byte),
+                None => Key::Other,
+            },
+            // Control sequence: parameter and intermediate bytes up to a final byte.
+            Some(b'[') => {
+                let mut params = vec![];
+                loop {
+                    match next()? {
+                        Some(byte @ 0x40..=0x7e) => break escape(&params, byte),
+                        Some(byte @ 0x20..=0x3f) => params.push(byte),
+                        Some(_) | None => break Key::Other,
+                    }
+                }
+            }
+            _ => Key::Other,
+        },
+        byte if byte >= 0x80 => {
+            // Leading byte of a multi-byte UTF-8 character.
+            let len = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Ok(Some(Key::Other)),
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..len {
+                match next()? {
+                    Some(byte) => bytes.push(byte),
+                    None => return Ok(None),
+                }
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(char) => Key::Char(char),
+                None => Key::Other,
+            }
+        }
+        byte if byte < 0x20 => Key::Other,
+        byte => Key::Char(byte as char),
+    };
+    Ok(Some(key))
+}
+
+/// Key of an escape sequence from its parameters and final byte, [`Key::Other`] if unknown.
+fn escape(params: &[u8], last: u8) -> Key {
+    match (params, last) {
+        (b"", b'A') => Key::Up,
+        (b"", b'B') => Key::Down,
+        (b"", b'C') => Key::Right,
+        (b"", b'D') => Key::Left,
+        (b"", b'H') | (b"1" | b"7", b'~') => Key::Home,
+        (b"", b'F') | (b"4" | b"8", b'~') => Key::End,
+        (b"3", b'~') => Key::Delete,
+        _ => Key::Other,
+    }
+}
+
+/// A line being edited.
+#[derive(Debug, Clone, Default)]
+struct Buffer {
+    /// Characters of the line.
+    chars: Vec<char>,
+    /// Position of the cursor, between `0` and `chars.len()`.
+    cursor: usize,
+}
+impl Buffer {
+    /// A buffer with some content, the cursor at the end.
+    fn of(line: &str) -> Self {
+        let chars: Vec<char> = line.chars().collect();
+        let cursor = chars.len();
+        Self { chars, cursor }
+    }
+
+    /// Content of the buffer.
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Applies an editing key, other keys are ignored.
+    fn edit(&mut self, key: Key) {
+        match key {
+            Key::Char(char) => {
+                self.chars.insert(self.cursor, char);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            Key::Delete | Key::Eof if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            Key::Left if self.cursor > 0 => self.cursor -= 1,
+            Key::Right if self.cursor < self.chars.len() => self.cursor += 1,
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.chars.len(),
+            Key::KillStart => {
+                self.chars.drain(0..self.cursor);
+                self.cursor = 0;
+            }
+            Key::KillEnd => self.chars.truncate(self.cursor),
+            _ => (),
+        }
+    }
+
+    /// Shows the buffer after a prompt on the current line of the terminal.
+    fn show(&self, out: &mut impl Write, prompt: &str) -> Res<()> {
+        write!(out, "\r{}{}\x1b[K", prompt, self.text())?;
+        if self.cursor < self.chars.len() {
+            write!(out, "\x1b[{}D", self.chars.len() - self.cursor)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Line editor, remembers the lines read.
+#[derive(Debug, Clone)]
+pub struct Editor {
+    /// Lines read so far, oldest first, without empty lines and consecutive duplicates.
+    history: Vec<String>,
+    /// True if lines are read in raw mode.
+    raw: bool,
+}
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Editor {
+    /// Constructor, edition is only active if the standard input and output are terminals.
+    pub fn new() -> Self {
+        Self {
+            history: vec![],
+            raw: cfg!(unix) && atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout),
+        }
+    }
+
+    /// Reads a line after showing a prompt.
+    pub fn read_line(&mut self, prompt: &str) -> Res<Line> {
+        if self.raw {
+            #[cfg(unix)]
+            if let Some(mode) = raw::Mode::enable() {
+                let line = self.edit(prompt);
+                drop(mode);
+                return line;
+            }
+        }
+        print!("{}", prompt);
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(Line::Eof);
+        }
+        let len = line.trim_end_matches(&['\n', '\r'][..]).len();
+        line.truncate(len);
+        Ok(Line::Text(line))
+    }
+
+    /// Reads and edits a line, the terminal must be in raw mode.
+    fn edit(&mut self, prompt: &str) -> Res<Line> {
+        let stdin = std::io::stdin();
+        let mut input = stdin.lock();
+        let mut out = std::io::stdout();
+        let mut buffer = Buffer::default();
+        // Index of the line shown in the history, `history.len()` for the line being typed.
+        let mut index = self.history.len();
+        // Line being typed, saved while browsing the history.
+        let mut typed = String::new();
+        buffer.show(&mut out, prompt)?;
+        loop {
+            match key(&mut input)? {
+                None => {
+                    write!(out, "\r\n")?;
+                    return Ok(Line::Eof);
+                }
+                Some(Key::Enter) => {
+                    write!(out, "\r\n")?;
+                    let line = buffer.text();
+                    if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                        self.history.push(line.clone())
+                    }
+                    return Ok(Line::Text(line));
+                }
+                Some(Key::Interrupt) => {
+                    write!(out, "^C\r\n")?;
+                    return Ok(Line::Interrupted);
+                }
+                Some(Key::Eof) if buffer.chars.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(Line::Eof);
+                }
+                Some(Key::Up) if index > 0 => {
+                    if index == self.history.len() {
+                        typed = buffer.text();
+                    }
+                    index -= 1;
+                    buffer = Buffer::of(&self.history[index]);
+                }
+                Some(Key::Down) if index < self.history.len() => {
+                    index += 1;
+                    buffer = Buffer::of(self.history.get(index).unwrap_or(&typed));
+                }
+                Some(key) => buffer.edit(key),
+            }
+            buffer.show(&mut out, prompt)?;
+        }
+    }
+}
+
+/// Raw mode for the terminal.
+#[cfg(unix)]
+mod raw {
+    /// Puts the terminal in raw mode until dropped.
+    pub struct Mode {
+        /// Attributes of the terminal before raw mode.
+        original: libc::termios,
+    }
+    impl Mode {
+        /// Puts the terminal in raw mode, `None` if the terminal does not support it.
+        pub fn enable() -> Option<Self> {
+            let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) } != 0 {
+                return None;
+            }
+            let original = unsafe { termios.assume_init() };
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            // `TCSADRAIN` and not `TCSAFLUSH`, pasted lines must not be lost.
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &raw) } != 0 {
+                return None;
+            }
+            Some(Self { original })
+        }
+    }
+    impl Drop for Mode {
+        fn drop(&mut self) {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.original) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(mut bytes: &[u8]) -> Vec<Key> {
+        let mut keys = vec![];
+        while let Some(key) = key(&mut bytes).unwrap() {
+            keys.push(key)
+        }
+        keys
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(
+            keys(b"\x1b[A\x1b[B\x1b[C\x1b[D\x1bOH\x1b[4~\x1b[3~\x1b[15~"),
+            vec![
+                Key::Up,
+                Key::Down,
+                Key::Right,
+                Key::Left,
+                Key::Home,
+                Key::End,
+                Key::Delete,
+                Key::Other
+            ],
+        );
+        // Sequences end at their final byte, even unknown ones.
+        assert_eq!(
+            keys(b"\x1b[1;5Cx\x1b[200~y\x1bOPz\x1b[1~"),
+            vec![
+                Key::Other,
+                Key::Char('x'),
+                Key::Other,
+                Key::Char('y'),
+                Key::Other,
+                Key::Char('z'),
+                Key::Home
+            ],
+        );
+    }
+
+    #[test]
+    fn characters() {
+        assert_eq!(
+            keys("a≥\r\x7f\x03".as_bytes()),
+            vec![
+                Key::Char('a'),
+                Key::Char('≥'),
+                Key::Enter,
+                Key::Backspace,
+                Key::Interrupt
+            ],
+        );
+    }
+
+    #[test]
+    fn edition() {
+        let mut buffer = Buffer::default();
+        for key in keys(b"x = 1\x1b[D\x1b[D\x7f\x1b[H\x04\x05 + 2") {
+            buffer.edit(key)
+        }
+        assert_eq!(buffer.text(), "  1 + 2");
+        for key in keys(b"\x1b[D\x1b[D\x0b\x1b[D\x15") {
+            buffer.edit(key)
+        }
+        assert_eq!((buffer.text(), buffer.cursor), ("+".into(), 0));
+    }
+}
@@ -0,0 +1,215 @@
+//! SMT logic of a system.
+//!
+//! Solvers pick better tactics when they know the fragment a problem lives in, Z3 in particular is
+//! much faster on linear problems with a fixed logic. The logic of a system is the narrowest one
+//! covering the types of its variables and the operators of its init, transition and candidates.
+
+mikino_api::prelude!();
+
+use expr::{Cst, Op, PExpr};
+use trans::Sys;
+
+/// Quantifier-free SMT logics of mikino's systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Logic {
+    /// Booleans only.
+    Bool,
+    /// Linear integer arithmetic.
+    Lia,
+    /// Linear rational arithmetic.
+    Lra,
+    /// Linear mixed integer and rational arithmetic.
+    Lira,
+    /// Nonlinear integer arithmetic.
+    Nia,
+    /// Nonlinear rational arithmetic.
+    Nra,
+    /// Nonlinear mixed integer and rational arithmetic.
+    Nira,
+}
+impl Logic {
+    /// SMT-LIB name of the logic.
+    pub fn smt_str(self) -> &'static str {
+        match self {
+            Self::Bool => "QF_UF",
+            Self::Lia => "QF_LIA",
+            Self::Lra => "QF_LRA",
+            Self::Lira => "QF_LIRA",
+            Self::Nia => "QF_NIA",
+            Self::Nra => "QF_NRA",
+            Self::Nira => "QF_NIRA",
+        }
+    }
+
+    /// Description of the logic.
+    pub fn desc(self) -> &'static str {
+        match self {
+            Self::Bool => "booleans only",
+            Self::Lia => "linear integer arithmetic",
+            Self::Lra => "linear rational arithmetic",
+            Self::Lira => "linear integer and rational arithmetic",
+            Self::Nia => "nonlinear integer arithmetic",
+            Self::Nra => "nonlinear rational arithmetic",
+            Self::Nira => "nonlinear integer and rational arithmetic",
+        }
+    }
+
+    /// True if the logic is nonlinear, solvers may not be able to decide some checks.
+    pub fn is_nonlinear(self) -> bool {
+        match self {
+            Self::Nia | Self::Nra | Self::Nira => true,
+            Self::Bool | Self::Lia | Self::Lra | Self::Lira => false,
+        }
+    }
+
+    /// Warning for systems in this logic, if any.
+    pub fn warning(self) -> Option<String> {
+        if self.is_nonlinear() {
+            Some(format!(
+                "system uses {} ({}), the solver may fail to decide some checks",
+                self.desc(),
+                self.smt_str(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Logic of a system.
+    pub fn of_sys(sys: &Sys) -> Self {
+        let mut fragment = Fragment::default();
+        for var in sys.decls().all() {
+            fragment.typ(var.typ())
+        }
+        fragment.expr(sys.init());
+        fragment.expr(sys.trans());
+        for po in sys.po_s().values() {
+            fragment.expr(po);
+        }
+        fragment.logic()
+    }
+}
+
+/// Features of the expressions of a system.
+#[derive(Debug, Clone, Copy, Default)]
+struct Fragment {
+    /// True if integers appear.
+    int: bool,
+    /// True if rationals appear.
+    rat: bool,
+    /// True if some operator application is nonlinear.
+    nonlinear: bool,
+}
+impl Fragment {
+    /// Narrowest logic covering the fragment.
+    fn logic(self) -> Logic {
+        match (self.int, self.rat, self.nonlinear) {
+            (false, false, _) => Logic::Bool,
+            (true, false, false) => Logic::Lia,
+            (false, true, false) => Logic::Lra,
+            (true, true, false) => Logic::Lira,
+            (true, false, true) => Logic::Nia,
+            (false, true, true) => Logic::Nra,
+            (true, true, true) => Logic::Nira,
+        }
+    }
+
+    /// Registers a type.
+    fn typ(&mut self, typ: Typ) {
+        match typ {
+            Typ::Int => self.int = true,
+            Typ::Rat => self.rat = true,
+            Typ::Bool => (),
+        }
+    }
+
+    /// Registers an expression, returns `true` if it is a constant.
+    fn expr<V: HasTyp>(&mut self, expr: &PExpr<V>) -> bool {
+        match expr {
+            PExpr::Cst(cst) => {
+                self.cst(cst);
+                true
+            }
+            PExpr::Var(var) => {
+                self.typ(var.typ());
+                false
+            }
+            PExpr::App { op, args } => {
+                let cst_args: Vec<bool> = args.iter().map(|arg| self.expr(arg)).collect();
+                let non_cst_count = cst_args.iter().filter(|is_cst| !**is_cst).count();
+                match op {
+                    Op::Mul => self.nonlinear = self.nonlinear || non_cst_count > 1,
+                    // Dividing by a non-constant is nonlinear.
+                    Op::Div | Op::IDiv | Op::Mod => {
+                        self.nonlinear =
+                            self.nonlinear || cst_args.iter().skip(1).any(|is_cst| !is_cst)
+                    }
+                    _ => (),
+                }
+                match op {
+                    Op::Div => self.rat = true,
+                    Op::IDiv | Op::Mod => self.int = true,
+                    _ => (),
+                }
+                non_cst_count == 0
+            }
+        }
+    }
+
+    /// Registers a constant.
+    fn cst(&mut self, cst: &Cst) {
+        self.typ(cst.typ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Logic of a system with variables `svars` and candidate `po`, `init` and `trans` are trivial.
+    fn logic(svars: &str, po: &str) -> Logic {
+        let txt = format!(
+            "svars {{ {} }} init {{ ⊤ }} trans {{ ⊤ }} candidates {{ \"po\": {} }}",
+            svars, po
+        );
+        Logic::of_sys(&parse::trans(&txt).unwrap())
+    }
+
+    #[test]
+    fn logics() {
+        let table = [
+            ("a: bool, b: bool", "a ∧ (b ⇒ ¬a)", Logic::Bool),
+            ("n: int, m: int", "n + 2 * m ≥ 7", Logic::Lia),
+            ("n: int", "n * 3 = 6 * 2", Logic::Lia),
+            ("n: int", "(2 + 1) * n = n", Logic::Lia),
+            ("n: int", "n % 2 = 0", Logic::Lia),
+            ("x: rat, y: rat", "x + 0.5 * y > 1.0", Logic::Lra),
+            ("x: rat", "x / 2.0 < 1.0", Logic::Lra),
+            ("x: rat", "x / (1.0 + 1.0) < 1.0", Logic::Lra),
+            ("n: int, x: rat", "n ≥ 0 ∧ x ≥ 0.0", Logic::Lira),
+            ("n: int, m: int", "n * m ≥ 0", Logic::Nia),
+            ("n: int", "n * n * 2 ≥ 0", Logic::Nia),
+            ("n: int, m: int", "n % m = 0", Logic::Nia),
+            ("x: rat, y: rat", "x * y > 0.0", Logic::Nra),
+            ("x: rat, y: rat", "x / y > 1.0", Logic::Nra),
+            ("n: int, x: rat", "n ≥ 0 ∧ x * x ≥ 0.0", Logic::Nira),
+        ];
+        for (svars, po, expected) in table {
+            assert_eq!(logic(svars, po), expected, "{} | {}", svars, po);
+        }
+    }
+
+    #[test]
+    fn nonlinear_warning() {
+        for logic in [Logic::Bool, Logic::Lia, Logic::Lra, Logic::Lira] {
+            assert_eq!(logic.warning(), None)
+        }
+        assert_eq!(
+            logic("n: int, m: int", "n * m ≥ 0").warning().unwrap(),
+            "system uses nonlinear integer arithmetic (QF_NIA), \
+            the solver may fail to decide some checks"
+        );
+        assert!(Logic::Nra.warning().is_some());
+        assert!(Logic::Nira.warning().is_some());
+    }
+}
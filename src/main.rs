@@ -1,26 +1,84 @@
-#![allow(dead_code)]
-
 mikino_api::prelude!();
 
-use std::{collections::BTreeSet as Set, io::Write, ops::Deref, path::PathBuf};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet as Set,
+    io::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use check::{BaseRes, CheckRes, StepRes};
+use check::{BaseRes, BmcRes, CheckRes, StepRes};
 use trans::Sys;
 
 use ansi_term::{Colour, Style};
 
 #[macro_export]
 macro_rules! prelude {
-    {} => { use crate::prelude::*; };
-    { pub } => { pub use crate::prelude::*; };
+    {} => { use $crate::prelude::*; };
+    { pub } => { pub use $crate::prelude::*; };
 }
 
+pub mod cex;
+pub mod checkpoint;
+pub mod debugger;
+pub mod doctor;
+pub mod export;
+pub mod fmt;
+pub mod graph;
+pub mod hsmt;
+pub mod html;
+pub mod import;
+pub mod interrupt;
+pub mod line;
+pub mod logic;
 pub mod mode;
+pub mod portfolio;
+pub mod progress;
+pub mod repl;
+pub mod replay;
+pub mod session;
+pub mod solver;
+pub mod spacer;
+pub mod stats;
+
+use mode::{Engine, Mode};
 
-use mode::Mode;
+/// Hint added to solver creation errors.
+const DOCTOR_HINT: &str = "run `mikino doctor` to diagnose your solver setup";
 
 /// Entry point.
 pub fn main() {
+    let args: Vec<_> = std::env::args_os().collect();
+    // Running a solver, see `solver::wrap`.
+    let spec = args
+        .last()
+        .and_then(|arg| arg.to_str())
+        .and_then(|arg| arg.strip_prefix(solver::WRAPPER_ARG));
+    if let Some(spec) = spec {
+        if let Err(e) = solver::exec_solver(spec, &args[1..args.len() - 1]) {
+            for e in e.iter() {
+                eprintln!("mikino solver wrapper: {}", e)
+            }
+        }
+        std::process::exit(2)
+    }
+    // Standing in for a solver, see `--replay_solver`.
+    let replay_dir = args
+        .iter()
+        .skip(1)
+        .filter_map(|arg| arg.to_str())
+        .find_map(|arg| arg.strip_prefix(replay::SERVE_ARG));
+    if let Some(dir) = replay_dir {
+        if let Err(e) = replay::serve(dir) {
+            for e in e.iter() {
+                eprintln!("mikino replay solver: {}", e)
+            }
+            std::process::exit(2)
+        }
+        return;
+    }
     Run::new().launch()
 }
 
@@ -39,6 +97,18 @@ pub struct Run {
     pub verb: usize,
     /// Z3 command.
     pub z3_cmd: String,
+    /// SMT solver backend.
+    pub solver: solver::Backend,
+    /// SMT solver command.
+    pub solver_cmd: String,
+    /// Options passed to every solver session.
+    pub solver_opts: Vec<(String, String)>,
+    /// Random seed of every solver session.
+    pub seed: Option<u64>,
+    /// Memory limit of every solver process in megabytes, if any.
+    pub mem_limit: Option<u64>,
+    /// Solver sessions, shared by the engines.
+    pub sessions: session::Pool,
     /// Run mode.
     pub mode: Mode,
 }
@@ -50,6 +120,9 @@ impl Deref for Run {
 }
 impl Run {
     /// Constructor, handles CLAP.
+    ///
+    /// Not a `Default`, the environment comes from the command line.
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         use clap::*;
         let app = clap::Command::new("mikino")
@@ -71,11 +144,30 @@ impl Run {
                     .long("z3_cmd")
                     .takes_value(true)
                     .default_value("z3")
-                    .help("specifies the command to run Z3"),
+                    .help("specifies the command to run Z3, also used by Z3-specific engines such as Spacer"),
+                Arg::new("SOLVER")
+                    .long("solver")
+                    .takes_value(true)
+                    .possible_values(solver::Backend::KEYS)
+                    .default_value("z3")
+                    .help("specifies the SMT solver used by induction, BMC and scripts"),
+                Arg::new("SOLVER_CMD")
+                    .long("solver_cmd")
+                    .takes_value(true)
+                    .value_name("CMD")
+                    .help(
+                        "specifies the command to run the SMT solver, \
+                        defaults to `--z3_cmd` for Z3 and to the solver's usual binary otherwise",
+                    ),
                 Arg::new("QUIET")
                     .short('q')
                     .help("Quiet output, only shows the final result (/!\\ hides counterexamples)"),
                 mode::cla::smt_log_arg(),
+                mode::cla::solver_opt_arg(),
+                mode::cla::seed_arg(),
+                mode::cla::mem_limit_arg(),
+                mode::cla::replay_solver_arg(),
+                mode::cla::portfolio_arg(),
             ])
             .subcommands(mode::Mode::subcommands())
             .subcommand_required(true)
@@ -85,10 +177,52 @@ impl Run {
         let color = matches.occurrences_of("NO_COLOR") == 0;
         let verb = ((matches.occurrences_of("VERB") + 1) % 4) as usize;
         let quiet = matches.occurrences_of("QUIET") > 0;
-        let z3_cmd = matches
+        let z3_cmd: String = matches
             .value_of("Z3_CMD")
             .expect("argument with default value")
             .into();
+        let solver = matches
+            .value_of("SOLVER")
+            .and_then(solver::Backend::of_str)
+            .expect("argument with default value and possible values");
+        let replay_solver = mode::cla::get_replay_solver(&matches);
+        let solver_cmd = match (&replay_solver, matches.value_of("SOLVER_CMD")) {
+            (Some(dir), _) => {
+                // The solver is mikino itself answering with the recorded answers, the directory
+                // goes to the solver's options.
+                let exe = std::env::current_exe()
+                    .map(|exe| exe.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| "mikino".into());
+                format!("{} {}{}", exe, replay::SERVE_ARG, dir)
+            }
+            (None, Some(cmd)) => cmd.into(),
+            (None, None) if solver == solver::Backend::Z3 => z3_cmd.clone(),
+            (None, None) => solver.default_cmd().into(),
+        };
+        let solver_opts = mode::cla::get_solver_opts(&matches);
+        let seed = mode::cla::get_seed(&matches);
+        let mem_limit = mode::cla::get_mem_limit(&matches);
+        let portfolio = mode::cla::get_portfolio(&matches)
+            .into_iter()
+            .map(|(name, backend, opts)| {
+                // Members using the `--solver` backend run its command with its options.
+                let (cmd, opts) = if backend == solver {
+                    (solver_cmd.clone(), [solver_opts.clone(), opts].concat())
+                } else if backend == solver::Backend::Z3 {
+                    (z3_cmd.clone(), opts)
+                } else {
+                    (backend.default_cmd().into(), opts)
+                };
+                portfolio::Member {
+                    name,
+                    backend,
+                    cmd,
+                    opts,
+                    seed,
+                    mem_limit,
+                }
+            })
+            .collect();
         let smt_log = mode::cla::get_smt_log(&matches);
         let verb = if quiet {
             0
@@ -105,28 +239,44 @@ impl Run {
             styles: Styles::new(color),
             verb,
             z3_cmd,
+            solver,
+            solver_cmd,
+            solver_opts,
+            seed,
+            mem_limit,
+            // Recorded answers are per engine, engines cannot share a replayed solver.
+            sessions: session::Pool::new(replay_solver.is_none(), portfolio),
             mode,
         }
     }
 
     /// Launches whatever the user told us to do.
     pub fn launch(&self) {
+        interrupt::watch(self.styles.clone());
         if let Err(e) = self.run() {
-            println!("|===| {}", self.red.paint("Error"));
-            for (e_idx, e) in e.into_iter().enumerate() {
-                for (l_idx, line) in e.pretty(&self.styles).lines().enumerate() {
-                    let pref = if e_idx == 0 {
-                        "| "
-                    } else if l_idx == 0 {
-                        "| - "
-                    } else {
-                        "|   "
-                    };
-                    println!("{}{}", pref, line);
-                }
+            // Errors caused by the interruption, the partial result is on its way.
+            interrupt::wait_if_interrupted();
+            self.print_error(e);
+            std::process::exit(2)
+        }
+    }
+
+    /// Prints an error.
+    pub fn print_error(&self, e: ErrorChain) {
+        println!("|===| {}", self.red.paint("Error"));
+        for (e_idx, e) in e.into_iter().enumerate() {
+            for (l_idx, line) in e.pretty(&self.styles).lines().enumerate() {
+                let pref = if e_idx == 0 {
+                    "| "
+                } else if l_idx == 0 {
+                    "| - "
+                } else {
+                    "|   "
+                };
+                println!("{}{}", pref, line);
             }
-            println!("|===|");
         }
+        println!("|===|");
     }
 
     /// Runs the mode.
@@ -136,9 +286,28 @@ impl Run {
                 input,
                 smt_log,
                 induction,
+                engine,
                 bmc,
                 bmc_max,
+                bmc_min,
+                checkpoint,
+                resume,
+                cex_dump,
+                cex_script,
+                cex_layout,
+                cex_diff,
+                html,
+                stats,
             } => {
+                if let (Some(min), Some(max)) = (bmc_min, bmc_max) {
+                    if min > max {
+                        bail!(
+                            "BMC minimum depth {} is above maximum depth {}, nothing to check",
+                            min,
+                            max
+                        )
+                    }
+                }
                 if let Some(smt_log) = smt_log {
                     if !std::path::Path::new(smt_log).exists() {
                         std::fs::create_dir_all(smt_log).chain_err(|| {
@@ -146,18 +315,100 @@ impl Run {
                         })?
                     }
                 }
-                let check = Check::new(self, input, smt_log)?;
-                let (base, step) = if *induction {
+                let mut times = vec![];
+                let start = Instant::now();
+                let mut check = Check::new(self, input, smt_log)?;
+                check.cex_dump = cex_dump.clone();
+                check.cex_script = cex_script.clone();
+                check.cex_layout = *cex_layout;
+                check.cex_diff = *cex_diff;
+                check.bmc_min = *bmc_min;
+                check.checkpoint = checkpoint.clone();
+                check.resume = resume.clone();
+                let parse_time = start.elapsed();
+                times.push(("parsing", parse_time));
+
+                let start = Instant::now();
+                let mut spacer = None;
+                let (base, step) = if *induction && *engine == Engine::Spacer {
+                    let res = check.spacer()?;
+                    times.push(("Spacer", start.elapsed()));
+                    // BMC only looks for the falsifications Spacer found, and for candidates
+                    // Spacer could not decide if asked to.
+                    let mut base = CheckRes::new(&check.sys);
+                    base.okay.retain(|candidate| {
+                        res.falsified().any(|c| c == *candidate)
+                            || (*bmc && res.unknown().any(|c| c == *candidate))
+                    });
+                    spacer = Some(res);
+                    (base.into(), None)
+                } else if *induction {
                     let (base, step) = check.run()?;
+                    times.push(("induction", start.elapsed()));
                     (base, Some(step))
                 } else {
                     (CheckRes::new(&check.sys).into(), None)
                 };
-                if *bmc {
+                let spacer_falsified = spacer
+                    .as_ref()
+                    .map(|res| res.has_falsifications())
+                    .unwrap_or(false);
+                let bmc_res = if *bmc || spacer_falsified {
                     if *induction {
                         println!();
                     }
-                    check.bmc(bmc_max.clone(), &base, step.as_ref())?
+                    let start = Instant::now();
+                    let (bmc_res, depths) = check.bmc(*bmc_max, &base, step.as_ref())?;
+                    times.push(("BMC", start.elapsed()));
+                    Some((bmc_res, depths))
+                } else {
+                    None
+                };
+
+                let stats = if *stats {
+                    let mut stats = stats::Stats::new(
+                        parse_time,
+                        check.engine_times.borrow().clone(),
+                        check.depth_times.borrow().clone(),
+                        self.sessions.counters(),
+                    );
+                    stats.count_cexs(base.cexs.values());
+                    if let Some(step) = &step {
+                        stats.count_cexs(step.cexs.values())
+                    }
+                    if let Some((bmc_res, _)) = &bmc_res {
+                        stats.count_cexs(bmc_res.cexs.values())
+                    }
+                    self.present_stats(&stats);
+                    Some(stats)
+                } else {
+                    None
+                };
+
+                let wins = check.wins.borrow();
+                if !wins.is_empty() {
+                    self.present_wins(&wins)
+                }
+
+                if let Some(html) = html {
+                    let report = html::Report {
+                        sys: &check.sys,
+                        input,
+                        src: &check.txt,
+                        // After Spacer, `base` only lists the candidates BMC looked at.
+                        base: step.as_ref().map(|_| &base),
+                        step: step.as_ref(),
+                        spacer: spacer.as_ref(),
+                        bmc: bmc_res.as_ref().map(|(res, depths)| (res, *depths)),
+                        times,
+                        stats: stats.as_ref(),
+                        wins: &wins,
+                    };
+                    report.write(html)?;
+                    if self.verb > 0 {
+                        println!();
+                        println!("wrote HTML report to `{}`", self.bold.paint(html));
+                    }
                 }
                 Ok(())
             }
@@ -165,6 +416,7 @@ impl Run {
                 input,
                 smt_log,
                 verb,
+                debug,
             } => {
                 if let Some(smt_log) = smt_log {
                     if !std::path::Path::new(smt_log).exists() {
@@ -174,14 +426,384 @@ impl Run {
                     }
                 }
 
-                run_script(self, input, smt_log, *verb)
+                run_script(self, input, smt_log, *verb, *debug)
                     .chain_err(|| format!("running `{}` script", self.styles.bold.paint(input)))
             }
+            Mode::Repl { smt_log } => {
+                if let Some(smt_log) = smt_log {
+                    if !std::path::Path::new(smt_log).exists() {
+                        std::fs::create_dir_all(smt_log).chain_err(|| {
+                            format!("while recursively creating SMT log directory `{}`", smt_log)
+                        })?
+                    }
+                }
+                repl::Repl::new(self, smt_log)?.run()
+            }
             Mode::Demo { target, check } => self.write_demo(target, *check),
             Mode::Parse { input } => {
                 let _check = Check::new(self, input, &None)?;
                 Ok(())
             }
+            Mode::Export {
+                input,
+                target,
+                format,
+            } => {
+                let check = Check::new(self, input, &None)?;
+                format.write(&check.sys, target)?;
+                if self.verb > 0 {
+                    println!(
+                        "wrote {} translation of `{}` to `{}`",
+                        format.desc(),
+                        self.bold.paint(input),
+                        self.bold.paint(target),
+                    )
+                }
+                Ok(())
+            }
+            Mode::Fmt { inputs, check } => {
+                let mut unformatted = vec![];
+                for input in inputs {
+                    let txt = std::fs::read_to_string(input)
+                        .chain_err(|| format!("while reading file `{}`", input))?;
+                    let formatted = fmt::format(&txt)
+                        .chain_err(|| format!("while formatting `{}`", self.bold.paint(input)))?;
+                    if formatted == txt {
+                        continue;
+                    }
+                    if *check {
+                        println!(
+                            "`{}` is {}",
+                            self.bold.paint(input),
+                            self.red.paint("not formatted")
+                        );
+                        unformatted.push(input);
+                    } else {
+                        std::fs::write(input, &formatted)
+                            .chain_err(|| format!("while writing file `{}`", input))?;
+                        if self.verb > 0 {
+                            println!("formatted `{}`", self.bold.paint(input))
+                        }
+                    }
+                }
+                if !unformatted.is_empty() {
+                    println!(
+                        "{} file(s) not formatted, run `{}` to format them",
+                        unformatted.len(),
+                        self.bold.paint("mikino fmt")
+                    );
+                    std::process::exit(1)
+                }
+                Ok(())
+            }
+            Mode::Graph { input } => {
+                let check = Check::new(self, input, &None)?;
+                let stdout = std::io::stdout();
+                graph::Graph::new(&check.sys).write_dot(&mut stdout.lock())
+            }
+            Mode::Doctor => self.doctor(),
+            Mode::Replay { dir, record } => self.replay(dir, *record),
+        }
+    }
+
+    /// Replays the SMT logs of a directory, exits with status `1` if some check-sat answers differ
+    /// from the recorded ones.
+    pub fn replay(&self, dir: &str, record: bool) -> Res<()> {
+        /// Minimal slowdown factor reported.
+        const SLOWDOWN_FACTOR: f64 = 2.0;
+        /// Minimal slowdown reported.
+        const SLOWDOWN_MIN: Duration = Duration::from_millis(50);
+        /// Maximal number of slowdowns reported per log.
+        const SLOWDOWN_MAX_COUNT: usize = 5;
+
+        let logs = replay::logs(dir)?;
+        if logs.is_empty() {
+            bail!("no SMT log (`.smt2` file) in directory `{}`", dir)
+        }
+        let conf = self.solver_conf()?;
+        let total = |answers: &[replay::Answer]| {
+            answers
+                .iter()
+                .fold(Duration::from_secs(0), |total, answer| total + answer.time)
+        };
+        let one_line = |s: &str| {
+            let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+            if s.chars().count() > 60 {
+                format!("{}...", s.chars().take(57).collect::<String>())
+            } else {
+                s
+            }
+        };
+
+        let mut verdict_diffs = 0;
+        println!(
+            "|===| {} of `{}` on {}",
+            self.bold.paint("Replay"),
+            self.bold.paint(dir),
+            self.solver.desc()
+        );
+        for (log_idx, log) in logs.iter().enumerate() {
+            if log_idx > 0 {
+                println!("|")
+            }
+            let name = log
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let commands = replay::commands(log)?;
+            let replayed = replay::run(&conf, &commands)
+                .chain_err(|| format!("while replaying `{}`", log.display()))?;
+
+            let answers_path = replay::answers_path(log);
+            if record || !answers_path.exists() {
+                replay::write_answers(&answers_path, &replayed)?;
+                println!(
+                    "| `{}`: recorded {} answer(s) in `{}`, {:.3}s",
+                    self.bold.paint(&name),
+                    replayed.len(),
+                    answers_path.display(),
+                    total(&replayed).as_secs_f64(),
+                );
+                continue;
+            }
+
+            let recorded = replay::read_answers(&answers_path)?;
+            let diffs = replay::compare(&commands, &recorded, &replayed);
+            let (old, new) = (total(&recorded), total(&replayed));
+            let change = if old.as_secs_f64() > 0.0 {
+                format!(
+                    " ({:+.0}%)",
+                    (new.as_secs_f64() / old.as_secs_f64() - 1.0) * 100.0
+                )
+            } else {
+                String::new()
+            };
+            let diff_desc = if diffs.is_empty() {
+                self.green.paint("no difference").to_string()
+            } else {
+                self.red
+                    .paint(format!("{} difference(s)", diffs.len()))
+                    .to_string()
+            };
+            println!(
+                "| `{}`: {} answer(s), {}, {:.3}s -> {:.3}s{}",
+                self.bold.paint(&name),
+                replayed.len(),
+                diff_desc,
+                old.as_secs_f64(),
+                new.as_secs_f64(),
+                change,
+            );
+
+            for diff in &diffs {
+                if diff.is_verdict() {
+                    verdict_diffs += 1
+                }
+                let answer = |answer: &Option<String>| match answer {
+                    Some(answer) => format!("`{}`", one_line(answer)),
+                    None => "nothing".into(),
+                };
+                println!(
+                    "|   command #{} `{}`: recorded {}, got {}",
+                    diff.idx,
+                    one_line(&diff.command),
+                    answer(&diff.recorded),
+                    if diff.is_verdict() {
+                        self.red.paint(answer(&diff.replayed))
+                    } else {
+                        self.bold.paint(answer(&diff.replayed))
+                    },
+                )
+            }
+
+            let recorded_times: std::collections::BTreeMap<_, _> = recorded
+                .iter()
+                .map(|answer| (answer.idx, answer.time))
+                .collect();
+            let mut slowdowns: Vec<_> = replayed
+                .iter()
+                .filter_map(|answer| {
+                    let old = *recorded_times.get(&answer.idx)?;
+                    let slower = answer.time.as_secs_f64() >= old.as_secs_f64() * SLOWDOWN_FACTOR
+                        && answer.time >= old + SLOWDOWN_MIN;
+                    if slower {
+                        Some((answer.idx, old, answer.time))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            slowdowns.sort_by_key(|(_, old, new)| std::cmp::Reverse(*new - *old));
+            for (idx, old, new) in slowdowns.into_iter().take(SLOWDOWN_MAX_COUNT) {
+                println!(
+                    "|   {} on command #{} `{}`: {:.3}s -> {:.3}s",
+                    self.red.paint("slowdown"),
+                    idx,
+                    one_line(commands.get(idx).map(String::as_str).unwrap_or("")),
+                    old.as_secs_f64(),
+                    new.as_secs_f64(),
+                )
+            }
+        }
+        println!("|===|");
+
+        if verdict_diffs > 0 {
+            println!(
+                "{} check-sat answer(s) differ from the recorded ones",
+                self.red.paint(verdict_diffs.to_string())
+            );
+            std::process::exit(1)
+        }
+        Ok(())
+    }
+
+    /// Diagnoses the environment, exits with status `1` if some problems are found.
+    pub fn doctor(&self) -> Res<()> {
+        use doctor::Status;
+        let diagnostics = doctor::diagnose(self);
+        let width = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.what.len())
+            .max()
+            .unwrap_or(0);
+
+        println!("|===| {}", self.bold.paint("Mikino doctor"));
+        for diagnostic in &diagnostics {
+            let status = match diagnostic.status {
+                Status::Ok => self.green.paint("ok     "),
+                Status::Warning => self.bold.paint("warning"),
+                Status::Problem => self.red.paint("problem"),
+                Status::Skipped => self.gray.paint("skipped"),
+            };
+            println!(
+                "| {: <3$} {} {}",
+                diagnostic.what, status, diagnostic.info, width
+            );
+            if let Some(fix) = &diagnostic.fix {
+                println!("| {: <2$} fix: {}", "", fix, width + 8);
+            }
+        }
+        println!("|===|");
+
+        let problems = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.status == Status::Problem)
+            .count();
+        if problems > 0 {
+            println!(
+                "{} problem(s) found, mikino cannot run until they are fixed",
+                self.red.paint(problems.to_string())
+            );
+            std::process::exit(1)
+        }
+        Ok(())
+    }
+
+    /// Presents the statistics of a check run.
+    pub fn present_stats(&self, stats: &stats::Stats) {
+        let secs = |time: Duration| format!("{:.3}s", time.as_secs_f64());
+        let depth_width = stats
+            .bmc_depths
+            .last()
+            .map(|(depth, _)| depth.to_string().len())
+            .unwrap_or(1);
+        let mut rows = vec![("parsing".to_string(), secs(stats.parse))];
+        for (engine, time) in &stats.engines {
+            rows.push((engine.to_string(), secs(*time)));
+            if *engine == "bmc" {
+                for (depth, time) in &stats.bmc_depths {
+                    rows.push((format!("  depth {: >1$}", depth, depth_width), secs(*time)))
+                }
+            }
+        }
+        rows.push(("total".into(), secs(stats.total())));
+        rows.push(("solver instances".into(), stats.solvers.to_string()));
+        rows.push(("solver calls".into(), stats.solver_calls.to_string()));
+        rows.push(("check-sats".into(), stats.check_sats.to_string()));
+        rows.push(("check-sat time".into(), secs(stats.check_sat_time)));
+        rows.push(("solver restarts".into(), stats.restarts.to_string()));
+        rows.push((
+            "peak cex length".into(),
+            format!("{} state(s)", stats.peak_cex_len),
+        ));
+
+        let width = rows.iter().map(|(desc, _)| desc.len()).max().unwrap_or(0);
+        println!();
+        println!("|===| {}", self.bold.paint("Statistics"));
+        for (desc, val) in rows {
+            println!("| {: <2$} {}", desc, val, width)
+        }
+        println!("|===|");
+    }
+
+    /// Presents the solvers that won the queries of a portfolio.
+    pub fn present_wins(&self, wins: &[portfolio::Win]) {
+        let secs = |time: Duration| format!("{:.3}s", time.as_secs_f64());
+        let tally = portfolio::tally(wins);
+        let name_width = tally.keys().map(|name| name.len()).max().unwrap_or(0);
+        let query_width = wins
+            .iter()
+            .map(|win| win.engine.len() + 1 + win.query.len())
+            .max()
+            .unwrap_or(0);
+
+        println!();
+        println!("|===| {}", self.bold.paint("Portfolio"));
+        for (name, (count, time)) in &tally {
+            println!(
+                "| {: <4$} won {} quer{}, {}",
+                name,
+                count,
+                if *count == 1 { "y" } else { "ies" },
+                secs(*time),
+                name_width,
+            )
+        }
+        println!("|");
+        for win in wins {
+            println!(
+                "| {: <3$} {: <4$} {}",
+                format!("{} {}", win.engine, win.query),
+                win.winner,
+                secs(win.time),
+                query_width,
+                name_width,
+            )
+        }
+        println!("|===|");
+    }
+
+    /// Configuration of the SMT solver.
+    pub fn solver_conf(&self) -> Res<SmtConf> {
+        self.solver_conf_for(None)
+    }
+
+    /// Configuration of the SMT solver for problems in some logic.
+    pub fn solver_conf_for(&self, logic: Option<logic::Logic>) -> Res<SmtConf> {
+        let mut conf = self.solver.conf(&self.solver_cmd, logic).chain_err(|| {
+            format!(
+                "while configuring {} with command `{}`",
+                self.solver.desc(),
+                self.solver_cmd
+            )
+        })?;
+        self.solver
+            .add_options(&mut conf, &self.solver_opts, self.seed)
+            .chain_err(|| format!("while passing options to {}", self.solver.desc()))?;
+        solver::wrap(&mut conf, self.mem_limit)?;
+        Ok(conf)
+    }
+
+    /// Configuration of Z3, for Z3-specific engines.
+    ///
+    /// Same as [`Self::solver_conf`] if the solver is Z3.
+    pub fn z3_conf(&self) -> Res<SmtConf> {
+        if self.solver == solver::Backend::Z3 {
+            self.solver_conf()
+        } else {
+            let mut conf = z3_cmd_to_conf(&self.z3_cmd)?;
+            solver::wrap(&mut conf, self.mem_limit)?;
+            Ok(conf)
         }
     }
 
@@ -220,6 +842,7 @@ pub fn run_script(
     script_path: impl AsRef<std::path::Path>,
     smt_log_dir: &Option<String>,
     verb: usize,
+    debug: bool,
 ) -> Res<()> {
     let with_pos = verb > 0;
     let script_path = script_path.as_ref();
@@ -266,9 +889,15 @@ pub fn run_script(
     if env.verb >= 3 {
         println!("parsing {}", env.styles.green.paint("successful"));
     }
+    let (mut debugger, script) = if debug {
+        let (debugger, script) = debugger::Debugger::new(env, script);
+        (Some(debugger), script)
+    } else {
+        (None, script)
+    };
 
     let mut runner = {
-        let conf = z3_cmd_to_conf(&env.z3_cmd)?;
+        let conf = env.solver_conf()?;
         let tee = smt_log_dir.as_ref().map(|s| {
             let mut path = PathBuf::from(s);
             path.push("script.smt2");
@@ -284,12 +913,23 @@ pub fn run_script(
 
     'step: loop {
         use mikino_api::script::{Outcome, Step};
-        match runner.step().chain_err(|| {
+        let step = runner.step().chain_err(|| {
             format!(
                 "performing script step for file `{}`",
                 env.bold.paint(script_path.display().to_string())
             )
-        })? {
+        })?;
+        if let Some(debugger) = debugger.as_mut() {
+            match debugger.handle(&step, &mut runner.solver, &script_content)? {
+                debugger::Handled::Runner => (),
+                debugger::Handled::Internal => continue 'step,
+                debugger::Handled::Quit => bail!(
+                    "stopped debugging script `{}` before it completed",
+                    env.bold.paint(script_path.display().to_string())
+                ),
+            }
+        }
+        match step {
             Step::Done(Outcome::Exit(_span_opt, code)) => {
                 println!(
                     "{}",
@@ -319,14 +959,98 @@ pub fn run_script(
     Ok(())
 }
 
+/// Describes the BMC depths from `first` to `last`, as in "no falsification in ...".
+pub fn bmc_depths_desc(first: Unroll, last: Unroll) -> String {
+    if first == 0 {
+        format!("{} step(s) or less", last)
+    } else {
+        format!("depths {} to {}", first, last)
+    }
+}
+
+/// Splits the columns of a table in chunks that fit in `term_width`.
+///
+/// Each row starts with `prefix_width` characters, and each column takes its width in `widths`
+/// plus a three-character separator. A chunk has at least one column even if it does not fit.
+pub fn column_chunks(
+    prefix_width: usize,
+    widths: &[usize],
+    term_width: usize,
+) -> Vec<std::ops::Range<usize>> {
+    let mut chunks = vec![];
+    let (mut start, mut width) = (0, prefix_width);
+    for (idx, col_width) in widths.iter().enumerate() {
+        if idx > start && width + 3 + col_width > term_width {
+            chunks.push(start..idx);
+            start = idx;
+            width = prefix_width;
+        }
+        width += 3 + col_width;
+    }
+    chunks.push(start..widths.len());
+    chunks
+}
+
+/// Width of the terminal, in columns.
+///
+/// Asks the terminal mikino writes to if any, falls back to the `COLUMNS` environment variable,
+/// and then to 80.
+pub fn term_width() -> usize {
+    #[cfg(unix)]
+    {
+        let mut size = std::mem::MaybeUninit::<libc::winsize>::zeroed();
+        for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, size.as_mut_ptr()) } == 0 {
+                let cols = unsafe { size.assume_init() }.ws_col;
+                if cols > 0 {
+                    return cols.into();
+                }
+            }
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|cols| cols.parse::<usize>().ok())
+        .filter(|cols| *cols > 0)
+        .unwrap_or(80)
+}
+
 /// Check environment.
 pub struct Check<'env> {
     /// Run env.
     pub env: &'env Run,
     /// System to check.
     pub sys: Sys,
+    /// Source of the system, empty for binary formats.
+    pub txt: String,
+    /// Path to the system's file.
+    pub input: String,
     /// Optional SMT log directory.
     pub smt_log_dir: Option<String>,
+    /// SMT logic of the system.
+    pub logic: logic::Logic,
+    /// Key of the system in the solver session pool.
+    pub session_key: usize,
+    /// Optional counterexample dump configuration.
+    pub cex_dump: Option<cex::Dump>,
+    /// Optional counterexample script dump configuration.
+    pub cex_script: Option<cex::ScriptDump>,
+    /// Layout of the counterexamples shown on the terminal.
+    pub cex_layout: cex::Layout,
+    /// True if counterexamples only show changes after the first step.
+    pub cex_diff: bool,
+    /// Depth BMC starts at, if any.
+    pub bmc_min: Option<Unroll>,
+    /// File BMC saves its progress to, if any.
+    pub checkpoint: Option<String>,
+    /// Checkpoint BMC resumes from, if any.
+    pub resume: Option<String>,
+    /// Time spent in each engine that ran, named after their SMT log.
+    pub engine_times: RefCell<Vec<(&'static str, Duration)>>,
+    /// Time spent at each BMC depth.
+    pub depth_times: RefCell<Vec<(Unroll, Duration)>>,
+    /// Solver that won each query, with `--portfolio`.
+    pub wins: RefCell<Vec<portfolio::Win>>,
 }
 impl<'env> Deref for Check<'env> {
     type Target = Styles;
@@ -342,10 +1066,15 @@ impl<'env> Check<'env> {
         let smt_log_dir = smt_log_dir.clone();
         let mut file = OpenOptions::new().read(true).open(input)?;
 
-        let mut txt = String::new();
-        file.read_to_string(&mut txt)?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
 
-        let sys = parse::trans(&txt)?;
+        let sys = import::parse(input, &bytes)?;
+        let txt = if import::is_binary(input) {
+            String::new()
+        } else {
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
         if env.verb >= 3 {
             println!("|===| Parsing {}:", env.styles.green.paint("successful"));
             for line in sys.to_ml_string().lines() {
@@ -354,18 +1083,41 @@ impl<'env> Check<'env> {
             println!("|===|");
             println!()
         }
+        let logic = logic::Logic::of_sys(&sys);
+        if let Some(warning) = logic.warning().filter(|_| env.verb > 0) {
+            println!("{}: {}", env.bold.paint("warning"), warning);
+            println!()
+        }
+        interrupt::checking(input);
 
         Ok(Self {
             env,
             sys,
+            txt,
+            input: input.into(),
             smt_log_dir,
+            logic,
+            session_key: env.sessions.new_system(),
+            cex_dump: None,
+            cex_script: None,
+            cex_layout: cex::Layout::Blocks,
+            cex_diff: false,
+            bmc_min: None,
+            checkpoint: None,
+            resume: None,
+            engine_times: RefCell::new(vec![]),
+            depth_times: RefCell::new(vec![]),
+            wins: RefCell::new(vec![]),
         })
     }
 
     /// Attemps to prove the candidates on a system.
-    pub fn run(&self) -> Res<(BaseRes, StepRes)> {
+    pub fn run(&self) -> Res<(BaseRes<'_>, StepRes<'_>)> {
         let base_res = self.base_check()?;
         let step_res = self.step_check()?;
+        for candidate in base_res.okay.intersection(&step_res.okay) {
+            interrupt::proved(candidate)
+        }
 
         println!("|===| {} attempt result", self.bold.paint("Induction"));
 
@@ -462,16 +1214,175 @@ impl<'env> Check<'env> {
         Ok((base_res, step_res))
     }
 
+    /// Attempts to prove the candidates on a system with Spacer.
+    pub fn spacer(&self) -> Res<spacer::SpacerRes<'_>> {
+        if self.env.verb > 0 {
+            println!(
+                "running {} on the Horn clause encoding of the system...",
+                self.bold.paint("Spacer")
+            )
+        }
+        let start = Instant::now();
+        let conf = self.env.z3_conf()?;
+        let tee = self.smt_log_dir.as_ref().map(std::path::PathBuf::from);
+        interrupt::running(Some("Spacer"));
+        let progress = self.progress("Spacer", self.sys.po_s().len());
+        let res = spacer::check(&self.sys, conf, tee, self.env.sessions.counters())
+            .chain_err(|| "during Spacer check")?;
+        drop(progress);
+        for (candidate, _) in res.proved() {
+            interrupt::proved(candidate)
+        }
+        for candidate in res.falsified() {
+            interrupt::falsified(candidate, "Spacer", None)
+        }
+        interrupt::running(None);
+        self.engine_times
+            .borrow_mut()
+            .push(("spacer", start.elapsed()));
+
+        println!("|===| {} result", self.bold.paint("Spacer"));
+
+        if res.has_proofs() {
+            println!(
+                "| - the following candidate(s) {} in all reachable states",
+                self.green.paint("hold")
+            );
+            for (candidate, inv) in res.proved() {
+                println!("|   `{}`", self.green.paint(candidate));
+                if self.env.verb > 0 {
+                    match inv {
+                        Some(inv) => println!(
+                            "|     inductive invariant: {}",
+                            hsmt::expr_to_hsmt(inv, |var| var.id().to_string())
+                        ),
+                        None => println!("|     (invariant cannot be represented in mikino)"),
+                    }
+                }
+            }
+        }
+        if res.has_falsifications() {
+            if res.has_proofs() {
+                println!("|")
+            }
+            println!(
+                "| - the following candidate(s) are {}",
+                self.red.paint("falsifiable")
+            );
+            for candidate in res.falsified() {
+                println!("|   `{}`", self.red.paint(candidate))
+            }
+        }
+        if res.has_unknowns() {
+            if res.has_proofs() || res.has_falsifications() {
+                println!("|")
+            }
+            println!(
+                "| - Spacer {} the following candidate(s)",
+                self.red.paint("could not decide")
+            );
+            for candidate in res.unknown() {
+                println!("|   `{}`", self.red.paint(candidate))
+            }
+        }
+
+        println!("|");
+
+        if res.has_falsifications() {
+            println!(
+                "| - system is {}, running BMC to produce counterexample(s)",
+                self.red.paint("unsafe")
+            )
+        } else if res.has_unknowns() {
+            println!("| - system {}", self.red.paint("might be unsafe"));
+        } else {
+            println!(
+                "| - system is {}, all reachable states verify the candidate(s)",
+                self.green.paint("safe")
+            );
+        }
+        if res.has_proofs() && self.env.verb == 0 {
+            println!(
+                "|   (run again without `{}` to see inductive invariants)",
+                self.bold.paint("-q")
+            )
+        }
+
+        println!("|===|");
+
+        Ok(res)
+    }
+
     /// Runs BMC.
-    pub fn bmc(&self, max: Option<usize>, base: &BaseRes, step: Option<&StepRes>) -> Res<()> {
-        let bmc_res = if let Some(step) = step {
+    ///
+    /// Returns the BMC result and the first and last depths checked if any, all depths in between
+    /// were checked.
+    pub fn bmc<'s>(
+        &'s self,
+        max: Option<usize>,
+        base: &BaseRes<'s>,
+        step: Option<&StepRes<'s>>,
+    ) -> Res<(BmcRes<'s>, Option<(Unroll, Unroll)>)> {
+        let mut bmc_res = if let Some(step) = step {
             base.merge_base_with_step(step)
                 .chain_err(|| "during base/step result merge for BMC")?
         } else {
             base.as_inner().clone().into()
         };
-        if bmc_res.all_falsified() {
-            return Ok(());
+        let mut start_depth = self.bmc_min.unwrap_or(0);
+        let mut first_depth = start_depth;
+        let mut resumed = vec![];
+        if let Some(path) = &self.resume {
+            let checkpoint = checkpoint::read(path)?;
+            if checkpoint.fingerprint != checkpoint::fingerprint(&self.sys) {
+                bail!(
+                    "checkpoint `{}` was written for another system (`{}`), \
+                    `{}` does not match its fingerprint",
+                    path,
+                    checkpoint.system,
+                    self.input
+                )
+            }
+            let candidate = |name: &str| {
+                self.sys
+                    .po_s()
+                    .keys()
+                    .find(|candidate| *candidate == name)
+                    .ok_or_else(|| {
+                        format!(
+                            "checkpoint `{}` mentions candidate `{}`, which is not in the system",
+                            path, name
+                        )
+                    })
+            };
+            for name in &checkpoint.okay {
+                candidate(name)?;
+            }
+            for (name, cex) in checkpoint.falsified {
+                let candidate = candidate(&name)?;
+                if bmc_res.okay.remove(candidate) {
+                    bmc_res.cexs.insert(candidate, cex);
+                    interrupt::falsified(candidate, "BMC", None);
+                    resumed.push(candidate)
+                }
+            }
+            // Depths between the checkpoint and `--bmc_min` are not checked, the depths the
+            // checkpoint covers only count if there is no such gap.
+            if start_depth <= checkpoint.depth + 1 {
+                first_depth = checkpoint.first_depth;
+                start_depth = checkpoint.depth + 1;
+            }
+            println!(
+                "resuming {} from checkpoint `{}` at depth {}, {} candidate(s) already falsified",
+                self.bold.paint("BMC"),
+                path,
+                start_depth,
+                resumed.len()
+            );
+        }
+        // Candidates falsified by the checkpoint still go through the result below.
+        if bmc_res.all_falsified() && resumed.is_empty() {
+            return Ok((bmc_res, None));
         }
 
         println!(
@@ -480,43 +1391,88 @@ impl<'env> Check<'env> {
             bmc_res.okay.len()
         );
 
-        let conf = z3_cmd_to_conf(&self.env.z3_cmd)?;
-        let tee = self.smt_log_dir.as_ref().map(std::path::PathBuf::from);
-        let mut bmc = check::Bmc::new(&self.sys, conf, tee, bmc_res)?;
-        let mut falsified = Set::new();
+        interrupt::running(Some("BMC"));
+        let bmc_start = Instant::now();
+        let mut bmc = self
+            .session("bmc")
+            .and_then(|session| session::Bmc::new(session, bmc_res))
+            .chain_err(|| "during BMC checker creation")
+            .chain_err(|| DOCTOR_HINT)?;
+        if start_depth > 0 && !bmc.is_done() {
+            if self.env.verb > 0 {
+                println!("skipping depths below {}", start_depth)
+            }
+            bmc.skip_to(start_depth).chain_err(|| {
+                format!("while unrolling the system to depth {} in BMC", start_depth)
+            })?;
+        }
+        let mut falsified: Set<_> = resumed.iter().map(|name| name.to_string()).collect();
+        let progress = self.progress("BMC", bmc.res().okay.len());
+        let mut depth_times = vec![];
 
         while !bmc.is_done() && max.map(|max| max >= bmc.next_check_step()).unwrap_or(true) {
-            let depth_str = bmc.next_check_step().to_string();
+            let depth = bmc.next_check_step();
+            let depth_str = depth.to_string();
+            progress.depth(depth, max, bmc.res().okay.len());
             if self.env.verb > 0 {
+                let _pause = progress.pause();
                 println!(
                     "checking for falsifications at depth {}",
                     self.env.styles.under.paint(&depth_str)
                 );
             }
 
+            let start = Instant::now();
             let new_falsifications = bmc.next_check().chain_err(|| {
                 format!(
                     "while checking for falsifications at depth {} in BMC",
                     self.env.styles.under.paint(&depth_str)
                 )
             })?;
+            depth_times.push((depth, start.elapsed()));
+            interrupt::bmc_depths(first_depth, depth);
+            if let Some(path) = &self.checkpoint {
+                checkpoint::write(
+                    path,
+                    &self.input,
+                    checkpoint::fingerprint(&self.sys),
+                    first_depth,
+                    depth,
+                    bmc.res(),
+                )
+                .chain_err(|| format!("while writing BMC checkpoint `{}`", path))?
+            }
 
             if new_falsifications {
+                let _pause = progress.pause();
                 for (candidate, cex) in bmc.res().cexs.iter() {
                     let is_new = falsified.insert(candidate.to_string());
                     if is_new {
+                        interrupt::falsified(candidate, "BMC", Some(cex.trace.len()));
                         println!(
                             "found a {} at depth {}:",
                             self.red.paint("falsification"),
                             self.env.styles.bold.paint(&depth_str)
                         );
-                        self.present_cex(&self.sys, candidate, cex, true)?
+                        self.present_cex(&self.sys, candidate, cex, true)?;
+                        self.dump_cex(candidate, cex, cex::Origin::Bmc)?
                     }
                 }
             }
         }
 
+        drop(progress);
+        // Depths below the start depth were checked by the checkpoint, if any.
+        let end = std::cmp::max(bmc.next_check_step(), start_depth);
+        let depths = Some((first_depth, end.saturating_sub(1))).filter(|_| end > first_depth);
+        interrupt::running(None);
         let bmc_res = bmc.destroy()?;
+        self.engine_times
+            .borrow_mut()
+            .push(("bmc", bmc_start.elapsed()));
+        self.wins
+            .borrow_mut()
+            .extend(self.env.sessions.take_wins("bmc"));
 
         if self.env.verb > 0 || !bmc_res.cexs.is_empty() {
             println!()
@@ -549,31 +1505,104 @@ impl<'env> Check<'env> {
             println!("| - system is {}", self.red.paint("unsafe"))
         } else {
             println!("| - system {}", self.red.paint("might be unsafe"),);
+            match depths {
+                Some((first, last)) => println!(
+                    "|   no falsification in {} was found for some candidate(s)",
+                    self.bold.paint(bmc_depths_desc(first, last)),
+                ),
+                None => println!("|   BMC did not check any depth"),
+            }
+        }
+        println!("|===|");
+
+        if self.env.verb > 1 {
+            self.present_depth_times(&depth_times)
+        }
+        self.depth_times.borrow_mut().extend(depth_times);
+
+        Ok((bmc_res, depths))
+    }
+
+    /// Solver session for an engine, which logs to `<engine>.smt2` if SMT logs are active.
+    pub fn session(&self, engine: &str) -> Res<session::Session<'_>> {
+        let conf = self.env.solver_conf_for(Some(self.logic))?;
+        let set_logic = !self.env.solver.logic_on_cmd_line();
+        let tee = self.smt_log_dir.as_ref().map(|dir| {
+            let mut path = PathBuf::from(dir);
+            path.push(format!("{}.smt2", engine));
+            path
+        });
+        self.env.sessions.session(
+            &self.sys,
+            self.session_key,
+            self.logic,
+            set_logic,
+            conf,
+            tee,
+        )
+    }
+
+    /// Creates a progress line for an engine, only active on terminals and if not quiet.
+    pub fn progress(&self, engine: &'static str, open: usize) -> progress::Progress {
+        let active = self.env.verb > 0 && progress::Progress::is_available();
+        progress::Progress::new(active, engine, open)
+    }
+
+    /// Presents the time spent at each depth as a histogram.
+    pub fn present_depth_times(&self, times: &[(Unroll, Duration)]) {
+        const BAR_WIDTH: f64 = 40.0;
+        let max = times
+            .iter()
+            .map(|(_, time)| time.as_secs_f64())
+            .fold(0.0, f64::max);
+        let depth_width = times
+            .last()
+            .map(|(depth, _)| depth.to_string().len())
+            .unwrap_or(1);
+        println!();
+        println!("|===| {} time per depth", self.bold.paint("Bmc"));
+        for (depth, time) in times {
+            let secs = time.as_secs_f64();
+            let bar = if max > 0.0 {
+                (secs / max * BAR_WIDTH).round() as usize
+            } else {
+                0
+            };
             println!(
-                "|   no falsification in {} was found for some candidate(s)",
-                self.bold.paint(format!(
-                    "{} step(s) or less",
-                    max.expect(
-                        "[fatal] cannot have BMC with no max end with unfalsified candidates"
-                    ),
-                )),
+                "| {: >3$} | {:>9.3}s | {}",
+                depth,
+                secs,
+                self.green.paint("█".repeat(bar)),
+                depth_width,
             );
         }
         println!("|===|");
-
-        Ok(())
     }
 
     /// Performs the base check.
-    pub fn base_check(&self) -> Res<BaseRes> {
+    pub fn base_check(&self) -> Res<BaseRes<'_>> {
         if self.env.verb > 0 {
             println!("checking {} case...", self.under.paint("base"))
         }
-        let conf = z3_cmd_to_conf(&self.env.z3_cmd)?;
-        let tee = self.smt_log_dir.as_ref().map(std::path::PathBuf::from);
-        let mut base_checker =
-            check::Base::new(&self.sys, conf, tee).chain_err(|| "during base checker creation")?;
-        let res = base_checker.check().chain_err(|| "during base check")?;
+        interrupt::running(Some("base"));
+        let start = Instant::now();
+        let mut session = self
+            .session("base")
+            .chain_err(|| "during base checker creation")
+            .chain_err(|| DOCTOR_HINT)?;
+        let progress = self.progress("base", self.sys.po_s().len());
+        let res = session.base().chain_err(|| "during base check")?;
+        drop(progress);
+        for (candidate, cex) in res.cexs.iter() {
+            interrupt::falsified(candidate, "base", Some(cex.trace.len()))
+        }
+        interrupt::running(None);
+        self.engine_times
+            .borrow_mut()
+            .push(("base", start.elapsed()));
+        self.wins
+            .borrow_mut()
+            .extend(self.env.sessions.take_wins("base"));
         if self.env.verb > 0 {
             if !res.has_falsifications() {
                 println!(
@@ -593,19 +1622,31 @@ impl<'env> Check<'env> {
             }
             println!()
         }
+        self.dump_cexs(&res, cex::Origin::Base)?;
         Ok(res)
     }
 
     /// Performs the step check.
-    pub fn step_check(&self) -> Res<StepRes> {
+    pub fn step_check(&self) -> Res<StepRes<'_>> {
         if self.env.verb > 0 {
             println!("checking {} case...", self.under.paint("step"))
         }
-        let conf = z3_cmd_to_conf(&self.env.z3_cmd)?;
-        let tee = self.smt_log_dir.as_ref().map(std::path::PathBuf::from);
-        let mut step_checker =
-            check::Step::new(&self.sys, conf, tee).chain_err(|| "during step checker creation")?;
-        let res = step_checker.check().chain_err(|| "during step check")?;
+        interrupt::running(Some("step"));
+        let start = Instant::now();
+        let mut session = self
+            .session("step")
+            .chain_err(|| "during step checker creation")
+            .chain_err(|| DOCTOR_HINT)?;
+        let progress = self.progress("step", self.sys.po_s().len());
+        let res = session.step().chain_err(|| "during step check")?;
+        drop(progress);
+        interrupt::running(None);
+        self.engine_times
+            .borrow_mut()
+            .push(("step", start.elapsed()));
+        self.wins
+            .borrow_mut()
+            .extend(self.env.sessions.take_wins("step"));
         if self.env.verb > 0 {
             if !res.has_falsifications() {
                 println!(
@@ -623,9 +1664,51 @@ impl<'env> Check<'env> {
             }
             println!()
         }
+        self.dump_cexs(&res, cex::Origin::Step)?;
         Ok(res)
     }
 
+    /// Writes counterexamples to files, if a counterexample dump is active.
+    pub fn dump_cexs<'sys, R: Deref<Target = CheckRes<'sys>>>(
+        &self,
+        res: &R,
+        origin: cex::Origin,
+    ) -> Res<()> {
+        for (candidate, cex) in res.cexs.iter() {
+            self.dump_cex(candidate, cex, origin)?
+        }
+        Ok(())
+    }
+    /// Writes a counterexample to a file, if a counterexample dump is active.
+    pub fn dump_cex(
+        &self,
+        candidate: &str,
+        cex: &check::cexs::Cex,
+        origin: cex::Origin,
+    ) -> Res<()> {
+        if let Some(dump) = self.cex_dump.as_ref() {
+            let path = dump.write(&self.sys, candidate, cex, origin)?;
+            self.report_dump(candidate, origin, "counterexample", &path)
+        }
+        if let Some(dump) = self.cex_script.as_ref() {
+            let path = dump.write(&self.sys, candidate, cex, origin)?;
+            self.report_dump(candidate, origin, "counterexample script", &path)
+        }
+        Ok(())
+    }
+    /// Reports a counterexample file was written, if verbose.
+    fn report_dump(&self, candidate: &str, origin: cex::Origin, what: &str, path: &Path) {
+        if self.env.verb > 1 {
+            println!(
+                "wrote {} {} for `{}` to `{}`",
+                origin.as_str(),
+                what,
+                self.bold.paint(candidate),
+                self.bold.paint(path.display().to_string()),
+            )
+        }
+    }
+
     pub fn present_base_cexs(&self, sys: &trans::Sys, res: &BaseRes) -> Res<()> {
         self.present_cexs(sys, res, true)
     }
@@ -639,7 +1722,7 @@ impl<'env> Check<'env> {
         is_base: bool,
     ) -> Res<()> {
         for (candidate, cex) in res.cexs.iter() {
-            self.present_cex(sys, *candidate, cex, is_base)?
+            self.present_cex(sys, candidate, cex, is_base)?
         }
         Ok(())
     }
@@ -650,7 +1733,6 @@ impl<'env> Check<'env> {
         cex: &check::cexs::Cex,
         is_base: bool,
     ) -> Res<()> {
-        let max_id_len = sys.decls().max_id_len();
         let def = sys.po_s().get(candidate).ok_or_else(|| {
             format!(
                 "failed to retrieve definition for candidate `{}`",
@@ -662,6 +1744,48 @@ impl<'env> Check<'env> {
             self.red.paint(candidate),
             self.bold.paint(format!("{}", def))
         );
+        // Variables of the candidate, only relevant when showing changes only.
+        let mut cand_vars = Set::new();
+        if self.cex_diff {
+            def.fold(
+                |var| {
+                    cand_vars.insert(var.id().to_string());
+                },
+                |_| (),
+                |_, _| (),
+            );
+            println!(
+                "  | ({} marks variables of the candidate, other unchanged values are hidden)",
+                self.red.paint("*")
+            );
+        }
+        match self.cex_layout {
+            cex::Layout::Blocks => self.present_cex_blocks(sys, cex, is_base, &cand_vars),
+            cex::Layout::Table => self.present_cex_table(cex, is_base, &cand_vars),
+        }
+        if !cex.unexpected.is_empty() {
+            println!("  |=| the solver produced the following unexpected values");
+            for (desc, val) in &cex.unexpected {
+                println!("  | {} = {}", self.red.paint(desc.to_string()), val);
+            }
+        }
+        println!("  |=|");
+        Ok(())
+    }
+
+    /// Presents the steps of a counterexample as one block of `var = value` lines per step.
+    ///
+    /// If `self.cex_diff`, steps after the first one only show the variables of the candidate
+    /// (`cand_vars`) and the variables whose value changed.
+    fn present_cex_blocks(
+        &self,
+        sys: &trans::Sys,
+        cex: &check::cexs::Cex,
+        is_base: bool,
+        cand_vars: &Set<String>,
+    ) {
+        let max_id_len = sys.decls().max_id_len();
+        let mut prev: Option<&std::collections::BTreeMap<_, _>> = None;
         for (step, values) in &cex.trace {
             let step_str = if is_base {
                 format!("{}", self.under.paint(step.to_string()))
@@ -673,23 +1797,126 @@ impl<'env> Check<'env> {
                 step_str
             };
             println!("  |=| Step {}", step_str);
+            let mut shown = 0;
             for (var, cst) in values {
                 let var_str = format!("{: >1$}", var.id(), max_id_len);
-                println!("  | {} = {}", self.bold.paint(var_str), cst)
+                if !self.cex_diff {
+                    println!("  | {} = {}", self.bold.paint(var_str), cst);
+                    continue;
+                }
+                let is_cand_var = cand_vars.contains(var.id());
+                let changed = prev.map(|prev| prev.get(var) != Some(cst)).unwrap_or(true);
+                if is_cand_var || changed {
+                    let mark = if is_cand_var { "*" } else { " " };
+                    println!(
+                        "  | {} {} = {}",
+                        self.red.paint(mark),
+                        self.bold.paint(var_str),
+                        cst
+                    );
+                    shown += 1
+                }
+            }
+            if self.cex_diff && shown == 0 {
+                println!("  | {}", self.gray.paint("(no change)"))
             }
+            prev = Some(values);
         }
-        if !cex.unexpected.is_empty() {
-            println!("  |=| Z3 produced the following unexpected values");
-            for (desc, val) in &cex.unexpected {
-                println!("  | {} = {}", self.red.paint(desc.to_string()), val);
+    }
+
+    /// Presents the steps of a counterexample as a table, variables as rows and steps as columns.
+    ///
+    /// Columns wrap to the terminal width, see [`term_width`] and [`column_chunks`]. Cells whose
+    /// value changed since the previous step are bold. If `self.cex_diff`, unchanged cells are left
+    /// empty except for the variables of the candidate (`cand_vars`).
+    fn present_cex_table(&self, cex: &check::cexs::Cex, is_base: bool, cand_vars: &Set<String>) {
+        let vars: Set<_> = cex
+            .trace
+            .values()
+            .flat_map(|values| values.keys())
+            .collect();
+        let label_width = vars
+            .iter()
+            .map(|var| var.id().chars().count())
+            .chain(std::iter::once("step".len()))
+            .max()
+            .unwrap_or(0);
+
+        // Step label and cells of each column, a cell is a value and whether it changed.
+        let mut prev = std::collections::BTreeMap::new();
+        let columns: Vec<(String, Vec<(String, bool)>)> = cex
+            .trace
+            .iter()
+            .map(|(step, values)| {
+                let label = match (is_base, *step) {
+                    (true, step) => step.to_string(),
+                    (false, 0) => "k".into(),
+                    (false, step) => format!("k + {}", step),
+                };
+                let cells = vars
+                    .iter()
+                    .map(|var| match values.get(var) {
+                        Some(cst) => {
+                            let val = cex::cst_to_cell(cst);
+                            let changed = prev.get(var).map(|p| p != &val).unwrap_or(false);
+                            prev.insert(*var, val.clone());
+                            (val, changed)
+                        }
+                        None => (String::new(), false),
+                    })
+                    .collect();
+                (label, cells)
+            })
+            .collect();
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|(label, cells)| {
+                cells
+                    .iter()
+                    .map(|(val, _)| val.chars().count())
+                    .chain(std::iter::once(label.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let chunks = column_chunks("  |=| ".len() + label_width, &widths, term_width());
+        for (chunk_idx, chunk) in chunks.into_iter().enumerate() {
+            if chunk_idx > 0 {
+                println!("  |")
+            }
+            let mut header = format!("  |=| {: >1$}", "step", label_width);
+            for idx in chunk.clone() {
+                let label = format!("{: >1$}", columns[idx].0, widths[idx]);
+                header = format!("{} | {}", header, self.under.paint(label));
+            }
+            println!("{}", header);
+            for (row, var) in vars.iter().enumerate() {
+                let is_cand_var = cand_vars.contains(var.id());
+                let mark = if is_cand_var { "*" } else { " " };
+                let mut line = format!(
+                    "  | {} {}",
+                    self.red.paint(mark),
+                    self.bold.paint(format!("{: >1$}", var.id(), label_width))
+                );
+                for idx in chunk.clone() {
+                    let (val, changed) = &columns[idx].1[row];
+                    let hidden = self.cex_diff && idx > 0 && !changed && !is_cand_var;
+                    let val = format!("{: >1$}", if hidden { "" } else { val }, widths[idx]);
+                    if *changed {
+                        line = format!("{} | {}", line, self.bold.paint(val))
+                    } else {
+                        line = format!("{} | {}", line, val)
+                    }
+                }
+                println!("{}", line);
             }
         }
-        println!("  |=|");
-        Ok(())
     }
 }
 
 /// Stores the output styles.
+#[derive(Clone)]
 pub struct Styles {
     /// Bold style.
     pub bold: Style,
@@ -784,3 +2011,25 @@ impl Styles {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunks() {
+        // Prefix of 10, columns of 5 plus separators: 8 each.
+        let widths = [5; 5];
+        assert_eq!(column_chunks(10, &widths, 80), vec![0..5]);
+        assert_eq!(column_chunks(10, &widths, 50), vec![0..5]);
+        assert_eq!(column_chunks(10, &widths, 49), vec![0..4, 4..5]);
+        assert_eq!(column_chunks(10, &widths, 26), vec![0..2, 2..4, 4..5]);
+        // Narrower than the prefix and a column, one column per chunk.
+        assert_eq!(
+            column_chunks(10, &widths, 5),
+            vec![0..1, 1..2, 2..3, 3..4, 4..5]
+        );
+        assert_eq!(column_chunks(10, &[3, 30, 3], 20), vec![0..1, 1..2, 2..3]);
+        assert_eq!(column_chunks(10, &[], 20), vec![0..0]);
+    }
+}
@@ -12,19 +12,70 @@ pub enum Mode {
         input: String,
         smt_log: Option<String>,
         induction: bool,
+        engine: Engine,
         bmc: bool,
         bmc_max: Option<usize>,
+        bmc_min: Option<usize>,
+        checkpoint: Option<String>,
+        resume: Option<String>,
+        cex_dump: Option<crate::cex::Dump>,
+        cex_script: Option<crate::cex::ScriptDump>,
+        cex_layout: crate::cex::Layout,
+        cex_diff: bool,
+        html: Option<String>,
+        stats: bool,
     },
     /// Script mode, run user's script.
     Script {
         input: String,
         smt_log: Option<String>,
         verb: usize,
+        debug: bool,
     },
+    /// REPL mode, runs hsmt commands interactively.
+    Repl { smt_log: Option<String> },
     /// Demo mode, generate a demo system to `target` if `check`, otherwise generates a demo script.
     Demo { check: bool, target: String },
     /// Parse mode, does nothing but parse the system.
     Parse { input: String },
+    /// Export mode, translates the `input` system to some `format` and writes it to `target`.
+    Export {
+        input: String,
+        target: String,
+        format: crate::export::Format,
+    },
+    /// Format mode, formats some systems and scripts in place, or only checks they are formatted
+    /// if `check`.
+    Fmt { inputs: Vec<String>, check: bool },
+    /// Graph mode, writes the variable dependency graph of the `input` system in DOT format.
+    Graph { input: String },
+    /// Doctor mode, diagnoses the solver setup and the terminal.
+    Doctor,
+    /// Replay mode, replays the SMT logs in `dir` and compares the answers with the recorded ones,
+    /// or records them if `record` or if there are none.
+    Replay { dir: String, record: bool },
+}
+
+/// Proof engines for check mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// Mikino's own induction engine.
+    Induction,
+    /// Z3's fixedpoint engine on the Horn clause encoding of the system.
+    Spacer,
+}
+impl Engine {
+    /// Legal string representations of engines.
+    pub const KEYS: [&'static str; 2] = ["induction", "spacer"];
+
+    /// Engine from a string.
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "induction" => Some(Self::Induction),
+            "spacer" => Some(Self::Spacer),
+            _ => None,
+        }
+    }
 }
 
 impl Mode {
@@ -33,9 +84,15 @@ impl Mode {
         vec![
             cla::check_subcommand(),
             cla::script_subcommand(),
+            cla::repl_subcommand(),
             cla::demo(),
             cla::bmc_subcommand(),
             cla::parse_subcommand(),
+            cla::export_subcommand(),
+            cla::fmt_subcommand(),
+            cla::graph_subcommand(),
+            cla::doctor_subcommand(),
+            cla::replay_subcommand(),
         ]
     }
 
@@ -44,9 +101,15 @@ impl Mode {
         let modes = [
             cla::try_check,
             cla::try_script,
+            cla::try_repl,
             cla::try_bmc,
             cla::try_demo,
             cla::try_parse,
+            cla::try_export,
+            cla::try_fmt,
+            cla::try_graph,
+            cla::try_doctor,
+            cla::try_replay,
         ];
         for try_mode in &modes {
             let maybe_res = try_mode(smt_log.clone(), matches);
@@ -65,20 +128,54 @@ pub mod cla {
     pub mod mode {
         pub const CHECK: &str = "check";
         pub const SCRIPT: &str = "script";
+        pub const REPL: &str = "repl";
         pub const DEMO: &str = "demo";
         pub const BMC: &str = "bmc";
         pub const PARSE: &str = "parse";
+        pub const EXPORT: &str = "export";
+        pub const FMT: &str = "fmt";
+        pub const GRAPH: &str = "graph";
+        pub const DOCTOR: &str = "doctor";
+        pub const REPLAY: &str = "replay";
     }
 
     mod arg {
         pub const BMC_KEY: &str = "BMC";
         pub const BMC_MAX_KEY: &str = "BMC_MAX";
+        pub const BMC_MIN_KEY: &str = "BMC_MIN";
+        pub const CHECKPOINT_KEY: &str = "CHECKPOINT";
+        pub const RESUME_KEY: &str = "RESUME";
         pub const SMT_LOG_KEY: &str = "SMT_LOG";
+        pub const SOLVER_OPT_KEY: &str = "SOLVER_OPT";
+        pub const SEED_KEY: &str = "SEED";
+        pub const MEM_LIMIT_KEY: &str = "MEM_LIMIT";
+        pub const REPLAY_SOLVER_KEY: &str = "REPLAY_SOLVER";
+        pub const PORTFOLIO_KEY: &str = "PORTFOLIO";
+        pub const REPLAY_DIR_KEY: &str = "REPLAY_DIR";
+        pub const REPLAY_RECORD_KEY: &str = "REPLAY_RECORD";
         pub const SYS_KEY: &str = "SYS_KEY";
         pub const SCRIPT_KEY: &str = "SCRIPT_KEY";
         pub const SCRIPT_VERBOSE_KEY: &str = "SCRIPT_VERBOSE";
+        pub const SCRIPT_DEBUG_KEY: &str = "SCRIPT_DEBUG";
         pub const DEMO_SCRIPT_KEY: &str = "DEMO_SCRIPT";
         pub const DEMO_TGT_KEY: &str = "DEMO_TGT";
+        pub const CEX_DIR_KEY: &str = "CEX_DIR";
+        pub const CEX_FORMAT_KEY: &str = "CEX_FORMAT";
+        pub const CEX_SCRIPT_KEY: &str = "CEX_SCRIPT";
+        pub const CEX_LAYOUT_KEY: &str = "CEX_LAYOUT";
+        pub const CEX_DIFF_KEY: &str = "CEX_DIFF";
+        pub const HTML_KEY: &str = "HTML";
+        pub const STATS_KEY: &str = "STATS";
+        pub const ENGINE_KEY: &str = "ENGINE";
+        pub const EXPORT_FORMAT_KEY: &str = "EXPORT_FORMAT";
+        pub const EXPORT_VMT_KEY: &str = "EXPORT_VMT";
+        pub const EXPORT_SMV_KEY: &str = "EXPORT_SMV";
+        pub const EXPORT_LUSTRE_KEY: &str = "EXPORT_LUSTRE";
+        pub const EXPORT_CHC_KEY: &str = "EXPORT_CHC";
+        pub const EXPORT_TGT_KEY: &str = "EXPORT_TGT";
+        pub const FMT_CHECK_KEY: &str = "FMT_CHECK";
+        pub const FMT_FILES_KEY: &str = "FMT_FILES";
+        pub const GRAPH_DOT_KEY: &str = "GRAPH_DOT";
     }
 
     fn bmc_max_arg() -> Arg {
@@ -95,11 +192,185 @@ pub mod cla {
     fn get_bmc_max(matches: &Matches, mut if_present_do: impl FnMut()) -> Option<usize> {
         matches.value_of(arg::BMC_MAX_KEY).map(|val| {
             if_present_do();
-            usize::from_str_radix(val, 10)
-                .expect(&format!("[clap] unexpected value for BMC max: `{}`", val))
+            val.parse::<usize>()
+                .unwrap_or_else(|_| panic!("[clap] unexpected value for BMC max: `{}`", val))
+        })
+    }
+
+    fn bmc_min_arg() -> Arg {
+        Arg::new(arg::BMC_MIN_KEY)
+            .help(
+                "Minimum number of transitions from the initial state(s) in BMC, \
+                falsifications with fewer transitions are not looked for",
+            )
+            .long("bmc_min")
+            .validator(validate_int)
+            .value_name("INT")
+    }
+    /// Yields the BMC min value, if any.
+    fn get_bmc_min(matches: &Matches, mut if_present_do: impl FnMut()) -> Option<usize> {
+        matches.value_of(arg::BMC_MIN_KEY).map(|val| {
+            if_present_do();
+            val.parse::<usize>()
+                .unwrap_or_else(|_| panic!("[clap] unexpected value for BMC min: `{}`", val))
         })
     }
 
+    fn checkpoint_arg() -> Arg {
+        Arg::new(arg::CHECKPOINT_KEY)
+            .help(
+                "Saves the progress of BMC to the file specified after each depth, \
+                see `--resume`",
+            )
+            .long("checkpoint")
+            .value_name("FILE")
+    }
+    /// Yields the checkpoint file, if any.
+    fn get_checkpoint(matches: &Matches, mut if_present_do: impl FnMut()) -> Option<String> {
+        matches.value_of(arg::CHECKPOINT_KEY).map(|val| {
+            if_present_do();
+            val.into()
+        })
+    }
+
+    fn resume_arg() -> Arg {
+        Arg::new(arg::RESUME_KEY)
+            .help(
+                "Resumes BMC after the depth of a checkpoint written by `--checkpoint`, \
+                keeping the counterexamples it contains",
+            )
+            .long("resume")
+            .value_name("FILE")
+    }
+    /// Yields the checkpoint to resume from, if any.
+    fn get_resume(matches: &Matches, mut if_present_do: impl FnMut()) -> Option<String> {
+        matches.value_of(arg::RESUME_KEY).map(|val| {
+            if_present_do();
+            val.into()
+        })
+    }
+
+    fn cex_dir_arg() -> Arg {
+        Arg::new(arg::CEX_DIR_KEY)
+            .help(
+                "Writes each counterexample found to its own file in the directory specified, \
+                regardless of verbosity",
+            )
+            .long("cex_dir")
+            .value_name("DIR")
+    }
+    fn cex_format_arg() -> Arg {
+        Arg::new(arg::CEX_FORMAT_KEY)
+            .help("Format of the counterexample files written by `--cex_dir`, `csv` by default")
+            .long("cex_format")
+            .possible_values(crate::cex::Format::KEYS)
+            .requires(arg::CEX_DIR_KEY)
+            .value_name("FORMAT")
+    }
+    /// Yields the counterexample dump configuration, if any.
+    fn get_cex_dump(matches: &Matches) -> Option<crate::cex::Dump> {
+        use crate::cex::{Dump, Format};
+        matches.value_of(arg::CEX_DIR_KEY).map(|dir| {
+            let format = matches
+                .value_of(arg::CEX_FORMAT_KEY)
+                .map(|fmt| {
+                    Format::of_str(fmt).unwrap_or_else(|| {
+                        panic!("[clap] unexpected value for cex format: `{}`", fmt)
+                    })
+                })
+                .unwrap_or(Format::Csv);
+            Dump::new(format, dir)
+        })
+    }
+
+    fn cex_script_arg() -> Arg {
+        Arg::new(arg::CEX_SCRIPT_KEY)
+            .help(
+                "Writes each counterexample found as an hsmt script replaying it \
+                in the directory specified, run them with `mikino script`",
+            )
+            .long("cex_script")
+            .value_name("DIR")
+    }
+    /// Yields the counterexample script dump configuration, if any.
+    fn get_cex_script(matches: &Matches) -> Option<crate::cex::ScriptDump> {
+        matches
+            .value_of(arg::CEX_SCRIPT_KEY)
+            .map(crate::cex::ScriptDump::new)
+    }
+
+    fn engine_arg() -> Arg {
+        Arg::new(arg::ENGINE_KEY)
+            .help(
+                "Proof engine, `induction` by default; `spacer` sends the Horn clause encoding \
+                of the system to Z3's fixedpoint engine",
+            )
+            .long("engine")
+            .possible_values(Engine::KEYS)
+            .value_name("ENGINE")
+    }
+    /// Yields the proof engine.
+    fn get_engine(matches: &Matches) -> Engine {
+        matches
+            .value_of(arg::ENGINE_KEY)
+            .map(|engine| {
+                Engine::of_str(engine)
+                    .unwrap_or_else(|| panic!("[clap] unexpected value for engine: `{}`", engine))
+            })
+            .unwrap_or(Engine::Induction)
+    }
+
+    fn cex_layout_arg() -> Arg {
+        Arg::new(arg::CEX_LAYOUT_KEY)
+            .help(
+                "Layout of the counterexamples shown on the terminal, `blocks` by default; \
+                `table` shows variables as rows and steps as columns",
+            )
+            .long("cex_layout")
+            .possible_values(crate::cex::Layout::KEYS)
+            .value_name("LAYOUT")
+    }
+    /// Yields the counterexample layout.
+    fn get_cex_layout(matches: &Matches) -> crate::cex::Layout {
+        use crate::cex::Layout;
+        matches
+            .value_of(arg::CEX_LAYOUT_KEY)
+            .map(|layout| {
+                Layout::of_str(layout).unwrap_or_else(|| {
+                    panic!("[clap] unexpected value for cex layout: `{}`", layout)
+                })
+            })
+            .unwrap_or(Layout::Blocks)
+    }
+
+    fn cex_diff_arg() -> Arg {
+        Arg::new(arg::CEX_DIFF_KEY)
+            .help(
+                "Counterexamples only show the values that changed after the first step, \
+                and always show (and mark) the variables of the falsified candidate",
+            )
+            .long("cex_diff")
+    }
+
+    fn html_arg() -> Arg {
+        Arg::new(arg::HTML_KEY)
+            .help("Writes a self-contained HTML report of the run to the file specified")
+            .long("html")
+            .value_name("FILE")
+    }
+    fn get_html(matches: &Matches) -> Option<String> {
+        matches.value_of(arg::HTML_KEY).map(String::from)
+    }
+
+    fn stats_arg() -> Arg {
+        Arg::new(arg::STATS_KEY)
+            .help(
+                "Prints statistics about the run: times, solver instances, calls and \
+                check-sats, solver restarts and longest counterexample (also in the HTML report)",
+            )
+            .long("stats")
+    }
+
     pub fn smt_log_arg() -> Arg {
         Arg::new(arg::SMT_LOG_KEY)
             .help("Activates SMT logging in the directory specified")
@@ -111,9 +382,107 @@ pub mod cla {
         matches.value_of(arg::SMT_LOG_KEY).map(String::from)
     }
 
+    pub fn solver_opt_arg() -> Arg {
+        Arg::new(arg::SOLVER_OPT_KEY)
+            .help(
+                "Passes an option to every solver session, \
+                *e.g.* `model.completion=true` for Z3 (can be repeated)",
+            )
+            .long("solver_opt")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .validator(validate_key_value)
+            .value_name("KEY=VALUE")
+    }
+    pub fn get_solver_opts(matches: &Matches) -> Vec<(String, String)> {
+        matches
+            .values_of(arg::SOLVER_OPT_KEY)
+            .into_iter()
+            .flatten()
+            .map(|opt| {
+                let (key, value) = opt
+                    .split_once('=')
+                    .unwrap_or_else(|| panic!("[clap] unexpected solver option `{}`", opt));
+                (key.into(), value.into())
+            })
+            .collect()
+    }
+
+    pub fn seed_arg() -> Arg {
+        Arg::new(arg::SEED_KEY)
+            .help("Random seed of every solver session, for reproducible runs")
+            .long("seed")
+            .validator(validate_int)
+            .value_name("INT")
+    }
+    pub fn get_seed(matches: &Matches) -> Option<u64> {
+        matches.value_of(arg::SEED_KEY).map(|val| {
+            val.parse::<u64>()
+                .unwrap_or_else(|_| panic!("[clap] unexpected value for seed: `{}`", val))
+        })
+    }
+
+    pub fn mem_limit_arg() -> Arg {
+        Arg::new(arg::MEM_LIMIT_KEY)
+            .help(
+                "Limits the memory (address space) of every solver process, in megabytes; \
+                solvers exceeding it fail (unix only)",
+            )
+            .long("mem_limit")
+            .validator(validate_int)
+            .value_name("MB")
+    }
+    pub fn get_mem_limit(matches: &Matches) -> Option<u64> {
+        matches.value_of(arg::MEM_LIMIT_KEY).map(|val| {
+            val.parse::<u64>()
+                .unwrap_or_else(|_| panic!("[clap] unexpected value for memory limit: `{}`", val))
+        })
+    }
+
+    pub fn replay_solver_arg() -> Arg {
+        Arg::new(arg::REPLAY_SOLVER_KEY)
+            .help(
+                "Answers solver queries with the answers recorded by `mikino replay` \
+                in the directory specified, instead of running a solver",
+            )
+            .long("replay_solver")
+            .validator(validate_replay_dir)
+            .value_name("DIR")
+    }
+    pub fn get_replay_solver(matches: &Matches) -> Option<String> {
+        matches.value_of(arg::REPLAY_SOLVER_KEY).map(String::from)
+    }
+
+    pub fn portfolio_arg() -> Arg {
+        Arg::new(arg::PORTFOLIO_KEY)
+            .help(
+                "Races several solvers on each induction and BMC query, the first definitive \
+                answer wins: comma-separated list of `SOLVER[:KEY=VALUE]*`, \
+                *e.g.* `z3,cvc5` or `z3,z3:smt.arith.solver=2`",
+            )
+            .long("portfolio")
+            .takes_value(true)
+            .validator(validate_portfolio)
+            .conflicts_with(arg::REPLAY_SOLVER_KEY)
+            .value_name("SOLVERS")
+    }
+    pub fn get_portfolio(matches: &Matches) -> Vec<crate::portfolio::Spec> {
+        matches
+            .value_of(arg::PORTFOLIO_KEY)
+            .map(|spec| {
+                crate::portfolio::parse_spec(spec)
+                    .unwrap_or_else(|_| panic!("[clap] unexpected portfolio `{}`", spec))
+            })
+            .unwrap_or_default()
+    }
+
     fn sys_arg() -> Arg {
         Arg::new(arg::SYS_KEY)
-            .help("Transition system to analyze (run `mikino demo -h` mode for details)")
+            .help(
+                "Transition system to analyze (run `mikino demo -h` mode for details), \
+                VMT-LIB if the file has extension `.vmt`, \
+                AIGER if it has extension `.aag` (ASCII) or `.aig` (binary)",
+            )
             .required(true)
             .value_name("FILE")
     }
@@ -149,7 +518,18 @@ pub mod cla {
                     )
                     .long("bmc"),
                 bmc_max_arg(),
+                bmc_min_arg(),
+                checkpoint_arg(),
+                resume_arg(),
+                engine_arg(),
                 smt_log_arg(),
+                cex_dir_arg(),
+                cex_format_arg(),
+                cex_script_arg(),
+                cex_layout_arg(),
+                cex_diff_arg(),
+                html_arg(),
+                stats_arg(),
                 sys_arg(),
             ])
     }
@@ -161,13 +541,33 @@ pub mod cla {
 
         let mut bmc = matches.is_present(arg::BMC_KEY);
         let bmc_max = get_bmc_max(matches, || bmc = true);
+        let bmc_min = get_bmc_min(matches, || bmc = true);
+        let checkpoint = get_checkpoint(matches, || bmc = true);
+        let resume = get_resume(matches, || bmc = true);
+        let engine = get_engine(matches);
+        let cex_dump = get_cex_dump(matches);
+        let cex_script = get_cex_script(matches);
+        let cex_layout = get_cex_layout(matches);
+        let cex_diff = matches.is_present(arg::CEX_DIFF_KEY);
+        let html = get_html(matches);
+        let stats = matches.is_present(arg::STATS_KEY);
 
         Some(Mode::Check {
             input,
             smt_log,
             induction: true,
+            engine,
             bmc,
             bmc_max,
+            bmc_min,
+            checkpoint,
+            resume,
+            cex_dump,
+            cex_script,
+            cex_layout,
+            cex_diff,
+            html,
+            stats,
         })
     }
 
@@ -183,6 +583,10 @@ pub mod cla {
                     .long("verbose")
                     .multiple_occurrences(true)
                     .help("increases script output verbosity"),
+                Arg::new(arg::SCRIPT_DEBUG_KEY).long("debug").help(
+                    "Runs the script in a debugger reading commands on the standard input, \
+                    with breakpoints, stepping and inspection of meta-variables and models",
+                ),
             ])
     }
     pub fn try_script(smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
@@ -191,14 +595,31 @@ pub mod cla {
         let input = get_script(matches);
         let smt_log = get_smt_log(matches).or(smt_log);
         let verb = matches.occurrences_of(arg::SCRIPT_VERBOSE_KEY) as usize;
+        let debug = matches.is_present(arg::SCRIPT_DEBUG_KEY);
 
         Some(Mode::Script {
             input,
             smt_log,
             verb,
+            debug,
         })
     }
 
+    /// Subcommand for the REPL mode.
+    pub fn repl_subcommand() -> App {
+        Command::new(mode::REPL)
+            .about(
+                "Runs hsmt script commands interactively, \
+                use `:help` in the REPL for the list of REPL commands",
+            )
+            .args(&[smt_log_arg()])
+    }
+    pub fn try_repl(smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
+        let matches = matches.subcommand_matches(mode::REPL)?;
+        let smt_log = get_smt_log(matches).or(smt_log);
+        Some(Mode::Repl { smt_log })
+    }
+
     /// Subcommand for the demo mode.
     pub fn demo() -> App {
         Command::new(mode::DEMO)
@@ -236,21 +657,55 @@ pub mod cla {
                 "Runs BMC (Bounded Model Checking) without induction. \
             Mikino will search for a falsification for each proof objective.",
             )
-            .args(&[bmc_max_arg(), smt_log_arg(), sys_arg()])
+            .args(&[
+                bmc_max_arg(),
+                bmc_min_arg(),
+                checkpoint_arg(),
+                resume_arg(),
+                smt_log_arg(),
+                cex_dir_arg(),
+                cex_format_arg(),
+                cex_script_arg(),
+                cex_layout_arg(),
+                cex_diff_arg(),
+                html_arg(),
+                stats_arg(),
+                sys_arg(),
+            ])
     }
     pub fn try_bmc(smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
         let matches = matches.subcommand_matches(mode::BMC)?;
         let bmc_max = get_bmc_max(matches, || ());
+        let bmc_min = get_bmc_min(matches, || ());
+        let checkpoint = get_checkpoint(matches, || ());
+        let resume = get_resume(matches, || ());
         let smt_log = get_smt_log(matches).or(smt_log);
         let input = get_sys(matches);
+        let cex_dump = get_cex_dump(matches);
+        let cex_script = get_cex_script(matches);
+        let cex_layout = get_cex_layout(matches);
+        let cex_diff = matches.is_present(arg::CEX_DIFF_KEY);
+        let html = get_html(matches);
+        let stats = matches.is_present(arg::STATS_KEY);
         let induction = false;
+        let engine = Engine::Induction;
         let bmc = true;
         Some(Mode::Check {
             input,
             bmc,
             bmc_max,
+            bmc_min,
+            checkpoint,
+            resume,
             induction,
+            engine,
             smt_log,
+            cex_dump,
+            cex_script,
+            cex_layout,
+            cex_diff,
+            html,
+            stats,
         })
     }
 
@@ -266,6 +721,193 @@ pub mod cla {
         Some(Mode::Parse { input })
     }
 
+    /// Subcommand for export mode.
+    pub fn export_subcommand() -> App {
+        Command::new(mode::EXPORT)
+            .about("Translates the input system to the input format of other model-checkers")
+            .args(&[
+                Arg::new(arg::EXPORT_VMT_KEY)
+                    .help("Exports to VMT-LIB (SMT-LIB 2 with transition system annotations)")
+                    .long("vmt"),
+                Arg::new(arg::EXPORT_SMV_KEY)
+                    .help("Exports to nuXmv's SMV dialect")
+                    .long("smv"),
+                Arg::new(arg::EXPORT_LUSTRE_KEY)
+                    .help("Exports to Lustre, as supported by Kind 2")
+                    .long("lustre"),
+                Arg::new(arg::EXPORT_CHC_KEY)
+                    .help("Exports to constrained Horn clauses (SMT-LIB 2 `HORN` logic)")
+                    .long("chc"),
+                sys_arg(),
+                Arg::new(arg::EXPORT_TGT_KEY)
+                    .help("Path of the file to write the translation to")
+                    .required(true)
+                    .value_name("OUT"),
+            ])
+            .group(
+                clap::ArgGroup::new(arg::EXPORT_FORMAT_KEY)
+                    .args(&[
+                        arg::EXPORT_VMT_KEY,
+                        arg::EXPORT_SMV_KEY,
+                        arg::EXPORT_LUSTRE_KEY,
+                        arg::EXPORT_CHC_KEY,
+                    ])
+                    .required(true),
+            )
+    }
+    pub fn try_export(_smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
+        use crate::export::Format;
+        let matches = matches.subcommand_matches(mode::EXPORT)?;
+        let input = get_sys(matches);
+        let target = matches
+            .value_of(arg::EXPORT_TGT_KEY)
+            .expect("[clap] required argument cannot be absent")
+            .into();
+        let format = if matches.is_present(arg::EXPORT_VMT_KEY) {
+            Format::Vmt
+        } else if matches.is_present(arg::EXPORT_SMV_KEY) {
+            Format::Smv
+        } else if matches.is_present(arg::EXPORT_LUSTRE_KEY) {
+            Format::Lustre
+        } else if matches.is_present(arg::EXPORT_CHC_KEY) {
+            Format::Chc
+        } else {
+            panic!("[clap] required export format cannot be absent")
+        };
+        Some(Mode::Export {
+            input,
+            target,
+            format,
+        })
+    }
+
+    /// Subcommand for format mode.
+    pub fn fmt_subcommand() -> App {
+        Command::new(mode::FMT)
+            .about("Formats hsmt systems and scripts in place, keeping comments")
+            .args(&[
+                Arg::new(arg::FMT_CHECK_KEY)
+                    .help(
+                        "Does not write anything, only checks the files are formatted \
+                        (fails if some are not)",
+                    )
+                    .long("check"),
+                Arg::new(arg::FMT_FILES_KEY)
+                    .help("Hsmt systems and scripts to format")
+                    .required(true)
+                    .multiple_values(true)
+                    .value_name("FILES"),
+            ])
+    }
+    pub fn try_fmt(_smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
+        let matches = matches.subcommand_matches(mode::FMT)?;
+        let inputs = matches
+            .values_of(arg::FMT_FILES_KEY)
+            .expect("[clap] required argument cannot be absent")
+            .map(String::from)
+            .collect();
+        let check = matches.is_present(arg::FMT_CHECK_KEY);
+        Some(Mode::Fmt { inputs, check })
+    }
+
+    /// Subcommand for graph mode.
+    pub fn graph_subcommand() -> App {
+        Command::new(mode::GRAPH)
+            .about(
+                "Prints the variable dependency graph of the input system: \
+                `x -> y` if the next value of `y` depends on the current value of `x`, \
+                candidates are linked to the variables they read",
+            )
+            .args(&[
+                Arg::new(arg::GRAPH_DOT_KEY)
+                    .help("Prints the graph in Graphviz's DOT format")
+                    .long("dot")
+                    .required(true),
+                sys_arg(),
+            ])
+    }
+    pub fn try_graph(_smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
+        let matches = matches.subcommand_matches(mode::GRAPH)?;
+        let input = get_sys(matches);
+        Some(Mode::Graph { input })
+    }
+
+    /// Subcommand for doctor mode.
+    pub fn doctor_subcommand() -> App {
+        Command::new(mode::DOCTOR).about(
+            "Diagnoses the environment: checks that the SMT solver can be found and run, \
+            that its version is supported, that it answers a tiny sat/unsat test, \
+            and whether the terminal supports colors",
+        )
+    }
+    pub fn try_doctor(_smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
+        matches.subcommand_matches(mode::DOCTOR)?;
+        Some(Mode::Doctor)
+    }
+
+    /// Subcommand for replay mode.
+    pub fn replay_subcommand() -> App {
+        Command::new(mode::REPLAY)
+            .about(
+                "Replays the SMT logs of a directory produced by `--smt_log` on the solver. \
+                The first replay records the answers of the solver next to each log, \
+                later replays report the answers that differ and the timing changes",
+            )
+            .args(&[
+                Arg::new(arg::REPLAY_RECORD_KEY)
+                    .help("Records the answers of the solver even if some were already recorded")
+                    .long("record"),
+                Arg::new(arg::REPLAY_DIR_KEY)
+                    .help("Directory containing the SMT logs")
+                    .required(true)
+                    .value_name("DIR"),
+            ])
+    }
+    pub fn try_replay(_smt_log: Option<String>, matches: &Matches) -> Option<Mode> {
+        let matches = matches.subcommand_matches(mode::REPLAY)?;
+        let dir = matches
+            .value_of(arg::REPLAY_DIR_KEY)
+            .expect("[clap] required directory argument cannot be absent")
+            .into();
+        let record = matches.is_present(arg::REPLAY_RECORD_KEY);
+        Some(Mode::Replay { dir, record })
+    }
+
+    /// Returns an error if the input string is not of the form `KEY=VALUE`, with a non-empty `KEY`.
+    ///
+    /// Used by CLAP.
+    pub fn validate_key_value(s: &str) -> Result<(), String> {
+        match s.split_once('=') {
+            Some((key, _)) if !key.trim().is_empty() && !key.contains(char::is_whitespace) => {
+                Ok(())
+            }
+            _ => Err(format!("expected `KEY=VALUE`, found `{}`", s)),
+        }
+    }
+
+    /// Returns an error if the input string is not a valid portfolio specification.
+    ///
+    /// Used by CLAP.
+    pub fn validate_portfolio(s: &str) -> Result<(), String> {
+        crate::portfolio::parse_spec(s)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Returns an error if the input string cannot be passed to mikino standing in for a solver.
+    ///
+    /// Used by CLAP.
+    pub fn validate_replay_dir(s: &str) -> Result<(), String> {
+        if s.contains(char::is_whitespace) {
+            Err(format!(
+                "replay directory `{}` contains whitespace, which solver commands cannot",
+                s
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Returns an error if the input string is not a valid integer.
     ///
     /// Used by CLAP.
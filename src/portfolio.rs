@@ -0,0 +1,776 @@
+//! Solver portfolio.
+//!
+//! With `--portfolio`, induction and BMC sessions run several solvers side by side. All solvers
+//! get the same declarations and assertions, and each check-sat goes to all of them at once. The
+//! first definitive answer wins, solvers still working on the query are killed. Killed solvers are
+//! spawned again before the next check-sat, and brought up to date with the commands of the scopes
+//! still open.
+//!
+//! Solvers are driven directly through their standard input and output with `:print-success`,
+//! each of them has a thread forwarding the lines it outputs.
+
+mikino_api::prelude!();
+
+use std::{
+    collections::{BTreeMap as Map, VecDeque},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
+use check::cexs::Cex;
+use expr::{Cst, PExpr, Var};
+
+use crate::{
+    import::{sexp, vmt},
+    logic::Logic,
+    solver::Backend,
+    stats::Counters,
+};
+
+/// A solver of a portfolio, as specified by the user.
+#[derive(Debug, Clone)]
+pub struct Member {
+    /// Name of the member, its specification on the command line.
+    pub name: String,
+    /// Solver backend.
+    pub backend: Backend,
+    /// Command running the solver.
+    pub cmd: String,
+    /// Options passed to the solver.
+    pub opts: Vec<(String, String)>,
+    /// Random seed.
+    pub seed: Option<u64>,
+    /// Memory limit in megabytes, if any.
+    pub mem_limit: Option<u64>,
+}
+impl Member {
+    /// Configuration of the solver for problems in some logic.
+    pub fn conf(&self, logic: Logic) -> Res<SmtConf> {
+        let mut conf = self
+            .backend
+            .conf(&self.cmd, Some(logic))
+            .chain_err(|| format!("while configuring portfolio solver `{}`", self.name))?;
+        self.backend
+            .add_options(&mut conf, &self.opts, self.seed)
+            .chain_err(|| format!("while passing options to portfolio solver `{}`", self.name))?;
+        crate::solver::wrap(&mut conf, self.mem_limit)?;
+        Ok(conf)
+    }
+}
+
+/// A solver of a portfolio specification: name, backend and options.
+pub type Spec = (String, Backend, Vec<(String, String)>);
+
+/// Parses a portfolio specification, a comma-separated list of `SOLVER[:KEY=VALUE]*`.
+pub fn parse_spec(spec: &str) -> Res<Vec<Spec>> {
+    let mut res = vec![];
+    for member in spec.split(',').map(str::trim) {
+        let mut parts = member.split(':');
+        let solver = parts.next().unwrap_or("");
+        let backend = Backend::of_str(solver).ok_or_else(|| {
+            format!(
+                "unknown solver `{}` in portfolio, expected one of {}",
+                solver,
+                Backend::KEYS.join(", ")
+            )
+        })?;
+        let mut opts = vec![];
+        for opt in parts {
+            let mut key_val = opt.splitn(2, '=');
+            match (key_val.next(), key_val.next()) {
+                (Some(key), Some(val)) if !key.is_empty() => opts.push((key.into(), val.into())),
+                _ => bail!(
+                    "illegal option `{}` for portfolio solver `{}`, expected `KEY=VALUE`",
+                    opt,
+                    member
+                ),
+            }
+        }
+        let mut name = member.to_string();
+        let mut cnt = 2;
+        while res.iter().any(|(other, _, _)| *other == name) {
+            name = format!("{}#{}", member, cnt);
+            cnt += 1;
+        }
+        res.push((name, backend, opts))
+    }
+    if res.len() < 2 {
+        bail!("a portfolio needs at least two solvers")
+    }
+    Ok(res)
+}
+
+/// Solver that won a query.
+#[derive(Debug, Clone)]
+pub struct Win {
+    /// Engine the query comes from.
+    pub engine: &'static str,
+    /// Description of the query.
+    pub query: String,
+    /// Name of the solver that answered first.
+    pub winner: String,
+    /// Time the winner took to answer.
+    pub time: Duration,
+}
+
+/// Number of queries each solver won and total time they took, by solver name.
+pub fn tally(wins: &[Win]) -> Map<&str, (usize, Duration)> {
+    let mut res: Map<&str, (usize, Duration)> = Map::new();
+    for win in wins {
+        let (count, time) = res.entry(win.winner.as_str()).or_default();
+        *count += 1;
+        *time += win.time;
+    }
+    res
+}
+
+/// A line output by a solver, `None` when the solver exits.
+type Line = (usize, usize, Option<String>);
+
+/// A running solver of the portfolio.
+struct Proc {
+    /// Solver process.
+    kid: Child,
+    /// Solver's standard input.
+    stdin: ChildStdin,
+}
+
+/// A solver of the portfolio.
+struct Solver {
+    /// Name of the solver.
+    name: String,
+    /// Configuration.
+    conf: SmtConf,
+    /// Logic to set with `set-logic`, if any.
+    logic: Option<Logic>,
+    /// Running process, `None` if killed.
+    proc: Option<Proc>,
+    /// Incremented each time the solver is spawned, lines of killed processes are ignored.
+    generation: usize,
+    /// Lines received but not consumed yet.
+    pending: VecDeque<String>,
+}
+
+/// Several solvers racing on each check-sat.
+pub struct Portfolio {
+    /// Solvers.
+    solvers: Vec<Solver>,
+    /// Receives the lines of all the solvers.
+    lines: Receiver<Line>,
+    /// Given to the threads reading the solvers' output.
+    sender: Sender<Line>,
+    /// Commands of the open scopes, to bring respawned solvers up to date.
+    scopes: Vec<Vec<String>>,
+    /// SMT log.
+    tee: Option<BufWriter<File>>,
+    /// Solver that answered the last check-sat.
+    winner: Option<usize>,
+    /// Solver activity, the portfolio counts the solvers it spawns and the commands it sends.
+    counters: Rc<Counters>,
+}
+impl Drop for Portfolio {
+    fn drop(&mut self) {
+        for idx in 0..self.solvers.len() {
+            self.kill(idx)
+        }
+    }
+}
+impl Portfolio {
+    /// Constructor, spawns all the solvers.
+    ///
+    /// Solvers are given as their name, configuration, and the logic to set with `set-logic` if
+    /// any. The commands common to all the solvers are logged to `tee`, if any.
+    pub fn new(
+        solvers: Vec<(String, SmtConf, Option<Logic>)>,
+        tee: Option<PathBuf>,
+        counters: Rc<Counters>,
+    ) -> Res<Self> {
+        let tee = match tee {
+            Some(path) => {
+                let file = File::create(&path)
+                    .chain_err(|| format!("while opening SMT log `{}`", path.display()))?;
+                let mut tee = BufWriter::new(file);
+                let names: Vec<_> = solvers.iter().map(|(name, _, _)| name.as_str()).collect();
+                writeln!(tee, "; Portfolio: {}\n", names.join(", "))?;
+                Some(tee)
+            }
+            None => None,
+        };
+        let (sender, lines) = mpsc::channel();
+        let mut res = Self {
+            solvers: solvers
+                .into_iter()
+                .map(|(name, conf, logic)| Solver {
+                    name,
+                    conf,
+                    logic,
+                    proc: None,
+                    generation: 0,
+                    pending: VecDeque::new(),
+                })
+                .collect(),
+            lines,
+            sender,
+            scopes: vec![vec![]],
+            tee,
+            winner: None,
+            counters,
+        };
+        for idx in 0..res.solvers.len() {
+            res.spawn(idx)?
+        }
+        Ok(res)
+    }
+
+    /// Spawns a solver and brings it up to date with the commands of the open scopes.
+    fn spawn(&mut self, idx: usize) -> Res<()> {
+        let solver = &mut self.solvers[idx];
+        let mut kid = Command::new(solver.conf.get_cmd())
+            .args(solver.conf.get_options())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .chain_err(|| {
+                format!(
+                    "while spawning portfolio solver `{}` with command `{}`",
+                    solver.name,
+                    solver.conf.get_cmd()
+                )
+            })?;
+        let stdin = kid.stdin.take().ok_or("could not access solver's stdin")?;
+        let stdout = kid
+            .stdout
+            .take()
+            .ok_or("could not access solver's stdout")?;
+        crate::interrupt::solver_spawned(kid.id());
+        self.counters.spawned(solver.generation > 0);
+        solver.generation += 1;
+        solver.pending.clear();
+        solver.proc = Some(Proc { kid, stdin });
+
+        let (sender, generation) = (self.sender.clone(), solver.generation);
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send((idx, generation, Some(line))).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+            let _ = sender.send((idx, generation, None));
+        });
+
+        let mut preamble = vec![
+            "(set-option :print-success true)".to_string(),
+            "(set-option :produce-models true)".to_string(),
+        ];
+        if let Some(logic) = self.solvers[idx].logic {
+            preamble.push(format!("(set-logic {})", logic.smt_str()))
+        }
+        for (scope_idx, scope) in self.scopes.iter().enumerate() {
+            if scope_idx > 0 {
+                preamble.push("(push 1)".into())
+            }
+            preamble.extend(scope.iter().cloned())
+        }
+        for command in preamble {
+            self.send(idx, &command)?;
+            self.expect_success(idx, &command)?
+        }
+        Ok(())
+    }
+
+    /// Kills a solver.
+    fn kill(&mut self, idx: usize) {
+        if let Some(mut proc) = self.solvers[idx].proc.take() {
+            let _ = writeln!(proc.stdin, "(exit)");
+            let _ = proc.stdin.flush();
+            let _ = proc.kid.kill();
+            let _ = proc.kid.wait();
+        }
+    }
+
+    /// Sends a command to a solver.
+    fn send(&mut self, idx: usize, command: &str) -> Res<()> {
+        let solver = &mut self.solvers[idx];
+        let name = &solver.name;
+        let proc = solver
+            .proc
+            .as_mut()
+            .ok_or_else(|| format!("portfolio solver `{}` is not running", name))?;
+        writeln!(proc.stdin, "{}", command)
+            .and_then(|()| proc.stdin.flush())
+            .chain_err(|| format!("while writing to portfolio solver `{}`", name))?;
+        self.counters.calls(1);
+        Ok(())
+    }
+
+    /// Next line output by any solver.
+    fn recv(&self) -> Res<Line> {
+        // The portfolio holds a sender, the channel cannot be closed.
+        self.lines
+            .recv()
+            .map_err(|_| "[fatal] portfolio solvers' output is closed".into())
+    }
+
+    /// Next line output by a solver, `None` if the solver exited.
+    fn read_line(&mut self, idx: usize) -> Res<Option<String>> {
+        if let Some(line) = self.solvers[idx].pending.pop_front() {
+            return Ok(Some(line));
+        }
+        loop {
+            let (src, generation, line) = self.recv()?;
+            let solver = &mut self.solvers[src];
+            if generation != solver.generation {
+                continue;
+            }
+            match line {
+                None if src == idx => return Ok(None),
+                Some(line) if src == idx => return Ok(Some(line)),
+                Some(line) => solver.pending.push_back(line),
+                None => (),
+            }
+        }
+    }
+
+    /// Reads the `success` answer of a solver to a command.
+    fn expect_success(&mut self, idx: usize, command: &str) -> Res<()> {
+        let answer = self.read_line(idx)?;
+        match answer.as_ref().map(|line| line.trim()) {
+            Some("success") => Ok(()),
+            answer => bail!(
+                "portfolio solver `{}` answered `{}` to `{}`",
+                self.solvers[idx].name,
+                answer.unwrap_or("<exited>"),
+                command
+            ),
+        }
+    }
+
+    /// Indices of the running solvers.
+    fn running(&self) -> Vec<usize> {
+        (0..self.solvers.len())
+            .filter(|idx| self.solvers[*idx].proc.is_some())
+            .collect()
+    }
+
+    /// Logs a command.
+    fn log(&mut self, command: &str) -> Res<()> {
+        if let Some(tee) = self.tee.as_mut() {
+            writeln!(tee, "{}", command)?;
+            tee.flush()?
+        }
+        Ok(())
+    }
+
+    /// Sends a command to all the running solvers.
+    pub fn command(&mut self, command: impl Into<String>) -> Res<()> {
+        let command = command.into();
+        self.log(&command)?;
+        let running = self.running();
+        for idx in &running {
+            self.send(*idx, &command)?
+        }
+        for idx in running {
+            self.expect_success(idx, &command)?
+        }
+        Ok(())
+    }
+
+    /// Pushes a scope.
+    pub fn push(&mut self) -> Res<()> {
+        self.command("(push 1)")?;
+        self.scopes.push(vec![]);
+        Ok(())
+    }
+    /// Pops a scope.
+    pub fn pop(&mut self) -> Res<()> {
+        if self.scopes.len() < 2 {
+            bail!("[fatal] trying to pop the top-level scope of a portfolio")
+        }
+        self.command("(pop 1)")?;
+        self.scopes.pop();
+        Ok(())
+    }
+    /// Sends a command that is not a push or a pop.
+    pub fn declare_or_assert(&mut self, command: String) -> Res<()> {
+        self.command(command.clone())?;
+        self.scopes
+            .last_mut()
+            .expect("[unreachable] portfolio with no scope")
+            .push(command);
+        Ok(())
+    }
+
+    /// Checks the current assertions on all the solvers.
+    ///
+    /// Returns the result of the first solver answering `sat` or `unsat`, the name of that solver
+    /// and the time it took. Solvers still working are killed.
+    pub fn check_sat(&mut self) -> Res<(bool, String, Duration)> {
+        for idx in 0..self.solvers.len() {
+            if self.solvers[idx].proc.is_none() {
+                self.spawn(idx)?
+            }
+        }
+        const COMMAND: &str = "(check-sat)";
+        self.log(COMMAND)?;
+        self.winner = None;
+        let start = Instant::now();
+        let mut racing = self.running();
+        for idx in &racing {
+            self.send(*idx, COMMAND)?
+        }
+
+        let mut failures = vec![];
+        let mut winner = None;
+        while winner.is_none() && !racing.is_empty() {
+            // No pending lines here, solvers answered all previous commands.
+            let (src, generation, line) = self.recv()?;
+            if generation != self.solvers[src].generation || !racing.contains(&src) {
+                continue;
+            }
+            let answer = line.as_ref().map(|line| line.trim());
+            match answer {
+                Some("sat") => winner = Some((true, src)),
+                Some("unsat") => winner = Some((false, src)),
+                // Lines that are not answers, warnings for instance.
+                Some(line) if line != "unknown" && !line.starts_with("(error") => continue,
+                _ => {
+                    failures.push(format!(
+                        "`{}` answered `{}`",
+                        self.solvers[src].name,
+                        answer.unwrap_or("<exited>")
+                    ));
+                    racing.retain(|idx| *idx != src);
+                    // Exited solvers are spawned again at the next check-sat.
+                    if line.is_none() {
+                        self.kill(src)
+                    }
+                }
+            }
+        }
+        let time = start.elapsed();
+
+        let (sat, winner) = match winner {
+            Some(winner) => winner,
+            None => bail!(
+                "no solver of the portfolio could decide the query: {}",
+                failures.join(", ")
+            ),
+        };
+        for idx in racing {
+            if idx != winner {
+                self.kill(idx)
+            }
+        }
+        self.winner = Some(winner);
+        Ok((sat, self.solvers[winner].name.clone(), time))
+    }
+
+    /// Retrieves a counterexample from the model of the winner of the last check-sat, ignoring
+    /// steps after `max_step`.
+    ///
+    /// The last check-sat must have been `sat`.
+    pub fn cex(&mut self, max_step: Unroll) -> Res<Cex> {
+        let idx = self
+            .winner
+            .ok_or("[fatal] asking for a model before any check-sat")?;
+        const COMMAND: &str = "(get-model)";
+        self.log(COMMAND)?;
+        self.send(idx, COMMAND)?;
+        let mut txt = String::new();
+        let mut depth = 0i64;
+        loop {
+            let line = self.read_line(idx)?.ok_or_else(|| {
+                format!(
+                    "portfolio solver `{}` exited while producing a model",
+                    self.solvers[idx].name
+                )
+            })?;
+            depth += count_parens(&line);
+            txt.push_str(&line);
+            txt.push('\n');
+            if depth <= 0 && !txt.trim().is_empty() {
+                break;
+            }
+        }
+        model_to_cex(&txt, max_step).chain_err(|| {
+            format!(
+                "while retrieving the model of portfolio solver `{}`",
+                self.solvers[idx].name
+            )
+        })
+    }
+}
+
+/// Number of opening parentheses minus the number of closing parentheses, outside of strings and
+/// quoted symbols.
+fn count_parens(line: &str) -> i64 {
+    let mut res = 0;
+    let mut delim = None;
+    for c in line.chars() {
+        match (delim, c) {
+            (None, '"') | (None, '|') => delim = Some(c),
+            (Some(d), c) if d == c => delim = None,
+            (None, '(') => res += 1,
+            (None, ')') => res -= 1,
+            _ => (),
+        }
+    }
+    res
+}
+
+/// Turns a model into a counterexample.
+fn model_to_cex(txt: &str, max_step: Unroll) -> Res<Cex> {
+    let sexps = sexp::parse(txt)?;
+    let model = match sexps.as_slice() {
+        [model] => model.list("a model")?,
+        _ => bail!("expected a model, found `{}`", txt.trim()),
+    };
+    // Older solvers start models with a `model` keyword.
+    let model = match model.first().and_then(sexp::Sexp::as_atom) {
+        Some("model") => &model[1..],
+        _ => model,
+    };
+
+    let mut cex = Cex::new();
+    for def in model {
+        let (id, args, typ, val) = match def.list("a definition")? {
+            [kw, id, args, typ, val] if kw.as_atom() == Some("define-fun") => {
+                (id.atom("a symbol")?, args.list("arguments")?, typ, val)
+            }
+            _ => bail!(
+                "line {}: expected a definition, found `{}`",
+                def.line(),
+                def
+            ),
+        };
+        let typ = match typ.as_atom() {
+            Some("Bool") => Typ::Bool,
+            Some("Int") => Typ::Int,
+            Some("Real") => Typ::Rat,
+            _ => {
+                cex.insert_unexpected(format!("{} {}", id, typ), val.to_string())?;
+                continue;
+            }
+        };
+        let step = id
+            .rsplit_once('@')
+            .and_then(|(id, step)| step.parse::<Unroll>().ok().map(|step| (id, step)));
+        let cst = match vmt::term(val, &Map::new()) {
+            Ok(PExpr::Cst(Cst::I(i))) if typ == Typ::Rat => Some(Cst::R(Rat::from_integer(i))),
+            Ok(PExpr::Cst(cst)) => Some(cst),
+            _ => None,
+        };
+        match (step, cst) {
+            (Some((_, step)), _) if step > max_step => (),
+            (Some((var, step)), Some(cst)) if args.is_empty() => {
+                cex.insert(step, Var::new(var, typ), cst)?
+            }
+            _ => cex.insert_unexpected(format!("{} {}", id, typ), val.to_string())?,
+        }
+    }
+    Ok(cex)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::Path;
+
+    #[test]
+    fn specs() {
+        let spec = parse_spec("z3, cvc5:tlimit-per=100,z3:smt.arith.solver=2:seed=7,z3").unwrap();
+        let names: Vec<_> = spec.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "z3",
+                "cvc5:tlimit-per=100",
+                "z3:smt.arith.solver=2:seed=7",
+                "z3#2"
+            ]
+        );
+        let backends: Vec<_> = spec.iter().map(|(_, backend, _)| *backend).collect();
+        assert_eq!(
+            backends,
+            vec![Backend::Z3, Backend::Cvc5, Backend::Z3, Backend::Z3]
+        );
+        assert_eq!(spec[1].2, vec![("tlimit-per".into(), "100".into())]);
+        assert_eq!(
+            spec[2].2,
+            vec![
+                ("smt.arith.solver".into(), "2".into()),
+                ("seed".into(), "7".into())
+            ]
+        );
+        assert!(spec[3].2.is_empty());
+        // Values can contain `=`.
+        let spec = parse_spec("yices2:a=b=c,mathsat").unwrap();
+        assert_eq!(spec[0].2, vec![("a".into(), "b=c".into())]);
+
+        let error = |spec| parse_spec(spec).unwrap_err().to_string();
+        assert_eq!(error("z3"), "a portfolio needs at least two solvers");
+        assert!(error("z3,cvc4").starts_with("unknown solver `cvc4` in portfolio"));
+        assert_eq!(
+            error("z3,cvc5:tlimit"),
+            "illegal option `tlimit` for portfolio solver `cvc5:tlimit`, expected `KEY=VALUE`"
+        );
+        assert_eq!(
+            error("z3:=1,cvc5"),
+            "illegal option `=1` for portfolio solver `z3:=1`, expected `KEY=VALUE`"
+        );
+    }
+
+    /// Fake solver answering `success` to commands and running `check_sat` on check-sats.
+    ///
+    /// Commands start with a line starting with `(`, the other lines continue the current command.
+    #[cfg(unix)]
+    fn fake_solver(dir: &Path, name: &str, check_sat: &str) -> (String, SmtConf, Option<Logic>) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        let script = format!(
+            "#!/bin/sh\n\
+            while IFS= read -r line; do\n\
+                case \"$line\" in '(check-sat'*) {} ;; '('*) echo success ;; esac\n\
+            done\n",
+            check_sat
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let conf = Backend::Z3.conf(path.to_string_lossy(), None).unwrap();
+        (name.into(), conf, None)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn races() {
+        let dir = std::env::temp_dir().join(format!("mikino-portfolio-{}", std::process::id()));
+        let slow = fake_solver(&dir, "slow", "sleep 10; echo sat");
+        let fast = fake_solver(&dir, "fast", "echo unsat");
+        let unknown = fake_solver(&dir, "unknown", "echo unknown");
+        let error = fake_solver(&dir, "error", "echo '(error \"oops\")'");
+        let counters = Rc::new(Counters::default());
+        let new = |solvers| Portfolio::new(solvers, None, counters.clone()).unwrap();
+
+        // The first definitive answer wins, the slow solver is killed.
+        let mut portfolio = new(vec![slow.clone(), unknown.clone(), fast]);
+        portfolio.push().unwrap();
+        portfolio
+            .declare_or_assert("(declare-const n Int)".into())
+            .unwrap();
+        let start = Instant::now();
+        let (sat, winner, _) = portfolio.check_sat().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!((sat, winner.as_str()), (false, "fast"));
+        assert_eq!(portfolio.winner, Some(2));
+        assert!(portfolio.solvers[0].proc.is_none());
+        assert!(portfolio.solvers[1].proc.is_some());
+
+        // Killed solvers are only spawned again at the next check-sat.
+        portfolio
+            .declare_or_assert("(assert (> n 0))".into())
+            .unwrap();
+        assert!(portfolio.solvers[0].proc.is_none());
+        let (_, winner, _) = portfolio.check_sat().unwrap();
+        assert_eq!(winner, "fast");
+        assert_eq!(portfolio.solvers[0].generation, 2);
+        assert_eq!(portfolio.solvers[2].generation, 1);
+        portfolio.pop().unwrap();
+        drop(portfolio);
+
+        // Without a definitive answer, the check-sat fails.
+        let mut portfolio = new(vec![unknown, error]);
+        assert_eq!(
+            portfolio.check_sat().unwrap_err().to_string(),
+            "no solver of the portfolio could decide the query: \
+            `unknown` answered `unknown`, `error` answered `(error \"oops\")`"
+        );
+        drop(portfolio);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Values of a counterexample, as `step: var = value`.
+    fn values(cex: &Cex) -> Vec<String> {
+        cex.trace
+            .iter()
+            .flat_map(|(step, values)| {
+                values
+                    .iter()
+                    .map(move |(var, cst)| format!("{}: {} = {}", step, var.id(), cst))
+            })
+            .collect()
+    }
+
+    /// Models of the same assignment, as each backend prints them.
+    #[test]
+    fn backend_models() {
+        let models = [
+            // Z3.
+            "(\n  (define-fun r@0 () Real (- (/ 1.0 3.0)))\n  (define-fun n@0 () Int (- 2))\n  \
+            (define-fun b@0 () Bool true)\n)",
+            // Older Z3 and CVC4.
+            "(model\n  (define-fun r@0 () Real (/ (- 1) 3))\n  (define-fun n@0 () Int (- 2))\n  \
+            (define-fun b@0 () Bool true)\n)",
+            // CVC5.
+            "(\n(define-fun r@0 () Real (/ (- 1) 3))\n(define-fun n@0 () Int (- 2))\n\
+            (define-fun b@0 () Bool true)\n)",
+            // Yices 2, with `--smt2-model-format`.
+            "(\n  (define-fun b@0 () Bool true)\n  (define-fun n@0 () Int (- 2))\n  \
+            (define-fun r@0 () Real (/ (- 1) 3))\n)",
+            // MathSAT.
+            "( (define-fun r@0 () Real (- (/ 1 3)))\n  (define-fun n@0 () Int (- 2))\n  \
+            (define-fun b@0 () Bool true) )",
+        ];
+        for model in models {
+            let cex = model_to_cex(model, 0).unwrap_or_else(|e| panic!("{}\n{}", e, model));
+            assert_eq!(
+                values(&cex),
+                vec!["0: b = true", "0: n = (- 2)", "0: r = (- (/ 1 3))"],
+                "{}",
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn cexs() {
+        let model = "(\n  (define-fun n@0 () Int 0)\n  (define-fun n@1 () Int 1)\n  \
+            (define-fun n@2 () Int 2)\n  (define-fun r@1 () Real 3)\n  \
+            (define-fun f@0 ((x Int)) Int x)\n  (define-fun k () Int 7)\n  \
+            (define-fun a@0 () (Array Int Int) ((as const (Array Int Int)) 0))\n  \
+            (define-fun m@0 () Int (+ n@0 1))\n)";
+        let cex = model_to_cex(model, 1).unwrap();
+        assert_eq!(values(&cex), vec!["0: n = 0", "1: n = 1", "1: r = (/ 3 1)"]);
+        let unexpected: Vec<_> = cex
+            .unexpected
+            .iter()
+            .map(|(var, val)| format!("{}: {}", var, val))
+            .collect();
+        assert_eq!(
+            unexpected,
+            vec![
+                "a@0 (Array Int Int): ((as const (Array Int Int)) 0)",
+                "f@0 int: x",
+                "k int: 7",
+                "m@0 int: (+ n@0 1)",
+            ]
+        );
+
+        assert!(model_to_cex("(define-fun n@0 () Int 0)", 0).is_err());
+        assert!(model_to_cex("((define-fun n@0 () Int 0))", 0).is_ok());
+        assert!(model_to_cex("((n@0 0))", 0).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Live progress line.
+//!
+//! While an engine runs, a background thread redraws a status line on `stderr` showing the engine,
+//! the current depth, the time spent at this depth, and the number of open candidates. The line is
+//! only drawn when both `stdout` and `stderr` are terminals.
+//!
+//! Anything printed while the line is active must go through [`Progress::pause`], which erases the
+//! line and prevents redraws until the guard is dropped.
+
+use std::{
+    io::Write,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// Time between two redraws.
+const TICK: Duration = Duration::from_millis(200);
+
+/// Progress state, shared with the redraw thread.
+pub struct State {
+    /// Engine running.
+    engine: &'static str,
+    /// Current depth, if relevant, and maximum depth if any.
+    depth: Option<(usize, Option<usize>)>,
+    /// Start of the current depth.
+    since: Instant,
+    /// Number of open candidates.
+    open: usize,
+    /// True if the line is currently drawn.
+    visible: bool,
+    /// True when the redraw thread must stop.
+    done: bool,
+}
+impl State {
+    /// Erases the line if needed.
+    fn erase(&mut self) {
+        if self.visible {
+            let mut err = std::io::stderr();
+            let _ = write!(err, "\r\x1b[K");
+            let _ = err.flush();
+            self.visible = false
+        }
+    }
+
+    /// Draws the line.
+    fn draw(&mut self) {
+        let depth = match self.depth {
+            Some((depth, Some(max))) => format!(" | depth {}/{}", depth, max),
+            Some((depth, None)) => format!(" | depth {}", depth),
+            None => String::new(),
+        };
+        let mut err = std::io::stderr();
+        let _ = write!(
+            err,
+            "\r\x1b[K[{}{} | {:.1}s | {} open candidate(s)]",
+            self.engine,
+            depth,
+            self.since.elapsed().as_secs_f64(),
+            self.open,
+        );
+        let _ = err.flush();
+        self.visible = true
+    }
+}
+
+/// State shared with the redraw thread, and condition variable used to wake it up when done.
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+/// Live progress line, does nothing if inactive.
+pub struct Progress {
+    /// Shared state and redraw thread, if active.
+    live: Option<(Shared, JoinHandle<()>)>,
+}
+impl Progress {
+    /// True if the progress line can be shown.
+    pub fn is_available() -> bool {
+        atty::is(atty::Stream::Stdout) && atty::is(atty::Stream::Stderr)
+    }
+
+    /// Constructor, the line is only shown if `active`.
+    pub fn new(active: bool, engine: &'static str, open: usize) -> Self {
+        if !active {
+            return Self { live: None };
+        }
+        let state = Arc::new((
+            Mutex::new(State {
+                engine,
+                depth: None,
+                since: Instant::now(),
+                open,
+                visible: false,
+                done: false,
+            }),
+            Condvar::new(),
+        ));
+        let thread_state = state.clone();
+        let thread = std::thread::spawn(move || {
+            let (state, wake) = &*thread_state;
+            let mut state = match state.lock() {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+            loop {
+                state = match wake.wait_timeout(state, TICK) {
+                    Ok((state, _)) => state,
+                    Err(_) => return,
+                };
+                if state.done {
+                    state.erase();
+                    return;
+                }
+                state.draw()
+            }
+        });
+        Self {
+            live: Some((state, thread)),
+        }
+    }
+
+    /// Starts a new depth, resets the time spent at the current depth.
+    pub fn depth(&self, depth: usize, max: Option<usize>, open: usize) {
+        if let Some(mut state) = self.lock() {
+            state.depth = Some((depth, max));
+            state.since = Instant::now();
+            state.open = open;
+        }
+    }
+
+    /// Erases the line and prevents redraws until the guard is dropped.
+    pub fn pause(&self) -> Option<MutexGuard<'_, State>> {
+        let mut state = self.lock()?;
+        state.erase();
+        Some(state)
+    }
+
+    /// Locks the state, if active.
+    fn lock(&self) -> Option<MutexGuard<'_, State>> {
+        self.live
+            .as_ref()
+            .and_then(|(state, _)| state.0.lock().ok())
+    }
+}
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some((state, thread)) = self.live.take() {
+            if let Ok(mut inner) = state.0.lock() {
+                inner.done = true;
+                inner.erase();
+            }
+            state.1.notify_all();
+            let _ = thread.join();
+        }
+    }
+}
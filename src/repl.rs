@@ -0,0 +1,616 @@
+//! Interactive hsmt scripts.
+//!
+//! The REPL reads hsmt commands on the standard input and runs them as soon as they are complete:
+//! an entry spans several lines as long as its braces, parentheses and brackets are not balanced
+//! and no empty line ends it. Lines are edited as in a shell when the input is a terminal, see
+//! [`crate::line`]. Lines starting with `:` are REPL commands, see [`HELP`].
+//!
+//! The session keeps a single solver alive, each entry only runs its own commands on it. The
+//! session text is still parsed and checked with each entry, so that an entry can use the
+//! declarations and meta-variables of the previous ones. Entries that do not parse, fail or panic
+//! are dropped from the session; as they may have modified the solver already, the solver is then
+//! reset and the session runs again silently. `:undo` does the same.
+
+mikino_api::prelude!();
+
+use std::{collections::BTreeMap as Map, path::PathBuf};
+
+use mikino_api::{
+    ast::script::{CheckSat, Command, Query},
+    err::PError,
+    parse,
+    script::{self, CheckSatRes, CheckSatResEnum, Outcome, Step},
+};
+
+use crate::{
+    line::{Editor, Line},
+    Run,
+};
+
+/// REPL commands.
+pub const HELP: &str = "\
+REPL commands:
+  :help          shows this message
+  :stack         shows the current declarations and assertions
+  :history       shows the entries of the session
+  :undo          drops the last entry of the session
+  :save <FILE>   writes the session as a script runnable with `mikino script`
+  :quit          exits the REPL, as does end of input
+
+Entries span several lines until their braces, parentheses and brackets are balanced, or
+until an empty line. Arrows move the cursor and browse the previous lines, `ctrl-c` drops the
+current entry.";
+
+/// Result of running some commands.
+enum Ran {
+    /// The commands ran, with the result of the last one if it is a query.
+    Query(Option<CheckSatRes>),
+    /// The commands exited or panicked.
+    Done(Outcome),
+}
+
+/// An interactive hsmt session.
+pub struct Repl<'env> {
+    /// Run environment.
+    env: &'env Run,
+    /// Solver of the session, logs to `repl.smt2` in the SMT log directory if any.
+    solver: SLSolver,
+    /// Meta-variables of the session and their value.
+    meta_env: Map<String, CheckSatRes>,
+    /// Entries of the session, in order.
+    entries: Vec<String>,
+    /// Line editor.
+    editor: Editor,
+    /// True if the input is a terminal, prompts are only shown in this case.
+    interactive: bool,
+    /// True while the session runs again after a reset, nothing is shown then.
+    replaying: bool,
+}
+
+impl<'env> Repl<'env> {
+    /// Constructor, spawns the solver of the session.
+    pub fn new(env: &'env Run, smt_log_dir: &Option<String>) -> Res<Self> {
+        let smt_log = smt_log_dir.as_ref().map(|s| {
+            let mut path = PathBuf::from(s);
+            path.push("repl.smt2");
+            path
+        });
+        let solver = SLSolver::new(env.solver_conf()?, smt_log)
+            .chain_err(|| "during REPL solver creation")
+            .chain_err(|| crate::DOCTOR_HINT)?;
+        crate::interrupt::rsmt2_solver_spawned();
+        Ok(Self {
+            env,
+            solver,
+            meta_env: Map::new(),
+            entries: vec![],
+            editor: Editor::new(),
+            interactive: atty::is(atty::Stream::Stdin),
+            replaying: false,
+        })
+    }
+
+    /// Runs the REPL until the end of the input, `:quit` or `exit!`.
+    ///
+    /// A non-zero `exit!` code exits the process with that code.
+    pub fn run(&mut self) -> Res<()> {
+        if self.interactive {
+            println!(
+                "mikino {} hsmt REPL, `{}` for help",
+                env!("CARGO_PKG_VERSION"),
+                self.env.bold.paint(":help")
+            );
+        }
+        while let Some(entry) = self.read()? {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            } else if let Some(cmd) = entry.strip_prefix(':') {
+                match self.command(cmd) {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(e) => self.env.print_error(e),
+                }
+                continue;
+            }
+            match self.run_entry(entry) {
+                Ok(None) => self.entries.push(entry.into()),
+                Ok(Some(0)) => break,
+                Ok(Some(code)) => std::process::exit(code as i32),
+                Err(e) => self.env.print_error(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads an entry, `None` at the end of the input.
+    ///
+    /// An interrupted entry is empty.
+    fn read(&mut self) -> Res<Option<String>> {
+        let mut entry = String::new();
+        loop {
+            let prompt = match (self.interactive, entry.is_empty()) {
+                (false, _) => String::new(),
+                (true, true) => self.env.gray.paint("mikino> ").to_string(),
+                (true, false) => self.env.gray.paint("    ... ").to_string(),
+            };
+            let line = match self.editor.read_line(&prompt)? {
+                Line::Text(line) => line,
+                Line::Interrupted => return Ok(Some(String::new())),
+                Line::Eof => return Ok(if entry.is_empty() { None } else { Some(entry) }),
+            };
+            let is_command = entry.is_empty() && line.trim_start().starts_with(':');
+            entry.push_str(&line);
+            entry.push('\n');
+            // An empty line ends the entry even if it is not balanced, the user is stuck otherwise.
+            if is_command || line.trim().is_empty() || depth(&entry) <= 0 {
+                return Ok(Some(entry));
+            }
+        }
+    }
+
+    /// Session text, the entries separated by newlines.
+    fn session(&self) -> String {
+        let mut txt = String::new();
+        for entry in &self.entries {
+            txt.push_str(entry);
+            txt.push('\n');
+        }
+        txt
+    }
+
+    /// Runs a REPL command, returns `false` if the REPL should stop.
+    fn command(&mut self, cmd: &str) -> Res<bool> {
+        let (cmd, arg) = match cmd.trim().split_once(char::is_whitespace) {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (cmd.trim(), ""),
+        };
+        match (cmd, arg) {
+            ("help" | "h", "") => println!("{}", HELP),
+            ("quit" | "q", "") => return Ok(false),
+            ("stack", "") => self.show_stack()?,
+            ("history", "") => {
+                if self.entries.is_empty() {
+                    println!("no entries so far");
+                }
+                for (idx, entry) in self.entries.iter().enumerate() {
+                    for (l_idx, line) in entry.lines().enumerate() {
+                        if l_idx == 0 {
+                            println!("{} | {}", self.env.gray.paint(format!("{:>3}", idx)), line);
+                        } else {
+                            println!("    | {}", line);
+                        }
+                    }
+                }
+            }
+            ("undo", "") => match self.entries.pop() {
+                Some(entry) => {
+                    self.restart()?;
+                    println!("dropped `{}`", self.env.bold.paint(entry))
+                }
+                None => println!("no entries so far"),
+            },
+            ("save", file) if !file.is_empty() => {
+                std::fs::write(file, self.session())
+                    .chain_err(|| format!("while writing session to file `{}`", file))?;
+                println!(
+                    "wrote {} entries to `{}`",
+                    self.entries.len(),
+                    self.env.bold.paint(file)
+                );
+            }
+            _ => bail!(
+                "unknown REPL command `{}`, `{}` lists the commands",
+                self.env.bold.paint(format!(":{}", cmd)),
+                self.env.bold.paint(":help")
+            ),
+        }
+        Ok(true)
+    }
+
+    /// Runs an entry after the session, yields the exit code if the entry exits.
+    fn run_entry(&mut self, entry: &str) -> Res<Option<isize>> {
+        // Parse the entry alone first, so that syntax errors point inside the entry.
+        let len = parse::script(entry)
+            .chain_err(|| "parsing entry")?
+            .content
+            .len();
+
+        let session = self.session();
+        let txt = format!("{}{}\n", session, entry);
+        let script = build(&txt, Some((session.len(), entry))).chain_err(|| "checking entry")?;
+        let cmds = commands(&script);
+        let cmds = &cmds[cmds.len() - len..];
+
+        match self.run_cmds(&txt, cmds) {
+            Ok(Ran::Query(res)) => {
+                self.show_res(&txt, res);
+                Ok(None)
+            }
+            Ok(Ran::Done(outcome @ Outcome::Exit(..))) => {
+                println!("{}", outcome.pretty(&txt, &self.env.styles, false));
+                match outcome {
+                    Outcome::Exit(_, code) => Ok(Some(code)),
+                    Outcome::Panic(..) => unreachable!(),
+                }
+            }
+            Ok(Ran::Done(outcome @ Outcome::Panic(..))) => {
+                eprintln!("{}", outcome.pretty(&txt, &self.env.styles, false));
+                self.restart()?;
+                bail!("entry panicked, dropping it")
+            }
+            Err(e) => {
+                self.restart()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Resets the solver and runs the session again, silently.
+    fn restart(&mut self) -> Res<()> {
+        let txt = self.session();
+        let script = build(&txt, None).chain_err(|| "checking session")?;
+        self.solver
+            .reset()
+            .chain_err(|| "while resetting the REPL solver")?;
+        self.meta_env.clear();
+        self.replaying = true;
+        let res = self.run_cmds(&txt, commands(&script));
+        self.replaying = false;
+        res.chain_err(|| "while running the session again")?;
+        Ok(())
+    }
+
+    /// Shows a step, unless replaying.
+    fn show(&self, txt: &str, step: Step) {
+        if self.replaying {
+            return;
+        }
+        if let Some(pretty) = step.pretty(txt, &self.env.styles, false) {
+            println!("{}", pretty)
+        }
+    }
+
+    /// Shows a query result, if any.
+    fn show_res(&self, txt: &str, res: Option<CheckSatRes>) {
+        if let Some(res) = res {
+            self.show(txt, Step::CheckRes(res.span, res.res))
+        }
+    }
+
+    /// Runs a sequence of commands.
+    ///
+    /// As in scripts, the result of the last command is the result of the sequence, the results
+    /// of the other queries are shown.
+    fn run_cmds(&mut self, txt: &str, cmds: &[Command<expr::Expr, expr::MExpr>]) -> Res<Ran> {
+        let mut last = None;
+        for cmd in cmds {
+            let res = last.take();
+            self.show_res(txt, res);
+            match self.run_cmd(txt, cmd)? {
+                Ran::Query(res) => last = res,
+                done @ Ran::Done(_) => return Ok(done),
+            }
+        }
+        Ok(Ran::Query(last))
+    }
+
+    /// Runs a command.
+    fn run_cmd(&mut self, txt: &str, cmd: &Command<expr::Expr, expr::MExpr>) -> Res<Ran> {
+        match cmd {
+            Command::SetOptions(opts) => {
+                for opt in opts.content.iter() {
+                    let key = format!(":{}", opt.key.inner);
+                    match opt.val.inner.as_ref() {
+                        Either::Left(cst) => self.solver.set_option(&key, cst),
+                        Either::Right(s) => self.solver.set_option(&key, format!("\"{}\"", s)),
+                    }
+                    .chain_err(|| {
+                        PError::new_error(opts.span, txt, "while handling these options")
+                    })?
+                }
+            }
+            Command::Vars(vars) => {
+                for var in vars.decls.all() {
+                    self.solver
+                        .declare_const(var.id(), var.typ())
+                        .chain_err(|| {
+                            PError::new_error(vars.span, txt, "while handling these declarations")
+                        })?
+                }
+            }
+            Command::MLet(mlet) => {
+                let res = self.check_sat(txt, &mlet.rhs)?;
+                let _prev = self.meta_env.insert(mlet.lhs.inner.clone(), res);
+            }
+            Command::Assert(assert) => {
+                for (idx, expr) in assert.exprs.iter().enumerate() {
+                    self.solver.assert(expr).chain_err(|| {
+                        PError::new_error(
+                            assert.span,
+                            txt,
+                            format!("while asserting expression #{} of this assertion", idx + 1),
+                        )
+                    })?
+                }
+            }
+            Command::Echo(echo) => self.show(txt, Step::Echo(echo.clone())),
+            Command::GetModel(gm) => {
+                let smt_model = self
+                    .solver
+                    .get_model()
+                    .chain_err(|| PError::new_error(gm.span, txt, "while requesting a model"))?;
+                let mut model = Map::new();
+                for (id, args, typ, val) in smt_model {
+                    if !args.is_empty() {
+                        bail!(PError::new_error(
+                            gm.span,
+                            txt,
+                            "unexpected function in model"
+                        ))
+                    }
+                    if model.contains_key(&id) {
+                        bail!(PError::new_error(
+                            gm.span,
+                            txt,
+                            format!("illegal model specifies `{}` twice", id)
+                        ))
+                    }
+                    let _prev = model.insert(id, (val, typ));
+                }
+                self.show(
+                    txt,
+                    Step::Model {
+                        span: gm.span,
+                        token: gm.token.clone(),
+                        model,
+                    },
+                )
+            }
+            Command::GetValues(gv) => {
+                let smt_vals = self
+                    .solver
+                    .get_values(gv.exprs.iter().map(|pair| &pair.0))
+                    .chain_err(|| {
+                        PError::new_error(gv.span, txt, "while performing an evaluation")
+                    })?;
+                if gv.exprs.len() != smt_vals.len() {
+                    bail!(PError::new_error(
+                        gv.span,
+                        txt,
+                        format!(
+                            "solver produced {} value(s), expected {}",
+                            smt_vals.len(),
+                            gv.exprs.len()
+                        )
+                    ))
+                }
+                let vals = gv
+                    .exprs
+                    .iter()
+                    .zip(smt_vals)
+                    .map(|((_, repr), (_, val))| (repr.clone(), val))
+                    .collect();
+                self.show(
+                    txt,
+                    Step::Eval {
+                        span: gv.span,
+                        token: gv.token.clone(),
+                        vals,
+                    },
+                )
+            }
+            Command::Reset(reset) => self
+                .solver
+                .reset()
+                .chain_err(|| PError::new_error(reset.span, txt, "while resetting the solver"))?,
+            Command::Query(query) => return self.run_query(txt, query),
+        }
+        Ok(Ran::Query(None))
+    }
+
+    /// Runs a query.
+    fn run_query(&mut self, txt: &str, query: &Query<expr::Expr, expr::MExpr>) -> Res<Ran> {
+        match query {
+            Query::Block(block) => self.run_cmds(txt, &block.content),
+            Query::CheckSat(check) => Ok(Ran::Query(Some(self.check_sat(txt, check)?))),
+            Query::Ite(ite) => {
+                let cnd = match &ite.cnd {
+                    Either::Left(meta) => match self.meta_env.get(&meta.inner.ident) {
+                        Some(res) => res.res,
+                        None => bail!(PError::new_error(
+                            meta.span,
+                            txt,
+                            "[fatal] unknown meta-variable"
+                        )),
+                    },
+                    Either::Right(check) => self.check_sat(txt, check)?.res,
+                };
+                let branch = match cnd {
+                    CheckSatResEnum::True => &ite.thn,
+                    CheckSatResEnum::False => &ite.els,
+                    CheckSatResEnum::Timeout | CheckSatResEnum::Unknown => match &ite.otw {
+                        Some(otw) => otw,
+                        None => bail!(PError::new_error(
+                            ite.span,
+                            txt,
+                            format!("condition is {} and there is no otherwise branch", cnd)
+                        )),
+                    },
+                };
+                self.run_cmds(txt, &branch.content)
+            }
+            Query::Panic(panic) => Ok(Ran::Done(Outcome::Panic(panic.span, panic.msg.clone()))),
+            Query::Exit(exit) => Ok(Ran::Done(Outcome::Exit(Some(exit.span), exit.code))),
+        }
+    }
+
+    /// Runs a check-sat.
+    fn check_sat(&mut self, txt: &str, check: &CheckSat) -> Res<CheckSatRes> {
+        let res = if check.assuming.is_empty() {
+            self.solver.check_sat()
+        } else {
+            self.solver
+                .check_sat_assuming(check.assuming.iter().map(|s| &s.inner))
+        };
+        CheckSatRes::new(check.span, res)
+            .chain_err(|| PError::new_error(check.span, txt, "while handling this check-sat"))
+    }
+
+    /// Shows the declarations and assertions of the session since the last reset.
+    fn show_stack(&self) -> Res<()> {
+        let txt = self.session();
+        let script = build(&txt, None).chain_err(|| "checking session")?;
+        let (mut vars, mut asserts) = (vec![], vec![]);
+        stack_of(std::slice::from_ref(&script), &mut vars, &mut asserts);
+
+        if vars.is_empty() && asserts.is_empty() {
+            println!("nothing declared or asserted");
+            return Ok(());
+        }
+        println!("{}", self.env.bold.paint("vars"));
+        for var in &vars {
+            println!("  {}: {}", var.id(), var.typ());
+        }
+        println!("{}", self.env.bold.paint("assertions"));
+        for expr in &asserts {
+            println!(
+                "  {}",
+                crate::hsmt::expr_to_hsmt(expr, |var| var.id().to_string())
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parses and builds a script.
+///
+/// Errors located after `offset` point inside `entry` if `entry = Some((offset, entry))`.
+fn build(txt: &str, entry: Option<(usize, &str)>) -> Res<Command<expr::Expr, expr::MExpr>> {
+    let ast = parse::script(txt)?;
+    script::build::doit(ast).map_err(|e| {
+        let span = e.span;
+        let (prev, row, col, line, next) = match entry {
+            Some((offset, entry)) if span.start >= offset => {
+                parse::Span::new(span.start - offset, span.end - offset).pretty_of(entry)
+            }
+            _ => span.pretty_of(txt),
+        };
+        Error::parse("", row, col, line, prev, next).extend(e.error.into_iter())
+    })
+}
+
+/// Top-level commands of a script.
+fn commands<E, ME>(script: &Command<E, ME>) -> &[Command<E, ME>] {
+    match script {
+        Command::Query(Query::Block(block)) => &block.content,
+        cmd => std::slice::from_ref(cmd),
+    }
+}
+
+/// Collects the declarations and assertions of some commands, conditional ones excluded.
+fn stack_of<E: Clone, ME>(
+    cmds: &[Command<E, ME>],
+    vars: &mut Vec<expr::Var>,
+    asserts: &mut Vec<E>,
+) {
+    for cmd in cmds {
+        match cmd {
+            Command::Vars(decls) => vars.extend(decls.decls.all()),
+            Command::Assert(assert) => asserts.extend(assert.exprs.iter().cloned()),
+            Command::Reset(_) => {
+                vars.clear();
+                asserts.clear();
+            }
+            Command::Query(Query::Block(block)) => stack_of(&block.content, vars, asserts),
+            _ => (),
+        }
+    }
+}
+
+/// Nesting depth of the delimiters at the end of some text, ignoring strings and comments.
+fn depth(txt: &str) -> isize {
+    let mut depth = 0;
+    let mut chars = txt.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            '"' => {
+                while let Some(char) = chars.next() {
+                    match char {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for char in chars.by_ref() {
+                    if char == '\n' {
+                        break;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    depth
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn depth_ignores_strings_and_comments() {
+        assert_eq!(depth("vars { x: int }"), 0);
+        assert_eq!(depth("assert {\n  x > 0,"), 1);
+        assert_eq!(depth("echo!(\"{ (\") // {\n"), 0);
+        assert_eq!(depth("echo!(\"\\\"}\""), 1);
+        assert_eq!(depth("}"), -1);
+    }
+
+    #[test]
+    fn entry_commands_are_the_last_ones() {
+        let session = "vars { x: int }\nassert { x > 0 }\n";
+        let entry = "check_sat!()\nassert { x < 2 } get_model!()";
+        let len = parse::script(entry).unwrap().content.len();
+        let txt = format!("{}{}\n", session, entry);
+        let script = build(&txt, Some((session.len(), entry))).unwrap();
+        let cmds = commands(&script);
+        assert_eq!((cmds.len(), len), (5, 3));
+        assert!(matches!(
+            &cmds[cmds.len() - len..],
+            [
+                Command::Query(Query::CheckSat(_)),
+                Command::Assert(_),
+                Command::GetModel(_)
+            ]
+        ));
+    }
+
+    #[test]
+    fn entry_errors_point_inside_the_entry() {
+        let session = "vars { x: int }\n";
+        let entry = "assert { y > 0 }";
+        let txt = format!("{}{}\n", session, entry);
+        let err = build(&txt, Some((session.len(), entry))).unwrap_err();
+        assert!(err.to_string().contains("1:10"), "{}", err);
+    }
+
+    #[test]
+    fn stack_skips_conditionals_and_resets() {
+        let txt = "vars { x: int }\nassert { x > 0 }\nreset!()\nvars { y: int, b: bool }\n\
+            { assert { y > 1 } }\nif check_sat!() { assert { b } } else { assert { ¬b } }\n";
+        let script = build(txt, None).unwrap();
+        let (mut vars, mut asserts) = (vec![], vec![]);
+        stack_of(commands(&script), &mut vars, &mut asserts);
+        let vars: Vec<_> = vars.iter().map(|var| var.id().to_string()).collect();
+        assert_eq!(vars, vec!["b", "y"]);
+        let asserts: Vec<_> = asserts.iter().map(|expr| expr.to_string()).collect();
+        assert_eq!(asserts, vec!["(> y 1)"]);
+    }
+}
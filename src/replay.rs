@@ -0,0 +1,414 @@
+//! Replay of SMT logs.
+//!
+//! SMT logs (see `--smt_log`) only contain the commands mikino sends. Replaying a log feeds these
+//! commands to a solver and records its answers, with the time each one took, next to the log:
+//! `base.smt2` gets `base.answers`. Later replays compare the answers of the solver with the
+//! recorded ones.
+//!
+//! Recorded answers also let mikino stand in for a solver, see [`serve`]: it reads commands on its
+//! standard input and answers with the recordings of the log these commands come from.
+
+mikino_api::prelude!();
+
+use std::{
+    collections::BTreeMap as Map,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::import::sexp;
+
+/// Prefix of the argument making mikino answer solver queries with recorded answers, see
+/// [`serve`].
+///
+/// The directory containing the logs and their answers follows the prefix. Mikino passes it to
+/// itself when standing in for a solver, among the options of the solver.
+pub const SERVE_ARG: &str = "--mikino-replay-solver=";
+
+/// Echoed after each command to know where its answer stops.
+const SYNC: &str = "mikino-replay-sync";
+
+/// Prefix of the lines starting an answer in answer files.
+const ANSWER_PREFIX: &str = ";; answer";
+
+/// A logged command.
+pub type Cmd = String;
+
+/// Answer of a solver to a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answer {
+    /// Index of the command in the log.
+    pub idx: usize,
+    /// Time the solver took to answer.
+    pub time: Duration,
+    /// Answer, trimmed.
+    pub text: String,
+}
+
+/// The logs of a directory, sorted by name.
+pub fn logs(dir: impl AsRef<Path>) -> Res<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let entries = std::fs::read_dir(dir)
+        .chain_err(|| format!("while reading directory `{}`", dir.display()))?;
+    let mut logs = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map(|ext| ext == "smt2").unwrap_or(false) {
+            logs.push(path)
+        }
+    }
+    logs.sort();
+    Ok(logs)
+}
+
+/// Path of the answer file of a log.
+pub fn answers_path(log: &Path) -> PathBuf {
+    log.with_extension("answers")
+}
+
+/// Commands of a log.
+pub fn commands(log: &Path) -> Res<Vec<Cmd>> {
+    let txt = std::fs::read_to_string(log)
+        .chain_err(|| format!("while reading SMT log `{}`", log.display()))?;
+    let commands =
+        sexp::parse(&txt).chain_err(|| format!("while parsing SMT log `{}`", log.display()))?;
+    Ok(commands.iter().map(sexp::Sexp::to_string).collect())
+}
+
+/// True if a command is `(exit)`.
+fn is_exit(command: &str) -> bool {
+    command == "(exit)"
+}
+
+/// Runs some commands on a solver.
+///
+/// Returns the non-empty answers, stops at the first `(exit)` command.
+pub fn run(conf: &SmtConf, commands: &[Cmd]) -> Res<Vec<Answer>> {
+    let mut kid = Command::new(conf.get_cmd())
+        .args(conf.get_options())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .chain_err(|| format!("while spawning solver `{}`", conf.get_cmd()))?;
+    crate::interrupt::solver_spawned(kid.id());
+    let mut stdin = kid.stdin.take().ok_or("could not access solver's stdin")?;
+    let mut stdout = BufReader::new(
+        kid.stdout
+            .take()
+            .ok_or("could not access solver's stdout")?,
+    );
+
+    let mut answers = vec![];
+    let mut line = String::new();
+    for (idx, command) in commands.iter().enumerate() {
+        if is_exit(command) {
+            break;
+        }
+        let start = Instant::now();
+        writeln!(stdin, "{}\n(echo \"{}\")", command, SYNC)?;
+        stdin.flush()?;
+
+        let mut text = String::new();
+        loop {
+            line.clear();
+            if stdout.read_line(&mut line)? == 0 {
+                bail!(
+                    "solver exited on command #{} `{}`, answer so far: `{}`",
+                    idx,
+                    command,
+                    text.trim()
+                )
+            }
+            if line.trim().trim_matches('"') == SYNC {
+                break;
+            }
+            text.push_str(&line)
+        }
+        let time = start.elapsed();
+
+        let text = text.trim();
+        if !text.is_empty() {
+            answers.push(Answer {
+                idx,
+                time,
+                text: text.into(),
+            })
+        }
+    }
+
+    let _ = writeln!(stdin, "(exit)");
+    drop(stdin);
+    let _ = kid.wait();
+    Ok(answers)
+}
+
+/// Writes some answers to a file.
+pub fn write_answers(path: &Path, answers: &[Answer]) -> Res<()> {
+    let mut file = std::fs::File::create(path)
+        .chain_err(|| format!("while opening file `{}` in write mode", path.display()))?;
+    for answer in answers {
+        writeln!(
+            file,
+            "{} {} {:.6}\n{}",
+            ANSWER_PREFIX,
+            answer.idx,
+            answer.time.as_secs_f64(),
+            answer.text
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads some answers from a file.
+pub fn read_answers(path: &Path) -> Res<Vec<Answer>> {
+    let txt = std::fs::read_to_string(path)
+        .chain_err(|| format!("while reading answer file `{}`", path.display()))?;
+    let mut answers: Vec<Answer> = vec![];
+    for (line_idx, line) in txt.lines().enumerate() {
+        if let Some(header) = line.strip_prefix(ANSWER_PREFIX) {
+            let mut header = header.split_whitespace();
+            let (idx, time) = match (header.next(), header.next()) {
+                (Some(idx), Some(time)) => (idx.parse::<usize>(), time.parse::<f64>()),
+                _ => bail!("line {}: illegal answer header", line_idx + 1),
+            };
+            match (idx, time) {
+                (Ok(idx), Ok(time)) if time >= 0.0 => answers.push(Answer {
+                    idx,
+                    time: Duration::from_secs_f64(time),
+                    text: String::new(),
+                }),
+                _ => bail!("line {}: illegal answer header", line_idx + 1),
+            }
+        } else if let Some(answer) = answers.last_mut() {
+            if !answer.text.is_empty() {
+                answer.text.push('\n')
+            }
+            answer.text.push_str(line)
+        } else {
+            bail!("line {}: expected an answer header", line_idx + 1)
+        }
+    }
+    Ok(answers)
+}
+
+/// Difference between a recorded answer and a replayed one.
+#[derive(Debug, Clone)]
+pub struct Diff {
+    /// Index of the command.
+    pub idx: usize,
+    /// Command.
+    pub command: Cmd,
+    /// Recorded answer, if any.
+    pub recorded: Option<String>,
+    /// Replayed answer, if any.
+    pub replayed: Option<String>,
+}
+impl Diff {
+    /// True if the difference is on the result of a check-sat.
+    pub fn is_verdict(&self) -> bool {
+        self.command.starts_with("(check-sat")
+    }
+}
+
+/// Compares recorded answers with replayed ones.
+pub fn compare(commands: &[Cmd], recorded: &[Answer], replayed: &[Answer]) -> Vec<Diff> {
+    let mut answers: Map<usize, (Option<&str>, Option<&str>)> = Map::new();
+    for answer in recorded {
+        answers.entry(answer.idx).or_default().0 = Some(&answer.text)
+    }
+    for answer in replayed {
+        answers.entry(answer.idx).or_default().1 = Some(&answer.text)
+    }
+    answers
+        .into_iter()
+        .filter(|(_, (recorded, replayed))| recorded != replayed)
+        .map(|(idx, (recorded, replayed))| Diff {
+            idx,
+            command: commands.get(idx).cloned().unwrap_or_default(),
+            recorded: recorded.map(String::from),
+            replayed: replayed.map(String::from),
+        })
+        .collect()
+}
+
+/// Answers solver queries on stdin with the recorded answers of the logs in a directory.
+///
+/// The log queries come from is the first one consistent with all the commands received so far.
+///
+/// Options set when spawning the solver, such as `:print-success` and `:produce-models`, are not
+/// part of the logs: `set-option` commands that are not logged are answered directly.
+pub fn serve(dir: impl AsRef<Path>) -> Res<()> {
+    let dir = dir.as_ref();
+    let mut logs = vec![];
+    for log in self::logs(dir)? {
+        let answers_path = answers_path(&log);
+        if !answers_path.exists() {
+            continue;
+        }
+        let answers: Map<usize, String> = read_answers(&answers_path)?
+            .into_iter()
+            .map(|answer| (answer.idx, answer.text))
+            .collect();
+        logs.push((commands(&log)?, answers))
+    }
+    if logs.is_empty() {
+        bail!(
+            "no recorded answers in `{}`, run `mikino replay {}` first",
+            dir.display(),
+            dir.display()
+        )
+    }
+
+    let stdin = std::io::stdin();
+    let mut stdin = stdin.lock();
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut buf = String::new();
+    let mut idx = 0;
+    let mut print_success = false;
+    loop {
+        let read = stdin.read_line(&mut buf)?;
+        // Commands can span several lines, wait until the buffer parses.
+        let commands = match sexp::parse(&buf) {
+            Ok(commands) => commands,
+            Err(_) if read > 0 => continue,
+            Err(e) => return Err(e.chain_err(|| "while parsing solver input")),
+        };
+        buf.clear();
+
+        for command in commands {
+            let command = command.to_string();
+            if is_exit(&command) {
+                return Ok(());
+            }
+            let logged = logs
+                .iter()
+                .any(|(commands, _)| commands.get(idx) == Some(&command));
+            if !logged && command.starts_with("(set-option ") {
+                if let Some(val) = command
+                    .strip_prefix("(set-option :print-success ")
+                    .and_then(|val| val.strip_suffix(')'))
+                {
+                    print_success = val == "true"
+                }
+                if print_success {
+                    writeln!(stdout, "success")?;
+                    stdout.flush()?;
+                }
+                continue;
+            }
+            logs.retain(|(commands, _)| commands.get(idx) == Some(&command));
+            let answers = match logs.first() {
+                Some((_, answers)) => answers,
+                None => {
+                    writeln!(
+                        stdout,
+                        "(error \"command #{} `{}` is not in any recorded log\")",
+                        idx,
+                        command.replace('"', "\"\"")
+                    )?;
+                    stdout.flush()?;
+                    bail!("command #{} `{}` is not in any recorded log", idx, command)
+                }
+            };
+            if let Some(answer) = answers.get(&idx) {
+                writeln!(stdout, "{}", answer)?;
+                stdout.flush()?;
+            } else if print_success {
+                writeln!(stdout, "success")?;
+                stdout.flush()?;
+            }
+            idx += 1
+        }
+
+        if read == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "mikino-replay-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        path
+    }
+
+    fn answer(idx: usize, millis: u64, text: &str) -> Answer {
+        Answer {
+            idx,
+            time: Duration::from_millis(millis),
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn answers_round_trip() {
+        let answers = vec![
+            answer(2, 1500, "sat"),
+            answer(3, 0, "((x 7)\n (y (- 1)))"),
+            answer(7, 20, "unknown"),
+        ];
+        let path = temp_path("answers");
+        write_answers(&path, &answers).unwrap();
+        let read = read_answers(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), answers);
+    }
+
+    #[test]
+    fn malformed_answers() {
+        let err = |txt: &str| {
+            let path = temp_path("malformed");
+            std::fs::write(&path, txt).unwrap();
+            let res = read_answers(&path);
+            std::fs::remove_file(&path).unwrap();
+            match res {
+                Ok(_) => panic!("expected an error for `{}`", txt),
+                Err(e) => e.to_string(),
+            }
+        };
+        assert_eq!(err("sat\n"), "line 1: expected an answer header");
+        assert_eq!(err(";; answer 0\nsat\n"), "line 1: illegal answer header");
+        assert_eq!(
+            err(";; answer 0 0.1\nsat\n;; answer x 0.1\n"),
+            "line 3: illegal answer header"
+        );
+        assert_eq!(
+            err(";; answer 0 -1\nsat\n"),
+            "line 1: illegal answer header"
+        );
+    }
+
+    #[test]
+    fn differences() {
+        let commands: Vec<Cmd> = vec!["(declare-fun x () Int)".into(), "(check-sat)".into()];
+        let recorded = vec![answer(1, 10, "sat")];
+        assert!(compare(&commands, &recorded, &[answer(1, 99, "sat")]).is_empty());
+        let diffs = compare(&commands, &recorded, &[answer(0, 1, "error")]);
+        let diffs: Vec<_> = diffs
+            .iter()
+            .map(|diff| {
+                let (recorded, replayed) = (diff.recorded.as_deref(), diff.replayed.as_deref());
+                (diff.idx, diff.is_verdict(), recorded, replayed)
+            })
+            .collect();
+        assert_eq!(
+            diffs,
+            vec![
+                (0, false, None, Some("error")),
+                (1, true, Some("sat"), None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,743 @@
+//! Solver sessions shared by the engines.
+//!
+//! Base, step and BMC all work on the same unrolled variables. Instead of spawning a solver and
+//! declaring everything for each engine, engines get a [`Session`] from a [`Pool`], which takes it
+//! back when the engine is done with it. The next engine reuses the solver and the declarations.
+//!
+//! The declarations live in a scope pushed when a solver starts working on a system, and engines
+//! make their assertions in nested scopes, popped before the session goes back to the pool. A
+//! session dropped with scopes still open, because an engine failed, does not go back to the pool.
+//!
+//! Mikino checks a single system per run, systems still have a key in the pool so that a solver
+//! never mixes the declarations of two systems: moving on to another system pops the declarations
+//! scope rather than sending a `reset`, which would also forget the options set when spawning the
+//! solver. Solvers get the logic of the first system they work on when spawned, they are only
+//! reused for systems in the same logic.
+//!
+//! With `--portfolio`, sessions run several solvers racing on each check-sat, see
+//! [`crate::portfolio`]. The solver that won each query is recorded by the pool.
+//!
+//! Sessions logging to a file are not shared: a solver only logs to one file, while SMT logs have
+//! one file per engine, which is what replays rely on.
+//!
+//! The pool counts the solvers spawned, the commands sent and the check-sats for `--stats`, see
+//! [`crate::stats::Counters`].
+
+mikino_api::prelude!();
+
+use std::{
+    cell::{Cell, RefCell},
+    io::Write,
+    path::PathBuf,
+    rc::Rc,
+    time::Instant,
+};
+
+use check::{BaseRes, BmcRes, CheckRes, StepRes};
+use expr::Var;
+use mikino_api::rsmt2::print::{Expr2Smt, Sort2Smt, Sym2Smt};
+use trans::Sys;
+
+use crate::{
+    logic::Logic,
+    portfolio::{self, Portfolio},
+    stats::Counters,
+};
+
+/// Solver behind a session.
+///
+/// The portfolio counts the commands it sends itself, as it sends each of them to several solvers.
+enum Solver {
+    /// A single solver.
+    Single(SFSolver),
+    /// Several solvers racing on each check-sat.
+    Portfolio(Portfolio),
+}
+impl Solver {
+    /// Pushes a scope.
+    fn push(&mut self, counters: &Counters) -> Res<()> {
+        match self {
+            Self::Single(solver) => {
+                counters.calls(1);
+                solver.push(1)?
+            }
+            Self::Portfolio(portfolio) => portfolio.push()?,
+        }
+        Ok(())
+    }
+    /// Pops a scope.
+    fn pop(&mut self, counters: &Counters) -> Res<()> {
+        match self {
+            Self::Single(solver) => {
+                counters.calls(1);
+                solver.pop(1)?
+            }
+            Self::Portfolio(portfolio) => portfolio.pop()?,
+        }
+        Ok(())
+    }
+
+    /// Declares a variable at some step.
+    fn declare(&mut self, var: &Var, step: Unroll, counters: &Counters) -> Res<()> {
+        match self {
+            Self::Single(solver) => {
+                counters.calls(1);
+                solver.declare_const_with(var, var.typ(), step)?
+            }
+            Self::Portfolio(portfolio) => {
+                let mut command = b"(declare-const ".to_vec();
+                var.sym_to_smt2(&mut command, step)?;
+                write!(command, " ")?;
+                var.typ().sort_to_smt2(&mut command)?;
+                write!(command, ")")?;
+                portfolio.declare_or_assert(String::from_utf8_lossy(&command).into())?
+            }
+        }
+        Ok(())
+    }
+
+    /// Asserts an expression at some step.
+    fn assert(
+        &mut self,
+        expr: impl Expr2Smt<Unroll>,
+        step: Unroll,
+        counters: &Counters,
+    ) -> Res<()> {
+        match self {
+            Self::Single(solver) => {
+                counters.calls(1);
+                solver.assert_with(expr, step)?
+            }
+            Self::Portfolio(portfolio) => {
+                let mut command = b"(assert ".to_vec();
+                expr.expr_to_smt2(&mut command, step)?;
+                write!(command, ")")?;
+                portfolio.declare_or_assert(String::from_utf8_lossy(&command).into())?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A solver waiting in the pool.
+struct Idle {
+    /// The solver.
+    solver: Solver,
+    /// Logic of the solver.
+    logic: Logic,
+    /// Key of the system the solver works on, and number of steps declared.
+    system: Option<(usize, Unroll)>,
+}
+
+/// Pool of solver processes.
+pub struct Pool {
+    /// False if solvers are never reused.
+    share: bool,
+    /// Solvers not used by any engine.
+    idle: RefCell<Vec<Idle>>,
+    /// Next system key.
+    next_key: Cell<usize>,
+    /// Solvers of the portfolio, if any.
+    portfolio: Vec<portfolio::Member>,
+    /// Solvers that won the portfolio queries so far, engines are set by [`Self::take_wins`].
+    wins: RefCell<Vec<portfolio::Win>>,
+    /// Solver activity, shared with the portfolios.
+    counters: Rc<Counters>,
+}
+impl Pool {
+    /// Constructor.
+    ///
+    /// Solvers are never reused if `!share`, each session spawns its own solver. Sessions run a
+    /// portfolio if `portfolio` is not empty.
+    pub fn new(share: bool, portfolio: Vec<portfolio::Member>) -> Self {
+        Self {
+            share,
+            idle: RefCell::new(vec![]),
+            next_key: Cell::new(0),
+            portfolio,
+            wins: RefCell::new(vec![]),
+            counters: Rc::new(Counters::default()),
+        }
+    }
+
+    /// Solver activity of the sessions so far.
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// True if sessions run a portfolio.
+    pub fn is_portfolio(&self) -> bool {
+        !self.portfolio.is_empty()
+    }
+
+    /// Takes the portfolio wins since the last call, they are attributed to `engine`.
+    pub fn take_wins(&self, engine: &'static str) -> Vec<portfolio::Win> {
+        let mut wins = std::mem::take(&mut *self.wins.borrow_mut());
+        for win in wins.iter_mut() {
+            win.engine = engine
+        }
+        wins
+    }
+
+    /// Fresh key for a system, sessions with the same key share declarations.
+    pub fn new_system(&self) -> usize {
+        let key = self.next_key.get();
+        self.next_key.set(key + 1);
+        key
+    }
+
+    /// A session for a system.
+    ///
+    /// Only spawns a solver with configuration `conf` if no solver in `logic` is available, or if
+    /// the session logs to `tee`. New solvers get a `set-logic` command if `set_logic`.
+    ///
+    /// Portfolio sessions ignore `conf` and `set_logic`, each solver of the portfolio has its own.
+    pub fn session<'s>(
+        &'s self,
+        sys: &'s Sys,
+        key: usize,
+        logic: Logic,
+        set_logic: bool,
+        conf: SmtConf,
+        tee: Option<PathBuf>,
+    ) -> Res<Session<'s>> {
+        let shared = self.share && tee.is_none();
+        let idle = if shared {
+            let mut idle = self.idle.borrow_mut();
+            // Prefer a solver that already knows the system.
+            let pos = idle
+                .iter()
+                .position(|idle| idle.logic == logic && idle.system.map(|(k, _)| k) == Some(key))
+                .or_else(|| idle.iter().rposition(|idle| idle.logic == logic));
+            pos.map(|pos| idle.swap_remove(pos))
+        } else {
+            None
+        };
+
+        let (solver, declared) = match idle {
+            Some(Idle {
+                solver,
+                system: Some((k, declared)),
+                ..
+            }) if k == key => (solver, declared),
+            Some(Idle {
+                mut solver, system, ..
+            }) => {
+                if system.is_some() {
+                    solver
+                        .pop(&self.counters)
+                        .chain_err(|| "while dropping the declarations of a previous system")?
+                }
+                solver.push(&self.counters)?;
+                (solver, 0)
+            }
+            None if self.is_portfolio() => {
+                let solvers = self
+                    .portfolio
+                    .iter()
+                    .map(|member| {
+                        let set_logic = !member.backend.logic_on_cmd_line();
+                        let conf = member.conf(logic)?;
+                        Ok((member.name.clone(), conf, Some(logic).filter(|_| set_logic)))
+                    })
+                    .collect::<Res<Vec<_>>>()?;
+                let mut solver =
+                    Solver::Portfolio(Portfolio::new(solvers, tee, self.counters.clone())?);
+                solver.push(&self.counters)?;
+                (solver, 0)
+            }
+            None => {
+                let mut solver = SFSolver::new(conf, tee)?;
+                crate::interrupt::rsmt2_solver_spawned();
+                self.counters.spawned(false);
+                if set_logic {
+                    self.counters.calls(1);
+                    solver.set_custom_logic(logic.smt_str())?
+                }
+                let mut solver = Solver::Single(solver);
+                solver.push(&self.counters)?;
+                (solver, 0)
+            }
+        };
+
+        Ok(Session {
+            pool: self,
+            solver: Some(solver),
+            sys,
+            vars: sys.decls().all().collect(),
+            logic,
+            key,
+            declared,
+            depth: 0,
+            shared,
+        })
+    }
+}
+
+/// A solver working on a system.
+///
+/// Goes back to its pool when dropped, unless it is not shared or an engine failed while using it.
+pub struct Session<'s> {
+    /// Pool the session comes from.
+    pool: &'s Pool,
+    /// Underlying solver, only `None` when dropped.
+    solver: Option<Solver>,
+    /// System the session works on.
+    sys: &'s Sys,
+    /// Variables of the system.
+    vars: Vec<Var>,
+    /// Logic of the solver.
+    logic: Logic,
+    /// Key of the system.
+    key: usize,
+    /// Number of steps declared, steps `0..self.declared` are declared.
+    declared: Unroll,
+    /// Number of scopes pushed on top of the declarations.
+    depth: usize,
+    /// True if the session goes back to the pool when dropped.
+    shared: bool,
+}
+impl<'s> Drop for Session<'s> {
+    fn drop(&mut self) {
+        if let Some(solver) = self.solver.take() {
+            if self.shared && self.depth == 0 {
+                self.pool.idle.borrow_mut().push(Idle {
+                    solver,
+                    logic: self.logic,
+                    system: Some((self.key, self.declared)),
+                })
+            }
+        }
+    }
+}
+impl<'s> Session<'s> {
+    /// Underlying solver.
+    fn solver(&mut self) -> &mut Solver {
+        self.solver
+            .as_mut()
+            .expect("[unreachable] accessing the solver of a dropped session")
+    }
+
+    /// Pushes a scope.
+    fn push(&mut self) -> Res<()> {
+        let pool = self.pool;
+        self.solver().push(&pool.counters)?;
+        self.depth += 1;
+        Ok(())
+    }
+    /// Pops a scope.
+    fn pop(&mut self) -> Res<()> {
+        let pool = self.pool;
+        self.solver().pop(&pool.counters)?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Asserts an expression at some step.
+    fn assert(&mut self, expr: impl Expr2Smt<Unroll>, step: Unroll) -> Res<()> {
+        let pool = self.pool;
+        self.solver().assert(expr, step, &pool.counters)
+    }
+
+    /// Declares all variables up to some step, if not already declared.
+    fn declare_upto(&mut self, step: Unroll) -> Res<()> {
+        while self.declared <= step {
+            let step = self.declared;
+            let solver = self
+                .solver
+                .as_mut()
+                .expect("[unreachable] accessing the solver of a dropped session");
+            for var in &self.vars {
+                solver
+                    .declare(var, step, &self.pool.counters)
+                    .chain_err(|| format!("while declaring variable `{}@{}`", var, step))?
+            }
+            self.declared += 1
+        }
+        Ok(())
+    }
+
+    /// Asserts the proof objectives that are okay in `res` at some step.
+    fn assert_po_s(&mut self, step: Unroll, res: &CheckRes) -> Res<()> {
+        let sys = self.sys;
+        for (name, po) in sys.po_s() {
+            if res.okay.contains(name) {
+                self.assert(po, step).chain_err(|| {
+                    format!("while asserting candidate `{}` at step {}", name, step)
+                })?
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds falsifications of the proof objectives that are okay in `res` at some step.
+    ///
+    /// Returns `true` if some new falsifications were found.
+    fn find_po_falsifications(&mut self, step: Unroll, res: &mut CheckRes<'s>) -> Res<bool> {
+        let sys = self.sys;
+        let mut changed = false;
+        let to_check: Vec<_> = res.okay.iter().cloned().collect();
+        for name in to_check {
+            let po = sys
+                .po_s()
+                .get(name)
+                .ok_or_else(|| format!("unknown candidate `{}`", name))?;
+            self.push()?;
+            self.assert(po.negated(), step).chain_err(|| {
+                format!(
+                    "while asserting negation of candidate `{}` at step {}",
+                    name, step
+                )
+            })?;
+            if self.check_sat(|| format!("`{}` at step {}", name, step))? {
+                changed = true;
+                self.register_falsification(name, step, res)?
+            }
+            self.pop()?
+        }
+        Ok(changed)
+    }
+
+    /// Checks the current assertions, `query` describes the check for portfolio reports.
+    fn check_sat(&mut self, query: impl FnOnce() -> String) -> Res<bool> {
+        let pool = self.pool;
+        let start = Instant::now();
+        match self.solver() {
+            Solver::Single(solver) => {
+                pool.counters.calls(1);
+                let sat = solver.check_sat()?;
+                pool.counters.check_sat(start.elapsed());
+                Ok(sat)
+            }
+            Solver::Portfolio(portfolio) => {
+                let (sat, winner, time) = portfolio.check_sat()?;
+                pool.counters.check_sat(start.elapsed());
+                pool.wins.borrow_mut().push(portfolio::Win {
+                    engine: "",
+                    query: query(),
+                    winner,
+                    time,
+                });
+                Ok(sat)
+            }
+        }
+    }
+
+    /// Registers the falsification of a candidate at some step, the last check-sat must be `sat`.
+    fn register_falsification(
+        &mut self,
+        name: &'s String,
+        step: Unroll,
+        res: &mut CheckRes<'s>,
+    ) -> Res<()> {
+        let pool = self.pool;
+        match self.solver() {
+            Solver::Single(solver) => {
+                pool.counters.calls(1);
+                res.register_falsification(name, solver)?;
+                // Variables declared for later steps by previous engines are part of the model.
+                if let Some(cex) = res.cexs.get_mut(name) {
+                    let _ = cex.trace.split_off(&(step + 1));
+                }
+            }
+            Solver::Portfolio(portfolio) => {
+                let cex = portfolio.cex(step)?;
+                if res.cexs.insert(name, cex).is_some() || !res.okay.remove(name) {
+                    bail!("trying to register candidate `{}` as falsified twice", name)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether some properties are falsified in the initial states.
+    pub fn base(&mut self) -> Res<BaseRes<'s>> {
+        let sys = self.sys;
+        self.declare_upto(0)?;
+        self.push()?;
+        self.assert(sys.init(), 0)
+            .chain_err(|| "while asserting init predicate at 0")?;
+        let mut res = CheckRes::new(sys);
+        let _ = self.find_po_falsifications(0, &mut res)?;
+        self.pop()?;
+        Ok(res.into())
+    }
+
+    /// Checks whether some properties are not inductive.
+    pub fn step(&mut self) -> Res<StepRes<'s>> {
+        let sys = self.sys;
+        self.declare_upto(1)?;
+        self.push()?;
+        self.assert(sys.trans(), 0)
+            .chain_err(|| "while asserting trans predicate at 0")?;
+
+        let mut res = CheckRes::new(sys);
+        loop {
+            self.push()?;
+            self.assert_po_s(0, &res)?;
+            let new_falsifications = self.find_po_falsifications(1, &mut res)?;
+            self.pop()?;
+            // Try again without the falsified candidates in the induction hypothesis.
+            if !new_falsifications || res.okay.is_empty() {
+                break;
+            }
+        }
+
+        self.pop()?;
+        Ok(res.into())
+    }
+}
+
+/// Bounded model checker over a session.
+pub struct Bmc<'s> {
+    /// Underlying session.
+    session: Session<'s>,
+    /// Number of steps declared by the session before BMC started.
+    declared: Unroll,
+    /// Next step at which the system should be unrolled.
+    ///
+    /// When `self.next_step` is `s`, the system is currently unrolled to `s - 1`.
+    next_step: Unroll,
+    /// Next step to check.
+    check_step: Unroll,
+    /// BMC result.
+    res: BmcRes<'s>,
+}
+impl<'s> Bmc<'s> {
+    /// Constructor.
+    pub fn new(mut session: Session<'s>, res: BmcRes<'s>) -> Res<Self> {
+        let declared = session.declared;
+        // Unrolling declares variables in the BMC scope, they are gone when it is popped.
+        session.push()?;
+        session.declare_upto(0)?;
+        let sys = session.sys;
+        session
+            .assert(sys.init(), 0)
+            .chain_err(|| "while asserting init predicate at 0")?;
+        Ok(Self {
+            session,
+            declared,
+            next_step: 1,
+            check_step: 0,
+            res,
+        })
+    }
+
+    /// Accessor to the BMC result.
+    pub fn res(&self) -> &BmcRes<'s> {
+        &self.res
+    }
+
+    /// True if all POs have been falsified.
+    pub fn is_done(&self) -> bool {
+        self.res.all_falsified()
+    }
+
+    /// The next step to check.
+    pub fn next_check_step(&self) -> Unroll {
+        self.check_step
+    }
+
+    /// Destroys itself to yield the result, the session goes back to its pool.
+    pub fn destroy(mut self) -> Res<BmcRes<'s>> {
+        self.session
+            .pop()
+            .chain_err(|| "while leaving the BMC scope")?;
+        self.session.declared = self.declared;
+        Ok(self.res)
+    }
+
+    /// Unrolls the system up to `step` without checking anything, the next check is at `step`.
+    ///
+    /// Falsifications at earlier steps are not looked for.
+    pub fn skip_to(&mut self, step: Unroll) -> Res<()> {
+        while self.check_step < step {
+            if self.check_step > 0 {
+                self.unroll()?
+            }
+            self.check_step += 1
+        }
+        Ok(())
+    }
+
+    /// Unrolls the system if needed and performs the next check.
+    ///
+    /// Returns `true` if some new falsifications were found.
+    pub fn next_check(&mut self) -> Res<bool> {
+        if self.check_step > 0 {
+            let step = self.next_step;
+            self.unroll()
+                .chain_err(|| format!("while unrolling the system to step {}", step))?;
+        }
+        let step = self.check_step;
+        let res = self
+            .session
+            .find_po_falsifications(step, &mut self.res)
+            .chain_err(|| {
+                if step == 0 {
+                    "while checking for a falsification in the initial state(s)".into()
+                } else {
+                    format!("while checking for a falsification at step {}", step)
+                }
+            })?;
+        self.check_step += 1;
+        Ok(res)
+    }
+
+    /// Unrolls the system one step further.
+    fn unroll(&mut self) -> Res<()> {
+        let step = self.next_step;
+        self.session.declare_upto(step)?;
+        let sys = self.session.sys;
+        self.session
+            .assert(sys.trans(), step - 1)
+            .chain_err(|| format!("while asserting trans predicate at {}", step - 1))?;
+        self.next_step += 1;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    use std::{path::Path, time::Duration};
+
+    use crate::{solver::Backend, stats::Stats};
+
+    /// System with a single candidate, never falsified.
+    const SYS: &str = "svars { n: int } init { n = 0 } trans { 'n = n + 1 } \
+        candidates { \"pos\": n ≥ 0 }";
+
+    /// Fake solver answering `unsat` to all check-sats and `success` to other commands, logs its input to `<dir>/<name>.smt2`.
+    ///
+    /// Commands start with a line starting with `(`, the other lines continue the current command.
+    fn fake_solver(dir: &Path, name: &str) -> (SmtConf, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::create_dir_all(dir).unwrap();
+        let (path, log) = (dir.join(name), dir.join(format!("{}.smt2", name)));
+        let script = format!(
+            "#!/bin/sh\n\
+            while IFS= read -r line; do\n\
+                printf '%s\\n' \"$line\" >> '{}'\n\
+                case \"$line\" in '(check-sat'*) echo unsat ;; '('*) echo success ;; esac\n\
+            done\n",
+            log.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let conf = Backend::Z3.conf(path.to_string_lossy(), None).unwrap();
+        (conf, log)
+    }
+
+    /// Commands logged by a fake solver, waits for the solver to read everything sent so far.
+    fn sent(session: &mut Session, log: &Path) -> Vec<String> {
+        session.check_sat(String::new).unwrap();
+        let log = std::fs::read_to_string(log).unwrap();
+        log.lines().map(str::to_string).collect()
+    }
+
+    /// Number of commands starting with `prefix`.
+    fn count(commands: &[String], prefix: &str) -> usize {
+        commands
+            .iter()
+            .filter(|cmd| cmd.starts_with(prefix))
+            .count()
+    }
+
+    /// Number of solvers spawned by a pool.
+    fn solvers(pool: &Pool) -> usize {
+        Stats::new(Duration::ZERO, vec![], vec![], pool.counters()).solvers
+    }
+
+    #[test]
+    fn reuse() {
+        let dir = std::env::temp_dir().join(format!("mikino-session-{}", std::process::id()));
+        let sys = parse::trans(SYS).unwrap();
+        let logic = Logic::of_sys(&sys);
+        let (conf, log) = fake_solver(&dir, "reuse");
+        let pool = Pool::new(true, vec![]);
+        let key = pool.new_system();
+        let session = || pool.session(&sys, key, logic, true, conf.clone(), None);
+
+        let res = session().unwrap().base().unwrap();
+        assert_eq!(res.okay.len(), 1);
+        let res = session().unwrap().step().unwrap();
+        assert_eq!(res.okay.len(), 1);
+        let mut bmc = Bmc::new(session().unwrap(), CheckRes::new(&sys).into()).unwrap();
+        for _ in 0..4 {
+            assert!(!bmc.next_check().unwrap());
+        }
+        let res = bmc.destroy().unwrap();
+        assert_eq!(res.okay.len(), 1);
+
+        let mut session = session().unwrap();
+        assert_eq!(session.declared, 2);
+        let commands = sent(&mut session, &log);
+        assert_eq!(solvers(&pool), 1);
+        assert_eq!(count(&commands, "(set-logic"), 1);
+        // Steps 0 and 1 are declared once, BMC declares steps 2 and 3 in its own scope.
+        assert_eq!(count(&commands, "(declare-const"), 4);
+        // Only the declarations scope is open.
+        assert_eq!(count(&commands, "(push"), count(&commands, "(pop") + 1);
+        drop(session);
+
+        // Another system in the same logic reuses the solver, but not the declarations.
+        let other = pool.new_system();
+        let mut session = pool
+            .session(&sys, other, logic, true, conf.clone(), None)
+            .unwrap();
+        assert_eq!(session.declared, 0);
+        session.base().unwrap();
+        let commands = sent(&mut session, &log);
+        assert_eq!(solvers(&pool), 1);
+        assert_eq!(count(&commands, "(declare-const"), 5);
+        assert_eq!(count(&commands, "(push"), count(&commands, "(pop") + 1);
+        drop(session);
+
+        // Solvers are not reused in other logics.
+        let session = pool
+            .session(&sys, other, Logic::Lra, true, conf, None)
+            .unwrap();
+        assert_eq!(session.declared, 0);
+        assert_eq!(solvers(&pool), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unbalanced_sessions() {
+        let dir = std::env::temp_dir().join(format!("mikino-unbalanced-{}", std::process::id()));
+        let sys = parse::trans(SYS).unwrap();
+        let logic = Logic::of_sys(&sys);
+        let (conf, _) = fake_solver(&dir, "unbalanced");
+
+        // A session dropped with a scope open does not go back to the pool.
+        let pool = Pool::new(true, vec![]);
+        let key = pool.new_system();
+        let mut session = pool
+            .session(&sys, key, logic, true, conf.clone(), None)
+            .unwrap();
+        session.push().unwrap();
+        drop(session);
+        assert!(pool.idle.borrow().is_empty());
+        let session = pool
+            .session(&sys, key, logic, true, conf.clone(), None)
+            .unwrap();
+        drop(session);
+        assert_eq!(solvers(&pool), 2);
+        assert_eq!(pool.idle.borrow().len(), 1);
+
+        // Sessions are never reused when the pool does not share them.
+        let pool = Pool::new(false, vec![]);
+        for _ in 0..2 {
+            let _ = pool
+                .session(&sys, key, logic, true, conf.clone(), None)
+                .unwrap();
+        }
+        assert_eq!(solvers(&pool), 2);
+        assert!(pool.idle.borrow().is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,383 @@
+//! SMT solver backends.
+//!
+//! Induction, BMC and scripts only need an SMT-LIB 2 solver supporting incrementality and models.
+//! Each backend knows the flags its solver needs for that, and the logic it supports for mikino's
+//! systems. Models are parsed the same way for all solvers, the flags make sure they produce
+//! SMT-LIB 2 models.
+
+mikino_api::prelude!();
+
+use crate::logic::Logic;
+
+/// Prefix of the last argument making mikino run a solver, see [`wrap`].
+///
+/// The file descriptor mikino listens to for process ids and the memory limit in megabytes
+/// follow the prefix, separated by a comma, both are optional. The solver binary is the argument
+/// before.
+pub const WRAPPER_ARG: &str = "--mikino-solver=";
+
+/// SMT solver backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Z3, the default.
+    Z3,
+    /// CVC5.
+    Cvc5,
+    /// Yices 2, through its SMT-LIB 2 frontend.
+    Yices2,
+    /// MathSAT 5.
+    MathSat,
+}
+impl Backend {
+    /// Legal string representations of backends.
+    pub const KEYS: [&'static str; 4] = ["z3", "cvc5", "yices2", "mathsat"];
+
+    /// Backend from a string.
+    pub fn of_str(s: &str) -> Option<Self> {
+        match s {
+            "z3" => Some(Self::Z3),
+            "cvc5" => Some(Self::Cvc5),
+            "yices2" => Some(Self::Yices2),
+            "mathsat" => Some(Self::MathSat),
+            _ => None,
+        }
+    }
+
+    /// Name of the solver.
+    pub fn desc(self) -> &'static str {
+        match self {
+            Self::Z3 => "Z3",
+            Self::Cvc5 => "CVC5",
+            Self::Yices2 => "Yices 2",
+            Self::MathSat => "MathSAT",
+        }
+    }
+
+    /// Default command to run the solver.
+    pub fn default_cmd(self) -> &'static str {
+        match self {
+            Self::Z3 => "z3",
+            Self::Cvc5 => "cvc5",
+            Self::Yices2 => "yices-smt2",
+            Self::MathSat => "mathsat",
+        }
+    }
+
+    /// True if the solver must know the logic on its command line.
+    ///
+    /// Z3, CVC5 and MathSAT get the logic with a `set-logic` command. Yices 2 must know the logic
+    /// upfront, and only supports linear arithmetic when mixing integers and rationals, which is
+    /// the logic it uses when the logic of the problem is unknown, *e.g.* for scripts.
+    pub fn logic_on_cmd_line(self) -> bool {
+        match self {
+            Self::Z3 | Self::Cvc5 | Self::MathSat => false,
+            Self::Yices2 => true,
+        }
+    }
+
+    /// Solver configuration from a command.
+    ///
+    /// The first word of `cmd` is the solver's binary, the remaining words are passed to the
+    /// solver as options, after the ones mikino needs. The `logic` of the problem, if known, only
+    /// matters for solvers that need it on their command line.
+    pub fn conf(self, cmd: impl AsRef<str>, logic: Option<Logic>) -> Res<SmtConf> {
+        let cmd = cmd.as_ref();
+        let mut split_cmd = cmd.split(|c: char| c.is_whitespace());
+        let bin = split_cmd
+            .next()
+            .map(str::trim)
+            .filter(|bin| !bin.is_empty())
+            .ok_or_else(|| format!("illegal {} command `{}`", self.desc(), cmd))?;
+
+        let mut conf = match self {
+            Self::Z3 => z3_cmd_to_conf(bin)?,
+            Self::Cvc5 => {
+                let mut conf = SmtConf::cvc4(bin);
+                conf.models();
+                conf.incremental();
+                conf
+            }
+            Self::Yices2 => {
+                let mut conf = SmtConf::yices_2(bin);
+                conf.models();
+                conf.incremental();
+                conf
+            }
+            Self::MathSat => mathsat_conf(bin),
+        };
+        if self.logic_on_cmd_line() {
+            let logic = logic.unwrap_or(Logic::Lira);
+            conf.option(format!("--logic={}", logic.smt_str()));
+        }
+
+        for opt in split_cmd {
+            let opt = opt.trim();
+            if !opt.is_empty() {
+                conf.option(opt);
+            }
+        }
+
+        Ok(conf)
+    }
+
+    /// Adds some `KEY=VALUE` options and an optional random seed to a configuration.
+    ///
+    /// Options are passed on the solver's command line, so that they apply to every command of the
+    /// session.
+    pub fn add_options(
+        self,
+        conf: &mut SmtConf,
+        opts: &[(String, String)],
+        seed: Option<u64>,
+    ) -> Res<()> {
+        if self == Self::Yices2 && (!opts.is_empty() || seed.is_some()) {
+            bail!(
+                "{} does not support solver options or seeds on its command line",
+                self.desc()
+            )
+        }
+        let seed_opts: &[&str] = match self {
+            Self::Z3 => &["smt.random_seed", "sat.random_seed"],
+            Self::Cvc5 => &["seed"],
+            Self::MathSat => &["random_seed"],
+            Self::Yices2 => &[],
+        };
+        let seed_opts = seed
+            .into_iter()
+            .flat_map(|seed| seed_opts.iter().map(move |key| (*key, seed.to_string())));
+        let opts = opts
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .chain(seed_opts);
+
+        for (key, value) in opts {
+            let opt = match self {
+                Self::Z3 => format!("{}={}", key, value),
+                Self::Cvc5 => format!("--{}={}", key, value),
+                Self::MathSat => format!("-{}={}", key, value),
+                Self::Yices2 => unreachable!("[unreachable] Yices 2 takes no options"),
+            };
+            conf.option(opt);
+        }
+        Ok(())
+    }
+}
+
+/// MathSAT configuration.
+///
+/// `rsmt2` has no MathSAT style, and the flags its styles add for models and incrementality are the
+/// ones of their solver. The configuration thus uses Yices 2's style, the only one with no flags of
+/// its own, without activating models or incrementality. MathSAT is incremental by default, and
+/// gets its own flags to read SMT-LIB 2 and generate models. It prints models as SMT-LIB 2
+/// `define-fun`s, like the other backends.
+fn mathsat_conf(bin: &str) -> SmtConf {
+    let mut conf = SmtConf::yices_2(bin);
+    conf.option("-input=smt2");
+    conf.option("-model_generation=true");
+    conf
+}
+
+/// Makes a configuration run its solver through mikino under a memory limit of `limit` megabytes,
+/// if any.
+///
+/// Solvers are spawned by `rsmt2`, which offers no way to limit their memory. The solver runs
+/// through mikino itself instead, which reports its process id to the interruption handler (see
+/// [`crate::interrupt::pid_fd`]), limits its own memory, and then executes the solver binary, see
+/// [`exec_solver`]. Mikino knows it is running a solver by its last argument, see [`WRAPPER_ARG`].
+///
+/// Without a memory limit, the configuration is left as is.
+pub fn wrap(conf: &mut SmtConf, limit: Option<u64>) -> Res<()> {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    if cfg!(not(unix)) {
+        bail!("memory limits are only supported on unix systems")
+    }
+    let exe = std::env::current_exe()
+        .chain_err(|| "while retrieving mikino's binary")?
+        .to_string_lossy()
+        .to_string();
+    // The configuration splits its command on spaces.
+    if exe.contains(' ') {
+        bail!(
+            "cannot run solvers under a memory limit, path to mikino's binary `{}` contains spaces",
+            exe
+        )
+    }
+    let pid_fd = crate::interrupt::pid_fd().map(|fd| fd.to_string());
+    let bin = conf.get_cmd().to_string();
+    // Words after the first one go after the solver's options, the binary and the wrapper argument
+    // end up last.
+    conf.cmd(format!(
+        "{} {} {}{},{}",
+        exe,
+        bin,
+        WRAPPER_ARG,
+        pid_fd.unwrap_or_default(),
+        limit,
+    ));
+    Ok(())
+}
+
+/// Executes a solver, only returns on errors.
+///
+/// The `spec` is what follows [`WRAPPER_ARG`]: a file descriptor to write mikino's process id to
+/// and a memory limit in megabytes, separated by a comma and both optional. The last of `args` is
+/// the solver binary, the other ones are the solver's options.
+#[cfg(unix)]
+pub fn exec_solver(spec: &str, args: &[std::ffi::OsString]) -> Res<()> {
+    use std::os::unix::{io::FromRawFd, process::CommandExt};
+
+    let (fd, limit) = spec
+        .split_once(',')
+        .ok_or_else(|| format!("illegal solver wrapper argument `{}`", spec))?;
+    let (bin, args) = args.split_last().ok_or("no solver to run")?;
+    if !fd.is_empty() {
+        let fd: libc::c_int = fd
+            .parse()
+            .map_err(|_| format!("illegal file descriptor `{}`", fd))?;
+        let mut pipe = unsafe { std::fs::File::from_raw_fd(fd) };
+        // Executing the solver keeps the process id, the write is atomic and the pipe is closed on
+        // drop.
+        std::io::Write::write_all(&mut pipe, format!("{}\n", std::process::id()).as_bytes())
+            .chain_err(|| "while reporting the solver's process id")?;
+    }
+    if !limit.is_empty() {
+        let limit: u64 = limit
+            .parse()
+            .map_err(|_| format!("illegal memory limit `{}`", limit))?;
+        let bytes = limit.saturating_mul(1024 * 1024) as libc::rlim_t;
+        let rlimit = libc::rlimit {
+            rlim_cur: bytes,
+            rlim_max: bytes,
+        };
+        if unsafe { libc::setrlimit(libc::RLIMIT_AS, &rlimit) } != 0 {
+            bail!(
+                "could not limit memory to {}MB: {}",
+                limit,
+                std::io::Error::last_os_error()
+            )
+        }
+    }
+    let e = std::process::Command::new(bin).args(args).exec();
+    bail!("could not run solver `{}`: {}", bin.to_string_lossy(), e)
+}
+/// Solvers only run through mikino on unix.
+#[cfg(not(unix))]
+pub fn exec_solver(_spec: &str, _args: &[std::ffi::OsString]) -> Res<()> {
+    bail!("solvers only run through mikino on unix systems")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cmd_line(conf: &SmtConf) -> String {
+        let mut res = vec![conf.get_cmd()];
+        res.extend(conf.get_options().iter().map(String::as_str));
+        res.join(" ")
+    }
+
+    #[test]
+    fn flags() {
+        let conf = |backend: Backend, cmd, logic| cmd_line(&backend.conf(cmd, logic).unwrap());
+        assert_eq!(conf(Backend::Z3, "z3", None), "z3 -in -smt2");
+        assert_eq!(
+            conf(Backend::Z3, "/opt/z3  -v:1", Some(Logic::Lia)),
+            "/opt/z3 -in -smt2 -v:1"
+        );
+        assert_eq!(
+            conf(Backend::Cvc5, "cvc5", Some(Logic::Nra)),
+            "cvc5 -q --no-interactive --lang smt2 --produce-models --incremental"
+        );
+        assert_eq!(
+            conf(Backend::MathSat, "mathsat -verbosity=1", Some(Logic::Lra)),
+            "mathsat -input=smt2 -model_generation=true -verbosity=1"
+        );
+        assert!(Backend::Z3.conf("  ", None).is_err());
+    }
+
+    #[test]
+    fn logics() {
+        for backend in [Backend::Z3, Backend::Cvc5, Backend::MathSat] {
+            assert!(!backend.logic_on_cmd_line());
+            let line = cmd_line(
+                &backend
+                    .conf(backend.default_cmd(), Some(Logic::Nia))
+                    .unwrap(),
+            );
+            assert!(!line.contains("logic"), "{}", line);
+        }
+
+        assert!(Backend::Yices2.logic_on_cmd_line());
+        let conf = |logic| cmd_line(&Backend::Yices2.conf("yices-smt2", logic).unwrap());
+        assert_eq!(
+            conf(Some(Logic::Lia)),
+            "yices-smt2 --smt2-model-format --incremental --logic=QF_LIA"
+        );
+        assert_eq!(
+            conf(None),
+            "yices-smt2 --smt2-model-format --incremental --logic=QF_LIRA"
+        );
+    }
+
+    #[test]
+    fn options_and_seeds() {
+        let opts = vec![("timeout".to_string(), "10".to_string())];
+        let line = |backend: Backend, seed| {
+            let mut conf = backend.conf(backend.default_cmd(), None).unwrap();
+            backend.add_options(&mut conf, &opts, seed).unwrap();
+            cmd_line(&conf)
+        };
+        assert_eq!(
+            line(Backend::Z3, Some(7)),
+            "z3 -in -smt2 timeout=10 smt.random_seed=7 sat.random_seed=7"
+        );
+        assert_eq!(
+            line(Backend::Cvc5, Some(7)),
+            "cvc5 -q --no-interactive --lang smt2 --produce-models --incremental --timeout=10 \
+            --seed=7"
+        );
+        assert_eq!(
+            line(Backend::MathSat, None),
+            "mathsat -input=smt2 -model_generation=true -timeout=10"
+        );
+
+        let mut conf = Backend::Yices2.conf("yices-smt2", None).unwrap();
+        assert!(Backend::Yices2.add_options(&mut conf, &[], None).is_ok());
+        assert!(Backend::Yices2.add_options(&mut conf, &opts, None).is_err());
+        assert!(Backend::Yices2
+            .add_options(&mut conf, &[], Some(7))
+            .is_err());
+    }
+
+    #[test]
+    fn keys() {
+        for key in Backend::KEYS {
+            assert!(Backend::of_str(key).is_some(), "{}", key);
+        }
+        assert!(Backend::of_str("yices").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wrapping() {
+        let original = cmd_line(&Backend::Z3.conf("z3", None).unwrap());
+        let mut conf = Backend::Z3.conf("z3", None).unwrap();
+        wrap(&mut conf, None).unwrap();
+        assert_eq!(cmd_line(&conf), original);
+
+        wrap(&mut conf, Some(512)).unwrap();
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(conf.get_cmd(), exe.to_string_lossy());
+        let options = conf.get_options();
+        assert_eq!(options[..options.len() - 2].join(" "), "-in -smt2");
+        assert_eq!(options[options.len() - 2], "z3");
+        // The pipe reporting process ids is only open once mikino handles signals.
+        let spec = options[options.len() - 1]
+            .strip_prefix(WRAPPER_ARG)
+            .unwrap();
+        assert!(spec.ends_with(",512"), "{}", spec);
+    }
+}
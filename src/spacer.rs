@@ -0,0 +1,194 @@
+//! Spacer backend.
+//!
+//! Checks the candidates by sending the system's Horn clause encoding (see
+//! [`export::chc`](crate::export::chc)) to Z3's fixedpoint engine, Spacer. Each candidate is
+//! checked separately so that it gets its own verdict. A proof comes with an inductive invariant,
+//! the interpretation Z3 found for the invariant predicate.
+
+mikino_api::prelude!();
+
+use std::{collections::BTreeMap as Map, path::PathBuf, time::Instant};
+
+use mikino_api::rsmt2::Solver;
+
+use crate::{
+    export::chc::Clauses,
+    import::{sexp, vmt},
+    stats::Counters,
+};
+use expr::{Expr, Var};
+use trans::Sys;
+
+/// Spacer verdict for a candidate.
+#[derive(Debug, Clone)]
+pub enum Verdict {
+    /// Candidate holds in all reachable states, with the invariant found if mikino can represent
+    /// it.
+    Proved(Option<Expr>),
+    /// Candidate is falsifiable.
+    Falsified,
+    /// Spacer gave up.
+    Unknown,
+}
+
+/// Result of a Spacer run.
+#[derive(Debug, Clone)]
+pub struct SpacerRes<'sys> {
+    /// Verdict for each candidate.
+    pub verdicts: Map<&'sys String, Verdict>,
+}
+impl<'sys> SpacerRes<'sys> {
+    /// Candidates proved, with their invariant if any.
+    pub fn proved(&self) -> impl Iterator<Item = (&'sys String, Option<&Expr>)> + '_ {
+        self.verdicts
+            .iter()
+            .filter_map(|(candidate, verdict)| match verdict {
+                Verdict::Proved(inv) => Some((*candidate, inv.as_ref())),
+                Verdict::Falsified | Verdict::Unknown => None,
+            })
+    }
+    /// Candidates falsified.
+    pub fn falsified(&self) -> impl Iterator<Item = &'sys String> + '_ {
+        self.verdicts
+            .iter()
+            .filter(|(_, verdict)| matches!(verdict, Verdict::Falsified))
+            .map(|(candidate, _)| *candidate)
+    }
+    /// Candidates Spacer could not decide.
+    pub fn unknown(&self) -> impl Iterator<Item = &'sys String> + '_ {
+        self.verdicts
+            .iter()
+            .filter(|(_, verdict)| matches!(verdict, Verdict::Unknown))
+            .map(|(candidate, _)| *candidate)
+    }
+
+    /// True if some candidates were proved.
+    pub fn has_proofs(&self) -> bool {
+        self.proved().next().is_some()
+    }
+    /// True if some candidates were falsified.
+    pub fn has_falsifications(&self) -> bool {
+        self.falsified().next().is_some()
+    }
+    /// True if some candidates are undecided.
+    pub fn has_unknowns(&self) -> bool {
+        self.unknown().next().is_some()
+    }
+}
+
+/// Checks the candidates of a system with Spacer.
+///
+/// If `tee` is a directory, the SMT-LIB interaction is logged in `spacer.smt2` there. Solver
+/// activity goes to `counters`.
+pub fn check<'sys>(
+    sys: &'sys Sys,
+    conf: SmtConf,
+    tee: Option<PathBuf>,
+    counters: &Counters,
+) -> Res<SpacerRes<'sys>> {
+    let clauses = Clauses::new(sys)?;
+    let mut solver = Solver::new(conf, ()).chain_err(|| "while spawning Z3 for Spacer")?;
+    crate::interrupt::rsmt2_solver_spawned();
+    counters.spawned(false);
+    if let Some(mut path) = tee {
+        path.push("spacer.smt2");
+        solver
+            .path_tee(&path)
+            .chain_err(|| format!("while setting up SMT log `{}`", path.display()))?
+    }
+
+    let mut verdicts = Map::new();
+    for ((candidate, _), (_, query)) in sys.po_s().iter().zip(clauses.queries.iter()) {
+        let verdict = check_candidate(sys, &mut solver, &clauses, query, counters)
+            .chain_err(|| format!("while checking candidate `{}` with Spacer", candidate))?;
+        verdicts.insert(candidate, verdict);
+    }
+
+    let _ = solver.kill();
+    Ok(SpacerRes { verdicts })
+}
+
+/// Checks the clauses of a single candidate.
+fn check_candidate(
+    sys: &Sys,
+    solver: &mut Solver<()>,
+    clauses: &Clauses,
+    query: &str,
+    counters: &Counters,
+) -> Res<Verdict> {
+    solver.reset()?;
+    solver.set_custom_logic("HORN")?;
+    solver.set_option(":fp.engine", "spacer")?;
+    solver.declare_fun(&clauses.pred, &clauses.sorts, "Bool")?;
+    solver.assert(&clauses.init)?;
+    solver.assert(&clauses.trans)?;
+    solver.assert(query)?;
+    // The seven commands above and the check-sat.
+    counters.calls(8);
+
+    let start = Instant::now();
+    let res = solver.check_sat_or_unk()?;
+    counters.check_sat(start.elapsed());
+    match res {
+        Some(true) => {
+            counters.calls(1);
+            let model = solver.get_model::<String, String, String>()?;
+            let inv = model
+                .into_iter()
+                .find(|(id, _, _, _)| id == &clauses.pred)
+                .and_then(|(_, args, _, body)| invariant(sys, &args, &body));
+            Ok(Verdict::Proved(inv))
+        }
+        Some(false) => Ok(Verdict::Falsified),
+        None => Ok(Verdict::Unknown),
+    }
+}
+
+/// Translates the interpretation of the invariant predicate, if mikino can represent it.
+///
+/// The parameters of the predicate are the state variables, in declaration order.
+fn invariant(sys: &Sys, args: &[(String, String)], body: &str) -> Option<Expr> {
+    let vars: Map<String, Var> = args
+        .iter()
+        .map(|(id, _)| id.clone())
+        .zip(sys.decls().all())
+        .collect();
+    let sexps = sexp::parse(body).ok()?;
+    match sexps.as_slice() {
+        [sexp] => vmt::term(sexp, &vars).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sys() -> Sys {
+        parse::trans(
+            "svars { x: int, b: bool } init { x = 0 } trans { 'x = x + 1 } \
+            candidates { \"pos\": x ≥ 0 }",
+        )
+        .unwrap()
+    }
+
+    fn args() -> Vec<(String, String)> {
+        vec![("A".into(), "Bool".into()), ("B".into(), "Int".into())]
+    }
+
+    #[test]
+    fn invariants() {
+        let sys = sys();
+        let inv = invariant(&sys, &args(), "(and (>= B 0) (or A (not A)))").unwrap();
+        assert_eq!(inv.to_string(), "(and (>= x 0) (or b (not b)))");
+    }
+
+    #[test]
+    fn unsupported_invariants() {
+        let sys = sys();
+        assert!(invariant(&sys, &args(), "(>= (f B) 0)").is_none());
+        assert!(invariant(&sys, &args(), "(>= B 0").is_none());
+        assert!(invariant(&sys, &args(), "true false").is_none());
+        assert!(invariant(&sys, &args(), "(>= C 0)").is_none());
+    }
+}
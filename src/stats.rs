@@ -0,0 +1,107 @@
+//! Run statistics.
+//!
+//! Times are measured by mikino itself. Solver statistics are counted by the sessions, the
+//! portfolio and Spacer as they talk to their solvers, see [`Counters`]: they do not depend on SMT
+//! logs and do not change how the engines run.
+
+mikino_api::prelude!();
+
+use std::{cell::Cell, time::Duration};
+
+use check::cexs::Cex;
+
+/// Solver activity counters, shared by everything that talks to a solver during a run.
+#[derive(Debug, Default)]
+pub struct Counters {
+    /// Number of solver processes spawned.
+    solvers: Cell<usize>,
+    /// Number of solver processes spawned to replace one that exited or was killed.
+    restarts: Cell<usize>,
+    /// Number of commands sent to solver processes.
+    calls: Cell<usize>,
+    /// Number of check-sats.
+    check_sats: Cell<usize>,
+    /// Time spent waiting for check-sat answers.
+    check_sat_time: Cell<Duration>,
+}
+impl Counters {
+    /// Counts a solver process spawned, `restart` if it replaces one that exited or was killed.
+    pub fn spawned(&self, restart: bool) {
+        self.solvers.set(self.solvers.get() + 1);
+        if restart {
+            self.restarts.set(self.restarts.get() + 1)
+        }
+    }
+    /// Counts commands sent to a solver process.
+    pub fn calls(&self, count: usize) {
+        self.calls.set(self.calls.get() + count)
+    }
+    /// Counts a check-sat and the time it took.
+    ///
+    /// Check-sats are queries: a portfolio check-sat sent to several solvers counts as one
+    /// check-sat, but as one call per solver.
+    pub fn check_sat(&self, time: Duration) {
+        self.check_sats.set(self.check_sats.get() + 1);
+        self.check_sat_time.set(self.check_sat_time.get() + time)
+    }
+}
+
+/// Statistics of a check run.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// Time spent parsing the system.
+    pub parse: Duration,
+    /// Time spent in each engine, in the order they ran.
+    ///
+    /// Engine names are also the name of their SMT log, *e.g.* `base` logs in `base.smt2`.
+    pub engines: Vec<(&'static str, Duration)>,
+    /// Time spent at each BMC depth.
+    pub bmc_depths: Vec<(Unroll, Duration)>,
+    /// Number of solver processes spawned.
+    pub solvers: usize,
+    /// Number of commands sent to the solver processes.
+    pub solver_calls: usize,
+    /// Number of check-sats.
+    pub check_sats: usize,
+    /// Time spent waiting for check-sat answers.
+    pub check_sat_time: Duration,
+    /// Number of solver processes spawned to replace one that exited or was killed.
+    pub restarts: usize,
+    /// Number of states of the longest counterexample.
+    pub peak_cex_len: usize,
+}
+impl Stats {
+    /// Constructor, the peak counterexample length is zero until [`Self::count_cexs`] is called.
+    pub fn new(
+        parse: Duration,
+        engines: Vec<(&'static str, Duration)>,
+        bmc_depths: Vec<(Unroll, Duration)>,
+        counters: &Counters,
+    ) -> Self {
+        Self {
+            parse,
+            engines,
+            bmc_depths,
+            solvers: counters.solvers.get(),
+            solver_calls: counters.calls.get(),
+            check_sats: counters.check_sats.get(),
+            check_sat_time: counters.check_sat_time.get(),
+            restarts: counters.restarts.get(),
+            peak_cex_len: 0,
+        }
+    }
+
+    /// Total time of the run.
+    pub fn total(&self) -> Duration {
+        self.engines
+            .iter()
+            .fold(self.parse, |total, (_, time)| total + *time)
+    }
+
+    /// Updates the peak counterexample length.
+    pub fn count_cexs<'a>(&mut self, cexs: impl IntoIterator<Item = &'a Cex>) {
+        for cex in cexs {
+            self.peak_cex_len = std::cmp::max(self.peak_cex_len, cex.trace.len())
+        }
+    }
+}